@@ -0,0 +1,84 @@
+//! Compares allocating short-lived trait objects in an `Arena` against
+//! allocating each one in its own `DynBox`.
+//!
+//! Run with `cargo bench --bench arena`.
+
+use std::{hint::black_box, time::Instant};
+
+use dyntable::{
+	arena::{Arena, ArenaBox},
+	dyntable,
+	DynBox,
+};
+
+#[dyntable]
+trait Node {
+	extern "C" fn eval(&self) -> u64;
+}
+
+struct Constant(u64);
+
+impl Node for Constant {
+	extern "C" fn eval(&self) -> u64 {
+		self.0
+	}
+}
+
+struct Scaled {
+	value: u64,
+	scale: u32,
+}
+
+impl Node for Scaled {
+	extern "C" fn eval(&self) -> u64 {
+		self.value * self.scale as u64
+	}
+}
+
+const FRAMES: u64 = 1000;
+const NODES: u64 = 5000;
+
+fn bench(name: &str, mut frame: impl FnMut() -> u64) {
+	let start = Instant::now();
+	let mut total = 0;
+
+	for _ in 0..FRAMES {
+		total += frame();
+	}
+
+	let elapsed = start.elapsed();
+	black_box(total);
+
+	println!(
+		"{name:>8}: {elapsed:>10.2?} total, {:>6.2} ns/object",
+		elapsed.as_nanos() as f64 / (FRAMES * NODES) as f64,
+	);
+}
+
+fn main() {
+	bench("DynBox", || {
+		let nodes = (0..NODES)
+			.map(|i| match i % 2 {
+				0 => DynBox::<dyn Node>::new(Constant(i)),
+				_ => DynBox::<dyn Node>::new(Scaled { value: i, scale: 2 }),
+			})
+			.collect::<Vec<_>>();
+
+		black_box(&nodes).iter().map(|node| node.eval()).sum()
+	});
+
+	let mut arena = Arena::new();
+	bench("Arena", || {
+		let nodes = (0..NODES)
+			.map(|i| match i % 2 {
+				0 => ArenaBox::<dyn Node>::new_in(Constant(i), &arena),
+				_ => ArenaBox::<dyn Node>::new_in(Scaled { value: i, scale: 2 }, &arena),
+			})
+			.collect::<Vec<_>>();
+
+		let sum = black_box(&nodes).iter().map(|node| node.eval()).sum();
+		drop(nodes);
+		arena.reset();
+		sum
+	});
+}
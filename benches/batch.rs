@@ -0,0 +1,100 @@
+//! Compares updating interleaved trait objects stored in a `DynBatch`
+//! against a `Vec` of `DynBox`es.
+//!
+//! Run with `cargo bench --bench batch`.
+
+use std::{hint::black_box, time::Instant};
+
+use dyntable::{batch::DynBatch, dyntable, DynBox};
+
+#[dyntable]
+trait Entity {
+	extern "C" fn update(&mut self, dt: u32);
+}
+
+struct Walker(u32);
+struct Runner(u64);
+struct Flyer([u32; 4]);
+struct Idle;
+
+impl Entity for Walker {
+	extern "C" fn update(&mut self, dt: u32) {
+		self.0 = self.0.wrapping_add(dt);
+	}
+}
+
+impl Entity for Runner {
+	extern "C" fn update(&mut self, dt: u32) {
+		self.0 = self.0.wrapping_add(dt as u64 * 3);
+	}
+}
+
+impl Entity for Flyer {
+	extern "C" fn update(&mut self, dt: u32) {
+		self.0[(dt % 4) as usize] ^= dt;
+	}
+}
+
+impl Entity for Idle {
+	extern "C" fn update(&mut self, _dt: u32) {}
+}
+
+const FRAMES: u32 = 1000;
+const ENTITIES: u32 = 10000;
+
+fn bench(name: &str, mut frame: impl FnMut(u32)) {
+	let start = Instant::now();
+
+	for dt in 0..FRAMES {
+		frame(black_box(dt));
+	}
+
+	let elapsed = start.elapsed();
+
+	println!(
+		"{name:>8}: {elapsed:>10.2?} total, {:>6.2} ns/call",
+		elapsed.as_nanos() as f64 / (FRAMES * ENTITIES) as f64,
+	);
+}
+
+fn main() {
+	// a fixed pseudo-random type order, to defeat branch prediction
+	let kinds = (0..ENTITIES)
+		.scan(12345u32, |state, _| {
+			*state = state.wrapping_mul(1103515245).wrapping_add(12345);
+			Some((*state >> 16) % 4)
+		})
+		.collect::<Vec<_>>();
+
+	let mut boxes = kinds
+		.iter()
+		.map(|kind| match kind {
+			0 => DynBox::<dyn Entity>::new(Walker(0)),
+			1 => DynBox::<dyn Entity>::new(Runner(0)),
+			2 => DynBox::<dyn Entity>::new(Flyer([0; 4])),
+			_ => DynBox::<dyn Entity>::new(Idle),
+		})
+		.collect::<Vec<_>>();
+
+	bench("DynBox", |dt| {
+		for entity in &mut boxes {
+			DynBox::borrow_mut(entity).update(dt);
+		}
+	});
+
+	let mut batch = DynBatch::<dyn Entity>::new();
+	for kind in &kinds {
+		match kind {
+			0 => batch.push(Walker(0)),
+			1 => batch.push(Runner(0)),
+			2 => batch.push(Flyer([0; 4])),
+			_ => batch.push(Idle),
+		}
+	}
+
+	bench("DynBatch", |dt| {
+		batch.for_each_mut(|mut entity| entity.update(dt));
+	});
+
+	black_box((boxes, batch));
+}
@@ -0,0 +1,453 @@
+//! Generation of C representations of VTables
+
+use proc_macro2::{Group, Ident, TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{ReturnType, Type};
+
+use crate::parse::{
+	abi_name,
+	c_calling_convention,
+	Abi,
+	ConstEntry,
+	DynTraitInfo,
+	MethodEntry,
+	MethodParam,
+	MethodReceiver,
+	ReceiverPinned,
+	ReceiverReference,
+	Subtable,
+	SubtableChildGraph,
+	SubtableEntry,
+	TopLevelSubtable,
+	TraitInfo,
+	VTableEntry,
+	VTableInfo,
+	VTableSlot,
+};
+
+/// Generate an implementation of `CVTable` for the VTable, if enabled
+pub fn gen_cvtable(
+	dyntrait @ DynTraitInfo {
+		dyntrait: TraitInfo { ident, .. },
+		vtable: VTableInfo {
+			name: vtable_ident,
+			generics,
+			..
+		},
+		drop: drop_abi,
+		dealloc: dealloc_abi,
+		header,
+		reserved,
+		c_header,
+		..
+	}: &DynTraitInfo,
+) -> TokenStream {
+	if !c_header {
+		return TokenStream::new()
+	}
+
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	let trait_name = ident.to_string();
+	let c_name = vtable_ident.to_string();
+	let c_ptr_name = format!("{}DynPtr", ident);
+
+	let cpp_trait = gen_cpp_trait(dyntrait);
+
+	let header = header
+		.map(|_| {
+			quote::quote! {
+				::dyntable::cheader::c_field(
+					&<::dyntable::VTableHeader as ::dyntable::cheader::CType>::c_type(header),
+					"__header",
+				)
+			}
+		})
+		.into_iter();
+
+	let slots = dyntrait.slots.iter().map(|slot| match slot {
+		VTableSlot::Drop => {
+			let convention = match drop_abi {
+				Some(Abi::Explicit(abi)) => c_calling_convention(&abi.to_string()).unwrap_or(""),
+				_ => "",
+			};
+
+			quote::quote! {
+				::dyntable::cheader::c_fn_field(
+					"__drop",
+					#convention,
+					"void",
+					&[::dyntable::cheader::c_param("void *", "")],
+				)
+			}
+		},
+		VTableSlot::Layout => quote::quote! {
+			::dyntable::cheader::c_field(
+				&<::dyntable::alloc::MemoryLayout as ::dyntable::cheader::CType>::c_type(header),
+				"__layout",
+			)
+		},
+		VTableSlot::Dealloc => {
+			let convention = match dealloc_abi {
+				Some(Abi::Explicit(abi)) => c_calling_convention(&abi.to_string()).unwrap_or(""),
+				_ => "",
+			};
+
+			quote::quote! {
+				::dyntable::cheader::c_fn_field(
+					"__dealloc",
+					#convention,
+					"void",
+					&[::dyntable::cheader::c_param("void *", "")],
+				)
+			}
+		},
+		VTableSlot::Entry(i) => gen_c_entry(&dyntrait.entries[*i]),
+	});
+
+	let reserved = match reserved {
+		0 => None,
+		n => {
+			let field = format!("void (*__reserved[{n}])(void);");
+			Some(quote::quote! { ::dyntable::__private::String::from(#field) })
+		},
+	}
+	.into_iter();
+
+	quote::quote! {
+		#[allow(non_camel_case_types)]
+		impl #impl_generics ::dyntable::cheader::CVTable for #vtable_ident #ty_generics
+		#where_clause {
+			const TRAIT_NAME: &'static str = #trait_name;
+			const C_NAME: &'static str = #c_name;
+			const C_PTR_NAME: &'static str = #c_ptr_name;
+
+			fn c_fields(
+				header: &mut ::dyntable::cheader::CHeader,
+			) -> ::dyntable::__private::Vec<::dyntable::__private::String> {
+				let mut fields = ::dyntable::__private::Vec::new();
+				#(fields.push(#header);)*
+				#(fields.push(#slots);)*
+				#(fields.push(#reserved);)*
+				fields
+			}
+
+			fn cpp_trait(
+				header: &mut ::dyntable::cheader::CHeader,
+			) -> ::dyntable::cheader::CppTrait {
+				#cpp_trait
+			}
+		}
+	}
+}
+
+/// Generate an expression describing the VTable for C++ class generation
+fn gen_cpp_trait(
+	dyntrait @ DynTraitInfo {
+		drop: drop_abi,
+		embed_layout,
+		dealloc: dealloc_abi,
+		header,
+		reserved,
+		entries,
+		..
+	}: &DynTraitInfo,
+) -> TokenStream {
+	let convention = |abi: &Abi| {
+		let convention = match abi {
+			Abi::Explicit(abi) => c_calling_convention(&abi.to_string()).unwrap_or(""),
+			Abi::ImplicitRust => "",
+		};
+
+		quote::quote! { #convention }
+	};
+	let drop = option_tokens(drop_abi.as_ref().map(convention));
+	let dealloc = option_tokens(dealloc_abi.as_ref().map(convention));
+
+	let header = header.map(|version| {
+		// the header covers the VTable up to the end of its last field
+		let last = match (reserved, dyntrait.slots.last()) {
+			(0, None) => String::from("__header"),
+			(0, Some(VTableSlot::Drop)) => String::from("__drop"),
+			(0, Some(VTableSlot::Layout)) => String::from("__layout"),
+			(0, Some(VTableSlot::Dealloc)) => String::from("__dealloc"),
+			(0, Some(VTableSlot::Entry(i))) => match &entries[*i] {
+				VTableEntry::Subtable(SubtableEntry { ident, .. }) => ident.to_string(),
+				VTableEntry::Method(MethodEntry { field, .. })
+				| VTableEntry::Const(ConstEntry { field, .. }) => field.to_string(),
+			},
+			_ => String::from("__reserved"),
+		};
+
+		quote::quote! { (#version, #last) }
+	});
+	let header = option_tokens(header);
+
+	let cpp_entries = entries.iter().map(gen_cpp_entry);
+
+	let upcasts = entries
+		.iter()
+		.filter_map(|entry| match entry {
+			VTableEntry::Subtable(SubtableEntry {
+				subtable: TopLevelSubtable { subtable, .. },
+				..
+			}) => Some(subtable.flatten_child_graph()),
+			_ => None,
+		})
+		.flatten()
+		.map(
+			|SubtableChildGraph {
+			     parent: Subtable { path: parent, .. },
+			     child: Subtable { path: child, .. },
+			 }| {
+				quote::quote! {
+					(
+						<<(dyn #parent + 'static) as ::dyntable::VTableRepr>::VTable
+							as ::dyntable::cheader::CVTable>::TRAIT_NAME,
+						<<(dyn #child + 'static) as ::dyntable::VTableRepr>::VTable
+							as ::dyntable::cheader::CVTable>::TRAIT_NAME,
+					)
+				}
+			},
+		);
+
+	quote::quote! {
+		::dyntable::cheader::CppTrait {
+			drop: #drop,
+			layout: #embed_layout,
+			dealloc: #dealloc,
+			header: #header,
+			entries: ::dyntable::__private::Vec::from([#(#cpp_entries),*]),
+			upcasts: ::dyntable::__private::Vec::from([#(#upcasts),*]),
+		}
+	}
+}
+
+/// Generate an expression describing a VTable entry for C++ class generation
+fn gen_cpp_entry(entry: &VTableEntry) -> TokenStream {
+	match entry {
+		VTableEntry::Subtable(SubtableEntry {
+			ident,
+			subtable: TopLevelSubtable {
+				ref_token,
+				subtable: Subtable { path, .. },
+			},
+		}) => {
+			let field = ident.to_string();
+			let pointer = ref_token.is_some();
+
+			quote::quote! {
+				::dyntable::cheader::CppEntry::Subtable {
+					field: #field,
+					name: <<(dyn #path + 'static) as ::dyntable::VTableRepr>::VTable
+						as ::dyntable::cheader::CVTable>::TRAIT_NAME,
+					pointer: #pointer,
+				}
+			}
+		},
+		VTableEntry::Method(MethodEntry {
+			abi,
+			ident,
+			receiver,
+			inputs,
+			output,
+			optional,
+			field,
+			..
+		}) => {
+			let name = ident.to_string();
+			let field = field.to_string();
+			let convention = abi
+				.as_ref()
+				.and_then(|abi| c_calling_convention(&abi_name(abi)))
+				.unwrap_or("");
+
+			let receiver = receiver.as_ref().map(|receiver| match receiver {
+				MethodReceiver::Reference(ReceiverReference {
+					mutability: None, ..
+				})
+				| MethodReceiver::Pinned(ReceiverPinned {
+					mutability: None, ..
+				}) => quote::quote! { ::dyntable::cheader::CppReceiver::Ref },
+				MethodReceiver::Reference(_) | MethodReceiver::Pinned(_) => {
+					quote::quote! { ::dyntable::cheader::CppReceiver::Mut }
+				},
+				MethodReceiver::Value(_) => {
+					quote::quote! { ::dyntable::cheader::CppReceiver::Value }
+				},
+			});
+			let receiver = option_tokens(receiver);
+
+			let params = inputs.iter().map(|MethodParam { ident, ty, .. }| {
+				let ty = static_lifetimes(ty);
+				let name = match ident == "_" {
+					true => String::new(),
+					false => ident.to_string(),
+				};
+
+				quote::quote! {
+					(<#ty as ::dyntable::cheader::CType>::c_type(header), #name)
+				}
+			});
+
+			let output = match output {
+				ReturnType::Default => {
+					quote::quote! { ::dyntable::__private::String::from("void") }
+				},
+				ReturnType::Type(_, ty) => {
+					let ty = static_lifetimes(ty);
+					quote::quote! { <#ty as ::dyntable::cheader::CType>::c_type(header) }
+				},
+			};
+
+			quote::quote! {
+				::dyntable::cheader::CppEntry::Method(::dyntable::cheader::CppMethod {
+					name: #name,
+					field: #field,
+					convention: #convention,
+					receiver: #receiver,
+					output: #output,
+					params: ::dyntable::__private::Vec::from([#(#params),*]),
+					optional: #optional,
+				})
+			}
+		},
+		VTableEntry::Const(ConstEntry { ident, field, .. }) => {
+			let name = ident.to_string();
+			let field = field.to_string();
+
+			quote::quote! {
+				::dyntable::cheader::CppEntry::Const {
+					field: #field,
+					name: #name,
+				}
+			}
+		},
+	}
+}
+
+/// Convert an optional expression into an `Option` expression
+fn option_tokens(tokens: Option<TokenStream>) -> TokenStream {
+	match tokens {
+		Some(tokens) => quote::quote! { ::core::option::Option::Some(#tokens) },
+		None => quote::quote! { ::core::option::Option::None },
+	}
+}
+
+/// Generate an expression formatting the C declaration of a VTable entry
+fn gen_c_entry(entry: &VTableEntry) -> TokenStream {
+	match entry {
+		VTableEntry::Subtable(SubtableEntry {
+			ident,
+			subtable: TopLevelSubtable {
+				ref_token,
+				subtable: Subtable { path, .. },
+			},
+		}) => {
+			let name = ident.to_string();
+			let pointer = ref_token.map(|_| " const *").unwrap_or("");
+
+			quote::quote! {
+				::dyntable::cheader::c_field(
+					&(::dyntable::cheader::CHeader::vtable::<
+						<(dyn #path + 'static) as ::dyntable::VTableRepr>::VTable,
+					>(header) + #pointer),
+					#name,
+				)
+			}
+		},
+		VTableEntry::Method(MethodEntry {
+			abi,
+			receiver,
+			inputs,
+			output,
+			field,
+			..
+		}) => {
+			let name = field.to_string();
+			let convention = abi
+				.as_ref()
+				.and_then(|abi| c_calling_convention(&abi_name(abi)))
+				.unwrap_or("");
+
+			let receiver = receiver
+				.as_ref()
+				.map(|_| quote::quote! { ::dyntable::cheader::c_param("void *", "self") })
+				.into_iter();
+
+			let params = inputs.iter().map(|MethodParam { ident, ty, .. }| {
+				let ty = static_lifetimes(ty);
+				let name = match ident == "_" {
+					true => String::new(),
+					false => ident.to_string(),
+				};
+
+				quote::quote! {
+					::dyntable::cheader::c_param(
+						&<#ty as ::dyntable::cheader::CType>::c_type(header),
+						#name,
+					)
+				}
+			});
+
+			let output = match output {
+				ReturnType::Default => {
+					quote::quote! { ::dyntable::__private::String::from("void") }
+				},
+				ReturnType::Type(_, ty) => {
+					let ty = static_lifetimes(ty);
+					quote::quote! { <#ty as ::dyntable::cheader::CType>::c_type(header) }
+				},
+			};
+
+			quote::quote! {
+				::dyntable::cheader::c_fn_field(
+					#name,
+					#convention,
+					&#output,
+					&[#(#receiver,)* #(#params),*],
+				)
+			}
+		},
+		VTableEntry::Const(ConstEntry { ty, field, .. }) => {
+			let name = field.to_string();
+			let ty = static_lifetimes(ty);
+
+			quote::quote! {
+				::dyntable::cheader::c_field(
+					&<#ty as ::dyntable::cheader::CType>::c_type(header),
+					#name,
+				)
+			}
+		},
+	}
+}
+
+/// Replace all lifetimes in a type with `'static`, as method lifetimes
+/// are not in scope in the generated implementation.
+fn static_lifetimes(ty: &Type) -> TokenStream {
+	fn replace(tokens: TokenStream) -> TokenStream {
+		let mut lifetime = false;
+
+		tokens
+			.into_iter()
+			.map(|token| {
+				let token = match token {
+					TokenTree::Group(group) => {
+						let mut new = Group::new(group.delimiter(), replace(group.stream()));
+						new.set_span(group.span());
+						TokenTree::Group(new)
+					},
+					TokenTree::Ident(ident) if lifetime => {
+						TokenTree::Ident(Ident::new("static", ident.span()))
+					},
+					token => token,
+				};
+
+				lifetime = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '\'');
+				token
+			})
+			.collect()
+	}
+
+	replace(ty.to_token_stream())
+}
@@ -0,0 +1,65 @@
+//! Generation of COM interface information
+
+use proc_macro2::TokenStream;
+
+use crate::parse::{
+	ComGuid,
+	DynTraitInfo,
+	Subtable,
+	SubtableEntry,
+	TopLevelSubtable,
+	VTableEntry,
+	VTableInfo,
+};
+
+/// Generate an implementation of `ComInterface` for the VTable, if enabled
+pub fn gen_com_interface(
+	dyntrait @ DynTraitInfo {
+		vtable: VTableInfo {
+			name: vtable_ident, ..
+		},
+		com,
+		..
+	}: &DynTraitInfo,
+) -> TokenStream {
+	let Some(ComGuid {
+		data1,
+		data2,
+		data3,
+		data4,
+	}) = com
+	else {
+		return TokenStream::new()
+	};
+
+	// COM interfaces always have a single parent interface, checked
+	// while parsing
+	let parent = dyntrait.entries.iter().find_map(|entry| match entry {
+		VTableEntry::Subtable(SubtableEntry {
+			subtable: TopLevelSubtable {
+				subtable: Subtable { path, .. },
+				..
+			},
+			..
+		}) => Some(path),
+		_ => None,
+	});
+
+	quote::quote! {
+		unsafe impl ::dyntable::com::ComInterface for #vtable_ident {
+			const IID: ::dyntable::com::Guid = ::dyntable::com::Guid {
+				data1: #data1,
+				data2: #data2,
+				data3: #data3,
+				data4: [#(#data4),*],
+			};
+
+			#[inline]
+			fn supports(iid: &::dyntable::com::Guid) -> bool {
+				*iid == <Self as ::dyntable::com::ComInterface>::IID
+					|| <<(dyn #parent + 'static) as ::dyntable::VTableRepr>::VTable
+						as ::dyntable::com::ComInterface>::supports(iid)
+			}
+		}
+	}
+}
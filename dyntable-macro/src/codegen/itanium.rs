@@ -0,0 +1,24 @@
+//! Generation of Itanium C++ ABI class information
+
+use proc_macro2::TokenStream;
+
+use crate::parse::{DynTraitInfo, ObjectLayout, VTableInfo};
+
+/// Generate an implementation of `CppVTable` for the VTable, if enabled
+pub fn gen_cpp_vtable(
+	DynTraitInfo {
+		vtable: VTableInfo {
+			name: vtable_ident, ..
+		},
+		object,
+		..
+	}: &DynTraitInfo,
+) -> TokenStream {
+	if *object != Some(ObjectLayout::Itanium) {
+		return TokenStream::new()
+	}
+
+	quote::quote! {
+		unsafe impl ::dyntable::itanium::CppVTable for #vtable_ident {}
+	}
+}
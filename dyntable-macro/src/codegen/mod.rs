@@ -6,6 +6,8 @@ use syn::{
 	punctuated::Punctuated,
 	GenericParam,
 	Lifetime,
+	Path,
+	ReturnType,
 	Token,
 	TraitBound,
 	TypeParam,
@@ -13,10 +15,14 @@ use syn::{
 };
 
 use crate::parse::{
+	path_as_disambiguator,
 	DynTraitInfo,
 	MethodEntry,
 	MethodParam,
 	MethodReceiver,
+	ObjectLayout,
+	ReceiverPinned,
+	ReceiverReference,
 	Subtable,
 	SubtableChildGraph,
 	SubtableEntry,
@@ -24,6 +30,9 @@ use crate::parse::{
 	VTableEntry,
 };
 
+mod cheader;
+mod com;
+mod itanium;
 mod vtable;
 
 /// Generate expanded macro code from trait body
@@ -33,11 +42,13 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 	let ident = &dyntrait.dyntrait.ident;
 	let trait_attrs = &dyntrait.dyntrait.attrs;
 	let type_entries = &dyntrait.dyntrait.associated_types;
+	let sized_methods = &dyntrait.dyntrait.sized_methods;
 	let proxy_trait = format_ident!("__DynTrait_{}", dyntrait.dyntrait.ident);
 	let (_, ty_generics, where_clause) = dyntrait.dyntrait.generics.split_for_impl();
 	let trait_generics = &dyntrait.dyntrait.generics;
 	let (vt_impl_generics, vt_ty_generics, _) = dyntrait.vtable.generics.split_for_impl();
 	let trait_vt_ty_generics = &dyntrait.dyntrait.vtable_ty_generics;
+	let as_dyn_ty_generics = dyntrait.dyntrait.vtable_ty_generics.as_disambiguator();
 
 	let type_impl_entries = dyntrait
 		.dyntrait
@@ -56,6 +67,9 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 
 	let vtable_def = vtable::gen_vtable(dyntrait);
 	let vtable_impl = vtable::gen_impl(dyntrait);
+	let c_vtable = cheader::gen_cvtable(dyntrait);
+	let com_interface = com::gen_com_interface(dyntrait);
+	let cpp_vtable = itanium::gen_cpp_vtable(dyntrait);
 
 	let impl_generic_entries = dyntrait
 		.vtable
@@ -165,7 +179,7 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 	};
 
 	let subtable_impls = dyntrait.entries.iter().filter_map(|entry| match entry {
-		VTableEntry::Method(_) => None,
+		VTableEntry::Method(_) | VTableEntry::Const(_) => None,
 		VTableEntry::Subtable(SubtableEntry {
 			ident: subtable_ident,
 			subtable: TopLevelSubtable {
@@ -179,10 +193,12 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 				     parent: Subtable { path: parent, .. },
 				     child: Subtable { path: child, .. },
 				 }| {
+					let child_vtable = subtable_vtable(dyntrait, child);
+
 					quote::quote! {
 						#[allow(non_camel_case_types)]
 						impl #vt_impl_generics
-							::dyntable::SubTable<<(dyn #child + 'static) as ::dyntable::VTableRepr>::VTable>
+							::dyntable::SubTable<#child_vtable>
 						for #vtable_ident #vt_ty_generics
 						#where_clause {
 							#[inline(always)]
@@ -199,6 +215,7 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 			);
 
 			let subtable_path = &subtable.path;
+			let subtable_vtable = subtable_vtable(dyntrait, subtable_path);
 
 			let getter = match ref_token {
 				Some(_) => quote::quote! { unsafe { &*self.#subtable_ident } },
@@ -208,7 +225,7 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 			quote::quote! {
 				#[allow(non_camel_case_types)]
 				impl #vt_impl_generics
-					::dyntable::SubTable<<(dyn #subtable_path + 'static) as ::dyntable::VTableRepr>::VTable>
+					::dyntable::SubTable<#subtable_vtable>
 				for #vtable_ident #vt_ty_generics
 				#where_clause {
 					#[inline(always)]
@@ -228,15 +245,21 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 		.entries
 		.iter()
 		.filter_map(|entry| match entry {
-			VTableEntry::Method(_) => None,
 			VTableEntry::Subtable(x) => Some(x),
+			_ => None,
 		})
 		.flat_map(|entry| entry.subtable.subtable.flatten())
 		.map(|subtable| &subtable.path)
 		.collect::<Vec<_>>();
 
+	let subtable_disambiguators = subtable_paths
+		.iter()
+		.map(|path| path_as_disambiguator(path))
+		.collect::<Vec<_>>();
+
 	let dyn_impl_methods = dyntrait.entries.iter().filter_map(|entry| match entry {
 		VTableEntry::Subtable(_) => None,
+		VTableEntry::Const(_) => None,
 		VTableEntry::Method(MethodEntry {
 			unsafety,
 			abi,
@@ -246,56 +269,294 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 			receiver,
 			inputs,
 			output,
+			default,
+			optional,
+			field,
+			..
 		}) => Some({
 			let (_, fn_ty_generics, fn_where_clause) = generics.split_for_impl();
 
-			let param_list = MethodParam::params_safe(inputs.iter());
-			let arg_list = MethodParam::idents_safe(inputs.iter());
+			let param_list = MethodParam::params_safe(inputs.iter()).collect::<Vec<_>>();
+			let arg_list = MethodParam::idents_safe(inputs.iter()).collect::<Vec<_>>();
+
+			// Static functions are not callable on an instance, only through the
+			// VTable, and are stubbed out the same as sized methods.
+			let Some(receiver) = receiver else {
+				return Some(quote::quote! {
+					#[inline(always)]
+					#[allow(unused_variables)]
+					#unsafety #abi #fn_token #fn_ident #fn_ty_generics (#(#param_list),*) #output
+					where
+						Self: ::core::marker::Sized,
+					{
+						let () = ::dyntable::__private::SizedOnly::<Self>::ERROR;
+						::core::unreachable!()
+					}
+				})
+			};
+
+			// reference to the container the VTable is read from, before and
+			// after the receiver is prepared
+			let (entry_ref, self_ref) = match receiver {
+				MethodReceiver::Reference(_) => (quote::quote! { self }, quote::quote! { self }),
+				MethodReceiver::Pinned(_) => (quote::quote! { &*self }, quote::quote! { __dyn_self }),
+				MethodReceiver::Value(_) => (quote::quote! { &self }, quote::quote! { &self }),
+			};
+
+			let vtable_entry = |container: &TokenStream| {
+				quote::quote! {
+					::dyntable::SubTable::<
+						<(dyn #ident #trait_vt_ty_generics + 'static) as ::dyntable::VTableRepr>::VTable,
+					>::subtable(&*::dyntable::AsDyn::<(dyn #ident #as_dyn_ty_generics + 'static)>::dyn_vtable(#container)).#field
+				}
+			};
+
+			// optional entries are read from the VTable before being called
+			let vtable_fn = match optional {
+				true => quote::quote! { __dyn_fn },
+				false => {
+					let entry = vtable_entry(&self_ref);
+					quote::quote! { (#entry) }
+				},
+			};
+
+			// only containers with exclusive access to their target may mutate it
+			let unique_check = match receiver {
+				MethodReceiver::Reference(ReceiverReference {
+					mutability: Some(_),
+					..
+				})
+				| MethodReceiver::Pinned(ReceiverPinned {
+					mutability: Some(_),
+					..
+				}) => Some(quote::quote! {
+					let () = ::dyntable::__private::UniqueOnly::<
+						Self,
+						(dyn #ident #as_dyn_ty_generics + 'static),
+					>::ERROR;
+				}),
+				_ => None,
+			};
 
 			let code = match receiver {
 				MethodReceiver::Reference(_) => quote::quote! {
-					(::dyntable::SubTable::<
-						<(dyn #ident #trait_vt_ty_generics + 'static) as ::dyntable::VTableRepr>::VTable,
-					>::subtable(&*::dyntable::AsDyn::<(dyn #ident #trait_vt_ty_generics + 'static)>::dyn_vtable(self)).#fn_ident)(
-						::dyntable::DynSelf::from_raw(::dyntable::AsDyn::<(dyn #ident #trait_vt_ty_generics + 'static)>::dyn_ptr(self)),
+					#unique_check
+					#vtable_fn(
+						::dyntable::DynSelf::from_raw(::dyntable::AsDyn::<(dyn #ident #as_dyn_ty_generics + 'static)>::dyn_ptr(#self_ref)),
 						#(#arg_list),*
 					)
 				},
+				MethodReceiver::Pinned(ReceiverPinned { mutability, .. }) => {
+					let unpin = match mutability {
+						Some(_) => quote::quote! { ::core::pin::Pin::get_unchecked_mut(self) },
+						None => quote::quote! { ::core::pin::Pin::get_ref(self) },
+					};
+
+					quote::quote! {
+						// only containers that pin their target may be unpinned
+						let () = ::dyntable::__private::PinnedOnly::<
+							Self,
+							(dyn #ident #as_dyn_ty_generics + 'static),
+						>::ERROR;
+						#unique_check
+						let __dyn_self = #unpin;
+
+						#vtable_fn(
+							::dyntable::DynSelf::from_raw(::dyntable::AsDyn::<(dyn #ident #as_dyn_ty_generics + 'static)>::dyn_ptr(#self_ref)),
+							#(#arg_list),*
+						)
+					}
+				},
 				MethodReceiver::Value(_) => quote::quote! {
+					// only containers owning their target may move it out
+					let () = ::dyntable::__private::OwnedOnly::<
+						Self,
+						(dyn #ident #as_dyn_ty_generics + 'static),
+					>::ERROR;
+
 					// call the function, the function will consider the pointer
 					// to be by value
-					let __dyn_result = (::dyntable::SubTable::<
-						<(dyn #ident #trait_vt_ty_generics + 'static) as ::dyntable::VTableRepr>::VTable,
-					>::subtable(&*::dyntable::AsDyn::<(dyn #ident #trait_vt_ty_generics + 'static)>::dyn_vtable(&self)).#fn_ident)(
-						::dyntable::AsDyn::<(dyn #ident #trait_vt_ty_generics + 'static)>::dyn_ptr(&self),
+					let __dyn_result = #vtable_fn(
+						::dyntable::AsDyn::<(dyn #ident #as_dyn_ty_generics + 'static)>::dyn_ptr(#self_ref),
 						#(#arg_list),*
 					);
 					// deallocate the pointer without dropping it
-					::dyntable::AsDyn::<(dyn #ident #trait_vt_ty_generics + 'static)>::dyn_dealloc(self);
+					::dyntable::AsDyn::<(dyn #ident #as_dyn_ty_generics + 'static)>::dyn_dealloc(self);
 					__dyn_result
 				},
 			};
 
-			quote::quote! {
-				#[inline(always)]
-				#unsafety #abi #fn_token #fn_ident #fn_ty_generics (#receiver, #(#param_list),*) #output
-				#fn_where_clause {
-					unsafe { #code }
-				}
+			match optional {
+				false => quote::quote! {
+					#[inline(always)]
+					#unsafety #abi #fn_token #fn_ident #fn_ty_generics (#receiver, #(#param_list),*) #output
+					#fn_where_clause {
+						unsafe { #code }
+					}
+				},
+				true => {
+					let entry = vtable_entry(&entry_ref);
+					let try_ident = format_ident!("try_{}", fn_ident);
+					let try_output = option_output(output);
+
+					// A missing entry falls back to the trait's default implementation,
+					// which is called on the container. Optional methods always have
+					// a default body, which is checked while parsing.
+					let bindings = inputs
+						.iter()
+						.zip(MethodParam::idents_safe(inputs.iter()))
+						.filter(|(param, _)| param.ident != "_")
+						.map(|(MethodParam { ident, .. }, arg)| quote::quote! { let #ident = #arg; });
+
+					let fallback = quote::quote! {{
+						#(#bindings)*
+						#default
+					}};
+
+					quote::quote! {
+						#[inline(always)]
+						#unsafety #abi #fn_token #fn_ident #fn_ty_generics (#receiver, #(#param_list),*) #output
+						#fn_where_clause {
+							match unsafe { #entry } {
+								::core::option::Option::Some(__dyn_fn) => unsafe { #code },
+								::core::option::Option::None => #fallback,
+							}
+						}
+
+						#[inline(always)]
+						#unsafety #fn_token #try_ident #fn_ty_generics (#receiver, #(#param_list),*) #try_output
+						where
+							Self: ::core::marker::Sized,
+						{
+							match unsafe { #entry } {
+								::core::option::Option::Some(__dyn_fn) => {
+									::core::option::Option::Some(unsafe { #code })
+								},
+								::core::option::Option::None => ::core::option::Option::None,
+							}
+						}
+					}
+				},
 			}
 		}),
 	});
 
+	// Optional methods have a `try_` counterpart returning `None` when the
+	// method is not present. Rust implementations always provide the method.
+	let optional_trait_entries = dyntrait
+		.entries
+		.iter()
+		.filter_map(|entry| match entry {
+			VTableEntry::Method(
+				method @ MethodEntry {
+					optional: true,
+					receiver: Some(_),
+					..
+				},
+			) => Some(method),
+			_ => None,
+		})
+		.map(
+			|MethodEntry {
+			     unsafety,
+			     fn_token,
+			     ident: fn_ident,
+			     generics,
+			     receiver,
+			     inputs,
+			     output,
+			     ..
+			 }| {
+				let (_, fn_ty_generics, _) = generics.split_for_impl();
+				let try_ident = format_ident!("try_{}", fn_ident);
+				let try_output = option_output(output);
+				let param_list = MethodParam::params_safe(inputs.iter());
+				let arg_list = MethodParam::idents_safe(inputs.iter());
+				let doc = format!(
+					"Calls [`{ident}::{fn_ident}`], returning `None` if the method is not \
+					 present in the object's VTable.\n\n\
+					 Rust implementations of [`{ident}`] always provide the method."
+				);
+
+				quote::quote! {
+					#[doc = #doc]
+					#[inline(always)]
+					#unsafety #fn_token #try_ident #fn_ty_generics (#receiver, #(#param_list),*) #try_output
+					where
+						Self: ::core::marker::Sized,
+					{
+						::core::option::Option::Some(#unsafety {
+							<Self as #ident #ty_generics>::#fn_ident(self, #(#arg_list),*)
+						})
+					}
+				}
+			},
+		);
+
+	// `where Self: Sized` methods have no VTable entry, and are stubbed
+	// out to fail compilation if called through a container.
+	let sized_impl_methods = sized_methods.iter().map(|method| {
+		let sig = &method.sig;
+
+		quote::quote! {
+			#[inline(always)]
+			#[allow(unused_variables)]
+			#sig {
+				let () = ::dyntable::__private::SizedOnly::<Self>::ERROR;
+				::core::unreachable!()
+			}
+		}
+	});
+
+	// Associated constants cannot be part of the trait itself, as the trait
+	// would no longer be object safe. They are split into a seperate trait,
+	// implemented alongside the dyntrait, and their values are copied into
+	// the VTable.
+	let consts_trait = {
+		let consts = dyntrait
+			.entries
+			.iter()
+			.filter_map(|entry| match entry {
+				VTableEntry::Const(entry) => Some(entry),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+
+		match consts.is_empty() {
+			true => None,
+			false => Some({
+				let consts_ident = &dyntrait.dyntrait.consts_ident;
+				let doc = format!(
+					"Associated constants of [`{ident}`], stored in its VTable.\n\n\
+					 Types must implement this trait in addition to [`{ident}`] to \
+					 be used as a `dyn {ident}`."
+				);
+
+				quote::quote! {
+					#[doc = #doc]
+					#vis trait #consts_ident #trait_generics: #ident #ty_generics
+					#where_clause {
+						#(#consts)*
+					}
+				}
+			}),
+		}
+	};
+
 	quote::quote! {
 		#(#trait_attrs)*
 		#vis trait #ident #trait_generics #(: #trait_bounds)*
 		#where_clause {
 			#(#type_entries)*
 			#(#trait_entries)*
+			#(#optional_trait_entries)*
+			#(#sized_methods)*
 		}
 
 		#vtable_def
 
+		#consts_trait
+
 		#vtable_bound_trait
 
 		#[allow(non_camel_case_types)]
@@ -356,6 +617,12 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 
 		#vtable_impl
 
+		#c_vtable
+
+		#com_interface
+
+		#cpp_vtable
+
 		#[allow(non_camel_case_types)]
 		impl<
 			#(#impl_generic_entries,)*
@@ -364,8 +631,8 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 		> #ident #ty_generics for __AsDyn
 		where
 			#(#where_predicates,)*
-			__AsDyn: ::dyntable::AsDyn<dyn #ident #trait_vt_ty_generics, Repr = __DynRepr>
-				#(+ ::dyntable::AsDyn<(dyn #subtable_paths + 'static), Repr = __DynRepr>)*
+			__AsDyn: ::dyntable::AsDyn<dyn #ident #as_dyn_ty_generics, Repr = __DynRepr>
+				#(+ ::dyntable::AsDyn<(dyn #subtable_disambiguators + 'static), Repr = __DynRepr>)*
 				#(+ #as_dyn_bounds)*,
 			__DynRepr: ::dyntable::VTableRepr + ?::core::marker::Sized + #ident #trait_vt_ty_generics,
 			<__DynRepr as ::dyntable::VTableRepr>::VTable: ::dyntable::SubTable<#vtable_ident #vt_ty_generics>
@@ -374,6 +641,43 @@ pub fn codegen(dyntrait: &DynTraitInfo) -> TokenStream {
 		{
 			#(#type_impl_entries)*
 			#(#dyn_impl_methods)*
+			#(#sized_impl_methods)*
 		}
 	}
 }
+
+/// Wrap a method's return type in an `Option`
+fn option_output(output: &ReturnType) -> TokenStream {
+	match output {
+		ReturnType::Default => quote::quote! { -> ::core::option::Option<()> },
+		ReturnType::Type(arrow, ty) => quote::quote! { #arrow ::core::option::Option<#ty> },
+	}
+}
+
+/// The VTable type of a subtable, as used in the header of a `SubTable`
+/// implementation.
+///
+/// Coherence cannot see through a `VTableRepr` projection implemented in
+/// another crate, such as the one for `dyn IUnknown`, so the parents of
+/// objects are named directly by their default VTable name instead.
+fn subtable_vtable(dyntrait: &DynTraitInfo, path: &Path) -> TokenStream {
+	match dyntrait.object {
+		Some(_) => {
+			let mut path = path.clone();
+			if let Some(segment) = path.segments.last_mut() {
+				segment.ident = format_ident!("{}VTable", segment.ident);
+			}
+			path.to_token_stream()
+		},
+		None => quote::quote! { <(dyn #path + 'static) as ::dyntable::VTableRepr>::VTable },
+	}
+}
+
+/// The type of the object holding values of the trait, if the VTable is
+/// referenced through a thin pointer to the object
+fn object_type(dyntrait: &DynTraitInfo) -> Option<TokenStream> {
+	dyntrait.object.map(|object| match object {
+		ObjectLayout::Com => quote::quote! { ::dyntable::com::ComObject },
+		ObjectLayout::Itanium => quote::quote! { ::dyntable::itanium::CppObject },
+	})
+}
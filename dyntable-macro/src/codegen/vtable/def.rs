@@ -12,16 +12,19 @@ use syn::{
 };
 
 use crate::parse::{
+	ConstEntry,
 	DynTraitInfo,
 	MethodEntry,
 	MethodParam,
 	MethodReceiver,
+	ReceiverPinned,
 	ReceiverReference,
 	Subtable,
 	SubtableEntry,
 	TopLevelSubtable,
 	VTableEntry,
 	VTableInfo,
+	VTableSlot,
 };
 
 pub fn gen_vtable(
@@ -33,26 +36,64 @@ pub fn gen_vtable(
 			generics,
 		},
 		drop: drop_abi,
-		embed_layout,
+		dealloc: dealloc_abi,
+		header,
+		reserved,
 		..
 	}: &DynTraitInfo,
 ) -> TokenStream {
-	let (impl_generics, _, where_clause) = generics.split_for_impl();
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 	let repr = repr.as_repr();
 
-	let drop_abi = drop_abi.as_ref().map(|abi| abi.as_abi()).into_iter();
+	let drop_abi = drop_abi.as_ref().map(|abi| abi.as_abi());
+	let dealloc_abi = dealloc_abi.as_ref().map(|abi| abi.as_abi());
 
-	let entries = dyntrait
+	let fields = dyntrait.slots.iter().map(|slot| match slot {
+		VTableSlot::Drop => quote::quote! {
+			#vis __drop: unsafe #drop_abi fn(*mut ::core::ffi::c_void)
+		},
+		VTableSlot::Layout => quote::quote! {
+			#vis __layout: ::dyntable::alloc::MemoryLayout
+		},
+		VTableSlot::Dealloc => quote::quote! {
+			#vis __dealloc: unsafe #dealloc_abi fn(*mut ::core::ffi::c_void)
+		},
+		VTableSlot::Entry(i) => gen_vtable_entry(dyntrait, &dyntrait.entries[*i]),
+	});
+
+	let static_fns = dyntrait
 		.entries
 		.iter()
-		.map(|entry| gen_vtable_entry(dyntrait, entry));
+		.filter_map(|entry| match entry {
+			VTableEntry::Method(method @ MethodEntry { receiver: None, .. }) => {
+				Some(gen_static_caller(dyntrait, method))
+			},
+			_ => None,
+		})
+		.collect::<Vec<_>>();
 
-	let embed_layout = match embed_layout {
-		true => Some(TokenStream::new()),
-		false => None,
-	}
-	.into_iter()
-	.collect::<Vec<_>>();
+	// Static functions are called through the VTable directly, as there is
+	// no instance to call them on.
+	let static_impl = match static_fns.is_empty() {
+		true => None,
+		false => Some(quote::quote! {
+			#[allow(non_camel_case_types)]
+			impl #impl_generics #vtable_ident #ty_generics
+			#where_clause {
+				#(#static_fns)*
+			}
+		}),
+	};
+
+	let header = header.map(|_| TokenStream::new()).into_iter();
+
+	// reserved slots are taken by methods added in later versions
+	let reserved = match reserved {
+		0 => None,
+		n => Some(quote::quote! {
+			#vis __reserved: [::core::option::Option<unsafe extern "C" fn()>; #n],
+		}),
+	};
 
 	let vtable_phantom_generics = {
 		let generics = generics
@@ -79,12 +120,15 @@ pub fn gen_vtable(
 		#repr
 		#vis struct #vtable_ident #impl_generics
 		#where_clause {
-			#(#vis __drop: unsafe #drop_abi fn(*mut ::core::ffi::c_void),)*
-			// embed_layout is a marker and generates no code
-			#(#vis __layout: ::dyntable::alloc::MemoryLayout, #embed_layout)*
-			#(#entries,)*
+			// the header must be the first field, so it can be read from
+			// VTables of any version
+			#(#vis __header: ::dyntable::VTableHeader, #header)*
+			#(#fields,)*
+			#reserved
 			#vis __generics: ::core::marker::PhantomData<#vtable_phantom_generics>,
 		}
+
+		#static_impl
 	}
 }
 
@@ -104,6 +148,15 @@ fn gen_vtable_entry(dyntrait: &DynTraitInfo, entry: &VTableEntry) -> TokenStream
 			}
 		},
 		VTableEntry::Method(method) => gen_vtable_method(dyntrait, method),
+		VTableEntry::Const(ConstEntry { field, ty, .. }) => {
+			let vis = &dyntrait.vis;
+			let mut ty = ty.clone();
+			visit_type_paths(&mut ty, &mut fix_vtable_associated_types);
+
+			quote::quote! {
+				#vis #field: #ty
+			}
+		},
 	}
 }
 
@@ -113,11 +166,13 @@ fn gen_vtable_method(
 		unsafety,
 		abi,
 		fn_token,
-		ident,
 		generics,
 		receiver,
 		inputs,
 		output,
+		optional,
+		field,
+		..
 	}: &MethodEntry,
 ) -> TokenStream {
 	let inputs = inputs.iter().map(|MethodParam { ty, .. }| {
@@ -135,9 +190,12 @@ fn gen_vtable_method(
 		}),
 	};
 
-	let self_ptr = match receiver {
+	let self_ptr = receiver.as_ref().map(|receiver| match receiver {
 		MethodReceiver::Reference(ReceiverReference {
 			reference: (_, lt), ..
+		})
+		| MethodReceiver::Pinned(ReceiverPinned {
+			reference: (_, lt), ..
 		}) => {
 			let lt = lt.into_iter();
 			quote::quote! { ::dyntable::DynSelf #(<#lt>)* }
@@ -145,7 +203,8 @@ fn gen_vtable_method(
 		MethodReceiver::Value(_) => {
 			quote::quote! { *mut ::core::ffi::c_void }
 		},
-	};
+	})
+	.into_iter();
 
 	let declared_lifetimes = generics
 		.params
@@ -167,11 +226,94 @@ fn gen_vtable_method(
 		false => quote::quote! { for<#declared_lifetimes> },
 	};
 
-	quote::quote! {
-		#vis #ident: #for_tok #unsafety #abi #fn_token (
-			#self_ptr,
+	let fn_ptr = quote::quote! {
+		#for_tok #unsafety #abi #fn_token (
+			#(#self_ptr,)*
 			#(#inputs),*
 		) #output
+	};
+
+	match optional {
+		// `Option<fn>` is guaranteed to be represented as a nullable pointer
+		true => quote::quote! { #vis #field: ::core::option::Option<#fn_ptr> },
+		false => quote::quote! { #vis #field: #fn_ptr },
+	}
+}
+
+/// Generate a method on the VTable calling a static function entry
+fn gen_static_caller(
+	DynTraitInfo { vis, .. }: &DynTraitInfo,
+	MethodEntry {
+		attrs,
+		unsafety,
+		ident,
+		generics,
+		inputs,
+		output,
+		optional,
+		field,
+		..
+	}: &MethodEntry,
+) -> TokenStream {
+	let (fn_generics, ..) = generics.split_for_impl();
+	let docs = attrs.iter().filter(|attr| attr.path().is_ident("doc"));
+
+	let inputs = inputs
+		.iter()
+		.map(|param| {
+			let mut ty = param.ty.clone();
+			visit_type_paths(&mut ty, &mut fix_vtable_associated_types);
+
+			MethodParam {
+				ident: param.ident.clone(),
+				colon_token: param.colon_token,
+				ty,
+			}
+		})
+		.collect::<Vec<_>>();
+
+	let param_list = MethodParam::params_safe(inputs.iter());
+	let arg_list = MethodParam::idents_safe(inputs.iter());
+
+	let output = match output {
+		syn::ReturnType::Default => syn::ReturnType::Default,
+		syn::ReturnType::Type(arrow, ty) => syn::ReturnType::Type(*arrow, {
+			let mut ty = ty.clone();
+			visit_type_paths(&mut ty, &mut fix_vtable_associated_types);
+			ty
+		}),
+	};
+
+	// optional static functions return `None` if they are not present
+	match optional {
+		true => {
+			let output = match output {
+				syn::ReturnType::Default => quote::quote! { -> ::core::option::Option<()> },
+				syn::ReturnType::Type(arrow, ty) => {
+					quote::quote! { #arrow ::core::option::Option<#ty> }
+				},
+			};
+
+			quote::quote! {
+				#(#docs)*
+				#[inline(always)]
+				#vis #unsafety fn #ident #fn_generics (&self, #(#param_list),*) #output {
+					match self.#field {
+						::core::option::Option::Some(f) => {
+							::core::option::Option::Some(#unsafety { f(#(#arg_list),*) })
+						},
+						::core::option::Option::None => ::core::option::Option::None,
+					}
+				}
+			}
+		},
+		false => quote::quote! {
+			#(#docs)*
+			#[inline(always)]
+			#vis #unsafety fn #ident #fn_generics (&self, #(#param_list),*) #output {
+				#unsafety { (self.#field)(#(#arg_list),*) }
+			}
+		},
 	}
 }
 
@@ -12,11 +12,16 @@ use syn::{
 };
 
 use super::def::{fix_vtable_associated_types, visit_type_paths};
+use crate::codegen::object_type;
 use crate::parse::{
+	ConstEntry,
 	DynTraitInfo,
 	MethodEntry,
 	MethodParam,
 	MethodReceiver,
+	ObjectLayout,
+	ReceiverPinned,
+	ReceiverReference,
 	Subtable,
 	SubtableEntry,
 	TopLevelSubtable,
@@ -39,10 +44,22 @@ pub fn gen_impl(
 		},
 		drop: drop_abi,
 		embed_layout,
+		dealloc: dealloc_abi,
+		header,
+		reserved,
 		..
 	}: &DynTraitInfo,
 ) -> TokenStream {
 	let proxy_trait = format_ident!("__DynTrait_{}", dyntrait.dyntrait.ident);
+
+	// COM interfaces and C++ classes are implemented by the object holding
+	// the value, as the object is the target of the `this` pointer.
+	let object = object_type(dyntrait);
+	let impl_target = match &object {
+		Some(object) => quote::quote! { #object<__DynTarget> },
+		None => quote::quote! { __DynTarget },
+	};
+
 	let (impl_generics, ty_generics, where_clause) = vtable_generics.split_for_impl();
 
 	let impl_vt_generic_entries = vtable_generics
@@ -96,9 +113,49 @@ pub fn gen_impl(
 				>>::VTABLE
 			},
 		},
-		VTableEntry::Method(method) => gen_method_entry(dyntrait, method),
+		VTableEntry::Method(method) => gen_method_entry(dyntrait, method, object.as_ref()),
+		VTableEntry::Const(ConstEntry {
+			ident: const_ident,
+			field,
+			..
+		}) => {
+			let consts_ident = &dyntrait.dyntrait.consts_ident;
+			let (_, trait_ty_generics, _) = dyntrait.dyntrait.generics.split_for_impl();
+
+			quote::quote! {
+				#field: <Self as #consts_ident #trait_ty_generics>::#const_ident
+			}
+		},
+	});
+
+	// Subtables are created from their own `DynTrait` implementation, which may
+	// require more than the trait bound itself (such as a constants trait).
+	let subtable_bounds = dyntrait.entries.iter().filter_map(|entry| match entry {
+		VTableEntry::Subtable(SubtableEntry {
+			subtable: TopLevelSubtable {
+				subtable: Subtable { path, .. },
+				..
+			},
+			..
+		}) => Some(quote::quote! {
+			#impl_target: ::dyntable::DynTrait<
+				'__dyn_vtable,
+				<(dyn #path + 'static) as ::dyntable::VTableRepr>::VTable,
+			>
+		}),
+		_ => None,
 	});
 
+	let consts_bound = dyntrait
+		.entries
+		.iter()
+		.any(|entry| matches!(entry, VTableEntry::Const(_)))
+		.then(|| {
+			let consts_ident = &dyntrait.dyntrait.consts_ident;
+			let (_, trait_ty_generics, _) = dyntrait.dyntrait.generics.split_for_impl();
+			quote::quote! { + #consts_ident #trait_ty_generics }
+		});
+
 	let (drop_marker, drop_abi) = match drop_abi.as_ref() {
 		Some(drop_abi) => (
 			Some(TokenStream::new()).into_iter(),
@@ -107,6 +164,14 @@ pub fn gen_impl(
 		None => (None.into_iter(), None.into_iter()),
 	};
 
+	let (dealloc_marker, dealloc_abi) = match dealloc_abi.as_ref() {
+		Some(dealloc_abi) => (
+			Some(TokenStream::new()).into_iter(),
+			Some(dealloc_abi.as_abi()).into_iter(),
+		),
+		None => (None.into_iter(), None.into_iter()),
+	};
+
 	let embed_layout = match embed_layout {
 		true => Some(TokenStream::new()),
 		false => None,
@@ -114,6 +179,33 @@ pub fn gen_impl(
 	.into_iter()
 	.collect::<Vec<_>>();
 
+	let (header_marker, header_version) = match header {
+		Some(version) => (Some(TokenStream::new()).into_iter(), Some(version).into_iter()),
+		None => (None.into_iter(), None.into_iter()),
+	};
+
+	let static_vtable = quote::quote! {
+		<Self as #proxy_trait<'__dyn_vtable, #vtable_ident #ty_generics>>::VTABLE
+	};
+
+	// C++ VTables are preceded by the offset to the top of the object
+	// and RTTI entries
+	let static_vtable = match dyntrait.object {
+		Some(ObjectLayout::Itanium) => quote::quote! {
+			&::dyntable::itanium::PrefixedVTable {
+				prefix: ::dyntable::itanium::VTablePrefix::NO_RTTI,
+				vtable: #static_vtable,
+			}
+			.vtable
+		},
+		_ => quote::quote! { &#static_vtable },
+	};
+
+	let reserved = match reserved {
+		0 => None,
+		n => Some(quote::quote! { __reserved: [::core::option::Option::None; #n], }),
+	};
+
 	quote::quote! {
 		#[allow(non_camel_case_types)]
 		unsafe impl<
@@ -121,14 +213,15 @@ pub fn gen_impl(
 			#(#impl_vt_generic_entries,)*
 			__DynTarget,
 		> #proxy_trait<'__dyn_vtable, #vtable_ident #ty_generics>
-		for __DynTarget
+		for #impl_target
 		where
 			#(#where_predicates,)*
-			__DynTarget: #ident #trait_ty_generics,
+			__DynTarget: #ident #trait_ty_generics #consts_bound,
+			#(#subtable_bounds,)*
 		{
-			const STATIC_VTABLE: &'__dyn_vtable #vtable_ident #ty_generics =
-				&<Self as #proxy_trait<'__dyn_vtable, #vtable_ident #ty_generics>>::VTABLE;
+			const STATIC_VTABLE: &'__dyn_vtable #vtable_ident #ty_generics = #static_vtable;
 			const VTABLE: #vtable_ident #ty_generics = #vtable_ident {
+				#(__header: <#vtable_ident #ty_generics as ::dyntable::AssociatedHeader>::HEADER, #header_marker)*
 				#(__drop: {
 					unsafe #drop_abi fn thunk<T>(ptr: *mut ::core::ffi::c_void) {
 						::core::ptr::drop_in_place(ptr as *mut T)
@@ -137,7 +230,16 @@ pub fn gen_impl(
 					thunk::<Self>
 				},)*
 				#(__layout: ::dyntable::alloc::MemoryLayout::new::<Self>(), #embed_layout)* // embed_layout is a marker
+				// values created by rust are allocated in the global allocator
+				#(__dealloc: {
+					unsafe #dealloc_abi fn thunk<T>(ptr: *mut ::core::ffi::c_void) {
+						::dyntable::__private::dealloc_global::<T>(ptr)
+					}
+
+					thunk::<Self>
+				},)*
 				#(#entries,)*
+				#reserved
 				__generics: ::core::marker::PhantomData,
 			};
 		}
@@ -155,6 +257,33 @@ pub fn gen_impl(
 			}
 		)*
 
+		// dealloc implementation
+		#(#dealloc_marker // marker, no code generated
+			#[allow(non_camel_case_types)]
+			unsafe impl #impl_generics ::dyntable::AssociatedDealloc
+			for #vtable_ident #ty_generics
+			#where_clause {
+				#[inline(always)]
+				unsafe fn virtual_dealloc(&self, instance: *mut ::core::ffi::c_void) {
+					(self.__dealloc)(instance)
+				}
+			}
+		)*
+
+		#(
+			#[allow(non_camel_case_types)]
+			unsafe impl #impl_generics ::dyntable::AssociatedHeader
+			for #vtable_ident #ty_generics
+			#where_clause {
+				const HEADER: ::dyntable::VTableHeader = ::dyntable::VTableHeader {
+					// `__generics` is a ZST following the last entry, so its offset
+					// excludes any trailing padding
+					size: ::core::mem::offset_of!(Self, __generics),
+					version: #header_version,
+				};
+			}
+		)*
+
 		#(#embed_layout // marker, no code generated
 			#[allow(non_camel_case_types)]
 			unsafe impl #impl_generics ::dyntable::AssociatedLayout
@@ -194,7 +323,11 @@ fn gen_method_entry(
 		receiver,
 		inputs,
 		output,
+		optional,
+		field,
+		..
 	}: &MethodEntry,
+	object: Option<&TokenStream>,
 ) -> TokenStream {
 	let output = match output {
 		ReturnType::Default => ReturnType::Default,
@@ -226,8 +359,51 @@ fn gen_method_entry(
 		.collect::<Vec<_>>();
 
 	let fn_path = match receiver {
-		MethodReceiver::Reference(_) => quote::quote! { Self::#fn_ident },
-		MethodReceiver::Value(_) => {
+		// Methods of objects are called with a pointer to the object, which
+		// needs a proxy thunk to find the value held by the object
+		Some(receiver) if object.is_some() => {
+			let (_, ty_generics, _) = trait_generics.split_for_impl();
+			let (_, _, fn_where_clause) = generics.split_for_impl();
+			let param_list = MethodParam::params_safe(inputs.iter());
+			let arg_list = MethodParam::idents_safe(inputs.iter());
+
+			// value receivers are rejected while parsing
+			let mutability = match receiver {
+				MethodReceiver::Reference(ReceiverReference { mutability, .. })
+				| MethodReceiver::Pinned(ReceiverPinned { mutability, .. }) => mutability,
+				MethodReceiver::Value(_) => &None,
+			};
+
+			let value = quote::quote! {
+				&#mutability *#object::<__DynSelf>::value(__dyn_this)
+			};
+
+			let value = match receiver {
+				MethodReceiver::Pinned(_) => quote::quote! {
+					// objects are never moved once allocated
+					::core::pin::Pin::new_unchecked(#value)
+				},
+				_ => value,
+			};
+
+			quote::quote! {{
+				#unsafety #abi #fn_token thunk <
+					__DynSelf: #ident #trait_vt_generics,
+				> (__dyn_this: *mut ::core::ffi::c_void, #(#param_list),*) #output
+				#fn_where_clause {
+					unsafe {
+						<__DynSelf as #ident #ty_generics>::#fn_ident(#value, #(#arg_list),*)
+					}
+				}
+
+				thunk::<__DynTarget>
+			}}
+		},
+		// `Pin<&T>` and `Pin<&mut T>` share the ABI of a reference
+		None | Some(MethodReceiver::Reference(_) | MethodReceiver::Pinned(_)) => {
+			quote::quote! { Self::#fn_ident }
+		},
+		Some(MethodReceiver::Value(_)) => {
 			// functions that take self by value need a proxy thunk to
 			// convert from a pointer to an owned Self
 
@@ -272,6 +448,7 @@ fn gen_method_entry(
 		},
 	};
 
+	let self_ptr = receiver.as_ref().map(|_| <Token![_]>::default()).into_iter();
 	let inputs = inputs.iter().map(|_| <Token![_]>::default());
 
 	let output = match output {
@@ -280,15 +457,22 @@ fn gen_method_entry(
 	}
 	.into_iter();
 
-	quote::quote! {
-		#fn_ident: unsafe {
+	let fn_ptr = quote::quote! {
+		unsafe {
 			::core::mem::transmute(
 				#fn_path as
 					#unsafety #abi #fn_token (
-						_,
+						#(#self_ptr,)*
 						#(#inputs),*
 					) #( -> #output)*
 			)
 		}
+	};
+
+	// Rust implementations always provide optional methods, either
+	// directly or through a default implementation.
+	match optional {
+		true => quote::quote! { #field: ::core::option::Option::Some(#fn_ptr) },
+		false => quote::quote! { #field: #fn_ptr },
 	}
 }
@@ -4,8 +4,10 @@ use proc_macro2::Span;
 use syn::{
 	parse::{Parse, ParseStream},
 	punctuated::Punctuated,
+	Attribute,
 	Ident,
 	LitBool,
+	LitInt,
 	LitStr,
 	Token,
 };
@@ -18,7 +20,26 @@ pub struct AttributeOptions {
 	pub relax_abi: bool,
 	pub drop: Option<Abi>,
 	pub embed_layout: bool,
+	/// ABI of the VTable's deallocation function, if it has one
+	pub dealloc: Option<Abi>,
+	/// Version stored in the VTable header, if the header is enabled
+	pub header: Option<u32>,
+	/// Number of reserved slots appended to the VTable
+	pub reserved: usize,
+	/// Explicit position of the drop function in the VTable
+	pub drop_slot: Option<LitInt>,
+	/// Explicit position of the embedded layout in the VTable
+	pub layout_slot: Option<LitInt>,
+	/// Explicit position of the deallocation function in the VTable
+	pub dealloc_slot: Option<LitInt>,
+	/// Generate a C representation of the VTable
+	pub c_header: bool,
+	/// Interface ID of a COM interface, if the VTable is laid out as one
+	pub com: Option<ComGuid>,
+	/// Lay out the VTable as an Itanium C++ ABI VTable
+	pub itanium: bool,
 	pub vtable_name: Option<Ident>,
+	pub consts_name: Option<Ident>,
 }
 
 impl Parse for AttributeOptions {
@@ -28,7 +49,18 @@ impl Parse for AttributeOptions {
 			RelaxAbi(bool),
 			Drop(Option<Abi>),
 			EmbedLayout(bool),
+			Dealloc(Option<Abi>),
+			Header(bool),
+			Version(u32),
+			Reserved(usize),
+			DropSlot(LitInt),
+			LayoutSlot(LitInt),
+			DeallocSlot(LitInt),
+			CHeader(bool),
+			Com(ComGuid),
+			Itanium(bool),
 			VTableName(Ident),
+			ConstsName(Ident),
 		}
 
 		struct SpannedAttrOption(Span, AttrOption);
@@ -43,27 +75,20 @@ impl Parse for AttributeOptions {
 					match &option_name.to_string() as &str {
 						"repr" => AttrOption::Repr(Abi::parse_struct_repr(input)?),
 						"relax_abi" => AttrOption::RelaxAbi(input.parse::<LitBool>()?.value),
-						"drop" => AttrOption::Drop({
-							if input.peek(Ident) {
-								let abi = input.parse::<Ident>()?;
-
-								match &abi.to_string() as &str {
-									"none" => None,
-									_ => {
-										return Err(syn::Error::new_spanned(
-											abi,
-											"expected quoted ABI or `none`",
-										))
-									},
-								}
-							} else {
-								let abi = input.parse::<LitStr>()?;
-
-								Some(Abi::Explicit(Ident::new(&abi.value(), abi.span())))
-							}
-						}),
+						"drop" => AttrOption::Drop(parse_optional_abi(input)?),
 						"embed_layout" => AttrOption::EmbedLayout(input.parse::<LitBool>()?.value),
+						"dealloc" => AttrOption::Dealloc(parse_optional_abi(input)?),
+						"header" => AttrOption::Header(input.parse::<LitBool>()?.value),
+						"version" => AttrOption::Version(input.parse::<LitInt>()?.base10_parse()?),
+						"reserved" => AttrOption::Reserved(input.parse::<LitInt>()?.base10_parse()?),
+						"drop_slot" => AttrOption::DropSlot(parse_slot(input)?),
+						"layout_slot" => AttrOption::LayoutSlot(parse_slot(input)?),
+						"dealloc_slot" => AttrOption::DeallocSlot(parse_slot(input)?),
+						"c_header" => AttrOption::CHeader(input.parse::<LitBool>()?.value),
+						"com" => AttrOption::Com(parse_guid(input)?),
+						"itanium" => AttrOption::Itanium(input.parse::<LitBool>()?.value),
 						"vtable" => AttrOption::VTableName(input.parse::<Ident>()?),
+						"consts" => AttrOption::ConstsName(input.parse::<Ident>()?),
 						_ => {
 							return Err(syn::Error::new_spanned(
 								&option_name,
@@ -82,7 +107,18 @@ impl Parse for AttributeOptions {
 			relax_abi: Option<bool>,
 			drop: Option<Option<Abi>>,
 			embed_layout: Option<bool>,
+			dealloc: Option<Option<Abi>>,
+			header: Option<bool>,
+			version: Option<u32>,
+			reserved: Option<usize>,
+			drop_slot: Option<LitInt>,
+			layout_slot: Option<LitInt>,
+			dealloc_slot: Option<LitInt>,
+			c_header: Option<bool>,
+			com: Option<ComGuid>,
+			itanium: Option<bool>,
 			vtable_name: Option<Ident>,
+			consts_name: Option<Ident>,
 		}
 
 		let mut option_struct = OptionalOptions {
@@ -90,10 +126,49 @@ impl Parse for AttributeOptions {
 			relax_abi: None,
 			drop: None,
 			embed_layout: None,
+			dealloc: None,
+			header: None,
+			version: None,
+			reserved: None,
+			drop_slot: None,
+			layout_slot: None,
+			dealloc_slot: None,
+			c_header: None,
+			com: None,
+			itanium: None,
 			vtable_name: None,
+			consts_name: None,
 		};
 
+		let mut header_span = None;
+		let mut version_span = None;
+		let mut reserved_span = None;
+		// first option that changes the layout of the VTable, which
+		// cannot be used with `com` or `itanium`
+		let mut layout_conflict = None;
+		let mut com_span = None;
+
 		for SpannedAttrOption(span, option) in options {
+			let layout_option = match &option {
+				AttrOption::Repr(Abi::Explicit(repr)) if repr == "C" => None,
+				AttrOption::Repr(_) => Some("repr"),
+				AttrOption::Drop(Some(_)) => Some("drop"),
+				AttrOption::EmbedLayout(true) => Some("embed_layout"),
+				AttrOption::Dealloc(Some(_)) => Some("dealloc"),
+				AttrOption::Header(_) => Some("header"),
+				AttrOption::Version(_) => Some("version"),
+				AttrOption::Reserved(_) => Some("reserved"),
+				AttrOption::DropSlot(_) => Some("drop_slot"),
+				AttrOption::LayoutSlot(_) => Some("layout_slot"),
+				AttrOption::DeallocSlot(_) => Some("dealloc_slot"),
+				AttrOption::CHeader(true) => Some("c_header"),
+				_ => None,
+			};
+
+			if let (None, Some(name)) = (&layout_conflict, layout_option) {
+				layout_conflict = Some((span, name));
+			}
+
 			let duplicate = match option {
 				AttrOption::Repr(x) => matches!(option_struct.repr.replace(x), Some(_)),
 				AttrOption::RelaxAbi(x) => matches!(option_struct.relax_abi.replace(x), Some(_)),
@@ -101,9 +176,38 @@ impl Parse for AttributeOptions {
 				AttrOption::EmbedLayout(x) => {
 					matches!(option_struct.embed_layout.replace(x), Some(_))
 				},
+				AttrOption::Dealloc(x) => matches!(option_struct.dealloc.replace(x), Some(_)),
+				AttrOption::Header(x) => {
+					header_span = Some(span);
+					matches!(option_struct.header.replace(x), Some(_))
+				},
+				AttrOption::Version(x) => {
+					version_span = Some(span);
+					matches!(option_struct.version.replace(x), Some(_))
+				},
+				AttrOption::Reserved(x) => {
+					reserved_span = Some(span);
+					matches!(option_struct.reserved.replace(x), Some(_))
+				},
+				AttrOption::DropSlot(x) => matches!(option_struct.drop_slot.replace(x), Some(_)),
+				AttrOption::LayoutSlot(x) => {
+					matches!(option_struct.layout_slot.replace(x), Some(_))
+				},
+				AttrOption::DeallocSlot(x) => {
+					matches!(option_struct.dealloc_slot.replace(x), Some(_))
+				},
+				AttrOption::CHeader(x) => matches!(option_struct.c_header.replace(x), Some(_)),
+				AttrOption::Com(x) => {
+					com_span = Some(span);
+					matches!(option_struct.com.replace(x), Some(_))
+				},
+				AttrOption::Itanium(x) => matches!(option_struct.itanium.replace(x), Some(_)),
 				AttrOption::VTableName(x) => {
 					matches!(option_struct.vtable_name.replace(x), Some(_))
 				},
+				AttrOption::ConstsName(x) => {
+					matches!(option_struct.consts_name.replace(x), Some(_))
+				},
 			};
 
 			if duplicate {
@@ -111,12 +215,248 @@ impl Parse for AttributeOptions {
 			}
 		}
 
+		let itanium = option_struct.itanium.unwrap_or(false);
+
+		if let (Some(_), true) = (&option_struct.com, itanium) {
+			return Err(syn::Error::new(
+				com_span.unwrap(),
+				"`com` cannot be used with `itanium`",
+			))
+		}
+
+		if let (Some(_), Some((span, name))) = (&option_struct.com, layout_conflict) {
+			return Err(syn::Error::new(
+				span,
+				format!("`{name}` cannot be used with `com`, as COM interfaces have a fixed layout"),
+			))
+		}
+
+		if let (true, Some((span, name))) = (itanium, layout_conflict) {
+			return Err(syn::Error::new(
+				span,
+				format!(
+					"`{name}` cannot be used with `itanium`, as C++ VTables have a fixed layout"
+				),
+			))
+		}
+
+		// specifying a version implies a header
+		let header = match (option_struct.header, option_struct.version) {
+			(Some(false), Some(_)) => {
+				return Err(syn::Error::new(
+					version_span.unwrap(),
+					"`version` is stored in the VTable header and cannot be used with `header = false`",
+				))
+			},
+			(Some(true), version) | (None, version @ Some(_)) => Some(version.unwrap_or(0)),
+			(_, None) => None,
+		};
+
+		let repr = option_struct.repr.unwrap_or(Abi::new_explicit_c());
+
+		// the header must be at the start of the VTable to be read from
+		// VTables of other versions
+		if header.is_some() && matches!(repr, Abi::ImplicitRust) {
+			return Err(syn::Error::new(
+				header_span.or(version_span).unwrap(),
+				"a VTable header requires a stable VTable layout (`repr = Rust` cannot be used)",
+			))
+		}
+
+		// reserved slots only keep the VTable's size stable if its layout is stable
+		if let (Some(span), Abi::ImplicitRust) = (reserved_span, &repr) {
+			return Err(syn::Error::new(
+				span,
+				"reserved slots require a stable VTable layout (`repr = Rust` cannot be used)",
+			))
+		}
+
+		// COM and C++ objects are destroyed through their parent VTable, and
+		// have no drop function or layout
+		let is_object = option_struct.com.is_some() || itanium;
+		let drop = match is_object {
+			true => None,
+			false => option_struct.drop.unwrap_or(Some(Abi::new_explicit_c())),
+		};
+		let embed_layout = option_struct.embed_layout.unwrap_or(!is_object);
+		let dealloc = option_struct.dealloc.unwrap_or(None);
+
+		if let (None, Some(slot)) = (&drop, &option_struct.drop_slot) {
+			return Err(syn::Error::new_spanned(
+				slot,
+				"`drop_slot` cannot be used with `drop = none`",
+			))
+		}
+
+		if let (false, Some(slot)) = (embed_layout, &option_struct.layout_slot) {
+			return Err(syn::Error::new_spanned(
+				slot,
+				"`layout_slot` cannot be used with `embed_layout = false`",
+			))
+		}
+
+		if let (None, Some(slot)) = (&dealloc, &option_struct.dealloc_slot) {
+			return Err(syn::Error::new_spanned(
+				slot,
+				"`dealloc_slot` cannot be used without `dealloc`",
+			))
+		}
+
 		Ok(Self {
-			repr: option_struct.repr.unwrap_or(Abi::new_explicit_c()),
+			repr,
 			relax_abi: option_struct.relax_abi.unwrap_or(false),
-			drop: option_struct.drop.unwrap_or(Some(Abi::new_explicit_c())),
-			embed_layout: option_struct.embed_layout.unwrap_or(true),
+			drop,
+			embed_layout,
+			dealloc,
+			header,
+			reserved: option_struct.reserved.unwrap_or(0),
+			drop_slot: option_struct.drop_slot,
+			layout_slot: option_struct.layout_slot,
+			dealloc_slot: option_struct.dealloc_slot,
+			c_header: option_struct.c_header.unwrap_or(false),
+			com: option_struct.com,
+			itanium,
 			vtable_name: option_struct.vtable_name,
+			consts_name: option_struct.consts_name,
 		})
 	}
 }
+
+/// Parse a quoted ABI, or `none`
+fn parse_optional_abi(input: ParseStream) -> syn::Result<Option<Abi>> {
+	if input.peek(Ident) {
+		let abi = input.parse::<Ident>()?;
+
+		match &abi.to_string() as &str {
+			"none" => Ok(None),
+			_ => Err(syn::Error::new_spanned(
+				abi,
+				"expected quoted ABI or `none`",
+			)),
+		}
+	} else {
+		let abi = input.parse::<LitStr>()?;
+
+		Ok(Some(Abi::Explicit(Ident::new(&abi.value(), abi.span()))))
+	}
+}
+
+/// Parse a VTable slot index
+fn parse_slot(input: ParseStream) -> syn::Result<LitInt> {
+	let slot = input.parse::<LitInt>()?;
+	slot.base10_parse::<usize>()?;
+	Ok(slot)
+}
+
+/// A COM interface ID, given by the `com` option.
+#[derive(Debug, Clone, Copy)]
+pub struct ComGuid {
+	pub data1: u32,
+	pub data2: u16,
+	pub data3: u16,
+	pub data4: [u8; 8],
+}
+
+/// Parse a GUID in the form `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`,
+/// optionally surrounded by braces
+fn parse_guid(input: ParseStream) -> syn::Result<ComGuid> {
+	let lit = input.parse::<LitStr>()?;
+	let value = lit.value();
+	let value = value
+		.strip_prefix('{')
+		.and_then(|value| value.strip_suffix('}'))
+		.unwrap_or(&value);
+
+	let groups = value.split('-').collect::<Vec<_>>();
+	let valid = groups.len() == 5
+		&& groups.iter().zip([8, 4, 4, 4, 12]).all(|(group, len)| {
+			group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit())
+		});
+
+	if !valid {
+		return Err(syn::Error::new_spanned(
+			lit,
+			"expected a GUID in the form `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`",
+		))
+	}
+
+	let hex = groups.concat();
+	let byte = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+
+	Ok(ComGuid {
+		data1: u32::from_str_radix(groups[0], 16).unwrap(),
+		data2: u16::from_str_radix(groups[1], 16).unwrap(),
+		data3: u16::from_str_radix(groups[2], 16).unwrap(),
+		data4: core::array::from_fn(|i| byte(8 + i)),
+	})
+}
+
+/// Options for a single VTable entry (a method or associated constant),
+/// specified by a `#[dyntable(...)]` attribute on the entry.
+#[derive(Debug, Default)]
+pub struct EntryOptions {
+	/// Span of the `optional` option, if specified
+	pub optional: Option<Span>,
+	/// Explicit position of the entry in the VTable
+	pub slot: Option<LitInt>,
+	/// Name of the entry's VTable field
+	pub field: Option<Ident>,
+}
+
+impl EntryOptions {
+	/// Strip and parse any `#[dyntable(...)]` attributes from an entry
+	pub fn strip_from(attrs: &mut Vec<Attribute>) -> syn::Result<Self> {
+		let mut options = Self::default();
+		let mut error = Option::<syn::Error>::None;
+
+		attrs.retain(|attr| {
+			if !attr.path().is_ident("dyntable") {
+				return true
+			}
+
+			let result = attr.parse_nested_meta(|meta| {
+				let Some(option_name) = meta.path.get_ident() else {
+					return Err(meta.error("expected option name"))
+				};
+
+				let duplicate = match &option_name.to_string() as &str {
+					"optional" => options.optional.replace(option_name.span()).is_some(),
+					"slot" => {
+						let slot = meta.value()?.parse::<LitInt>()?;
+						slot.base10_parse::<usize>()?;
+						options.slot.replace(slot).is_some()
+					},
+					"field" => options.field.replace(meta.value()?.parse::<Ident>()?).is_some(),
+					_ => {
+						return Err(syn::Error::new_spanned(
+							option_name,
+							&format!("Unknown entry option '{}'", option_name),
+						))
+					},
+				};
+
+				match duplicate {
+					true => Err(syn::Error::new_spanned(
+						option_name,
+						"option can only be defined once",
+					)),
+					false => Ok(()),
+				}
+			});
+
+			if let Err(e) = result {
+				match &mut error {
+					Some(error) => error.combine(e),
+					None => error = Some(e),
+				}
+			}
+
+			false
+		});
+
+		match error {
+			Some(error) => Err(error),
+			None => Ok(options),
+		}
+	}
+}
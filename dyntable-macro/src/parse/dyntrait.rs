@@ -14,16 +14,19 @@ use syn::{
 	token,
 	Attribute,
 	FnArg,
+	GenericArgument,
 	GenericParam,
 	Generics,
 	Ident,
 	ItemTrait,
+	Lifetime,
 	LifetimeParam,
 	Pat,
 	PatIdent,
 	PatType,
 	PatWild,
 	Path,
+	PathArguments,
 	PredicateType,
 	Receiver,
 	Signature,
@@ -31,10 +34,12 @@ use syn::{
 	TraitBound,
 	TraitBoundModifier,
 	TraitItem,
+	TraitItemConst,
 	TraitItemFn,
 	TraitItemType,
 	Type,
 	TypeParamBound,
+	TypePath,
 	TypeReference,
 	TypeTraitObject,
 	Visibility,
@@ -43,13 +48,17 @@ use syn::{
 };
 
 use super::{
+	ConstEntry,
 	MethodEntry,
 	MethodParam,
 	MethodReceiver,
+	ReceiverPinned,
 	ReceiverReference,
 	Subtable,
 	TopLevelSubtable,
+	VTableEntry,
 };
+use super::attribute::EntryOptions;
 use crate::parse::SubtableEntry;
 
 /// Validated #[dyntable] trait AST tokens
@@ -62,11 +71,14 @@ pub struct DynTraitBody {
 	pub ident: Ident,
 	pub generics: Generics,
 	pub associated_types: Vec<TraitItemType>,
+	/// Methods bounded by `where Self: Sized`, which are excluded from the VTable
+	pub sized_methods: Vec<TraitItemFn>,
 	pub colon_token: Option<Token![:]>,
 	pub supertraits: Punctuated<TypeParamBound, Token![+]>,
 	pub subtables: Vec<SubtableEntry>,
 	pub brace_token: token::Brace,
-	pub methods: Vec<MethodEntry>,
+	/// Methods and associated constants, in declaration order
+	pub entries: Vec<VTableEntry>,
 }
 
 impl Parse for DynTraitBody {
@@ -127,18 +139,17 @@ impl Parse for DynTraitBody {
 				.collect::<syn::Result<Vec<_>>>()?
 		};
 
-		let mut methods = Vec::<MethodEntry>::new();
-		let associated_types = Vec::<TraitItemType>::new();
+		let mut entries = Vec::<VTableEntry>::new();
+		let mut associated_types = Vec::<TraitItemType>::new();
+		let mut sized_methods = Vec::<TraitItemFn>::new();
 
 		for item in dyntrait.items {
 			match item {
-				TraitItem::Type(item) => {
-					return Err(syn::Error::new_spanned(item, "associated types are currently unsupported in #[dyntable] traits"));
-					//associated_types.push(item)
-				},
-				TraitItem::Fn(TraitItemFn { sig, .. }) => methods.push(MethodEntry::try_from(sig)?),
+				TraitItem::Type(item) => associated_types.push(item),
+				TraitItem::Fn(item) if requires_sized_self(&item.sig.generics) => sized_methods.push(item),
+				TraitItem::Fn(item) => entries.push(VTableEntry::Method(MethodEntry::try_from(item)?)),
+				TraitItem::Const(item) => entries.push(VTableEntry::Const(ConstEntry::try_from(item)?)),
 
-				TraitItem::Const(entry) => return Err(syn::Error::new_spanned(entry, "associated constants are not supported in #[dyntable] traits")),
 				TraitItem::Macro(entry) => return Err(syn::Error::new_spanned(entry, "macro invocations are not supported for directly creating entries in #[dyntable] traits")),
 				entry => return Err(syn::Error::new_spanned(entry, "unknown entry")),
 			}
@@ -155,12 +166,36 @@ impl Parse for DynTraitBody {
 			supertraits: dyntrait.supertraits,
 			subtables: subtable_entries,
 			brace_token: dyntrait.brace_token,
-			methods,
+			entries,
 			associated_types,
+			sized_methods,
 		})
 	}
 }
 
+/// Check if a method opts out of the VTable with a `Self: Sized` bound
+fn requires_sized_self(generics: &Generics) -> bool {
+	let Some(where_clause) = &generics.where_clause else { return false };
+
+	where_clause.predicates.iter().any(|predicate| match predicate {
+		WherePredicate::Type(PredicateType {
+			lifetimes: None,
+			bounded_ty: Type::Path(TypePath { qself: None, path }),
+			bounds,
+			..
+		}) if path.is_ident("Self") => bounds.iter().any(|bound| match bound {
+			TypeParamBound::Trait(TraitBound {
+				modifier: TraitBoundModifier::None,
+				lifetimes: None,
+				path,
+				..
+			}) => path.segments.last().is_some_and(|segment| segment.ident == "Sized"),
+			_ => false,
+		}),
+		_ => false,
+	})
+}
+
 /// Strip and return dyn entries from a where clause
 fn strip_dyn_entries(where_clause: &mut WhereClause) -> Result<Vec<DynPredicate>, syn::Error> {
 	let mut dyn_entries = Vec::<DynPredicate>::new();
@@ -472,11 +507,23 @@ fn graph_subtables(
 	Subtable { path, subtables }
 }
 
-impl TryFrom<Signature> for MethodEntry {
+impl TryFrom<TraitItemFn> for MethodEntry {
 	type Error = syn::Error;
 
-	fn try_from(sig: Signature) -> Result<Self, Self::Error> {
-		let sig_span = sig.span();
+	fn try_from(item: TraitItemFn) -> Result<Self, Self::Error> {
+		let TraitItemFn {
+			mut attrs,
+			sig,
+			default,
+			..
+		} = item;
+
+		let EntryOptions {
+			optional,
+			slot,
+			field,
+		} = EntryOptions::strip_from(&mut attrs)?;
+
 		let Signature {
 			unsafety,
 			abi,
@@ -496,6 +543,13 @@ impl TryFrom<Signature> for MethodEntry {
 			))
 		}
 
+		if let (Some(span), None) = (optional, &default) {
+			return Err(syn::Error::new(
+				span,
+				"optional methods must have a default body, which is used by Rust implementations that leave the method out",
+			))
+		}
+
 		if let Some(where_clause) = generics.where_clause {
 			return Err(syn::Error::new_spanned(
 				where_clause,
@@ -537,15 +591,30 @@ impl TryFrom<Signature> for MethodEntry {
 		for input in inputs {
 			match input {
 				FnArg::Receiver(
-					receiver @ Receiver {
+					pinned @ Receiver {
 						colon_token: Some(_),
 						..
 					},
 				) => {
 					// explicitly typed self makes it impossible to determine if the
 					// type is a reference or value, due to type aliases and permitted
-					// wrappers.
-					return Err(syn::Error::new_spanned(receiver, "`self` parameter must use implicit type syntax (e.g. `self`, `&self`, `&mut self`)"));
+					// wrappers. `Pin` is the only wrapper that is permitted.
+					let Some((reference, mutability)) = pinned_reference(&pinned.ty) else {
+						return Err(syn::Error::new_spanned(pinned, "`self` parameter must use implicit type syntax (e.g. `self`, `&self`, `&mut self`) or be pinned (`self: Pin<&Self>`, `self: Pin<&mut Self>`)"));
+					};
+
+					if receiver.is_some() {
+						return Err(syn::Error::new_spanned(
+							pinned.self_token,
+							"`self` is bound more than once",
+						))
+					}
+
+					receiver = Some(MethodReceiver::Pinned(ReceiverPinned {
+						reference,
+						mutability,
+						receiver: pinned,
+					}));
 				},
 				FnArg::Receiver(Receiver {
 					reference: None,
@@ -588,8 +657,6 @@ impl TryFrom<Signature> for MethodEntry {
 						..
 					} = ty;
 
-					let pat_span = pat.span();
-
 					let ident = match *pat {
 						Pat::Ident(PatIdent {
 							by_ref: None,
@@ -609,10 +676,6 @@ impl TryFrom<Signature> for MethodEntry {
 						},
 					};
 
-					if receiver.is_none() {
-						return Err(syn::Error::new(pat_span, "first parameter must be `self`"))
-					}
-
 					args.push(MethodParam {
 						ident,
 						colon_token,
@@ -622,18 +685,102 @@ impl TryFrom<Signature> for MethodEntry {
 			}
 		}
 
-		let receiver = receiver
-			.ok_or_else(|| syn::Error::new(sig_span, "missing required `self` parameter"))?;
-
 		Ok(Self {
+			attrs,
 			unsafety,
 			abi,
 			fn_token,
-			ident,
 			generics,
 			receiver,
 			inputs: args,
 			output,
+			field: field.unwrap_or_else(|| ident.clone()),
+			ident,
+			default,
+			optional: optional.is_some(),
+			slot,
+		})
+	}
+}
+
+/// Match a `Pin<&Self>` or `Pin<&mut Self>` receiver type, returning its reference
+fn pinned_reference(
+	ty: &Type,
+) -> Option<((Token![&], Option<Lifetime>), Option<Token![mut]>)> {
+	let Type::Path(TypePath { qself: None, path }) = ty else { return None };
+	let segment = path.segments.last()?;
+
+	if segment.ident != "Pin" {
+		return None
+	}
+
+	let PathArguments::AngleBracketed(arguments) = &segment.arguments else { return None };
+	if arguments.args.len() != 1 {
+		return None
+	}
+
+	let Some(GenericArgument::Type(Type::Reference(TypeReference {
+		and_token,
+		lifetime,
+		mutability,
+		elem,
+	}))) = arguments.args.first()
+	else {
+		return None
+	};
+
+	match &**elem {
+		Type::Path(TypePath { qself: None, path }) if path.is_ident("Self") => {
+			Some(((*and_token, lifetime.clone()), *mutability))
+		},
+		_ => None,
+	}
+}
+
+impl TryFrom<TraitItemConst> for ConstEntry {
+	type Error = syn::Error;
+
+	fn try_from(item: TraitItemConst) -> Result<Self, Self::Error> {
+		let TraitItemConst {
+			mut attrs,
+			const_token,
+			ident,
+			generics,
+			colon_token,
+			ty,
+			default,
+			..
+		} = item;
+
+		if !generics.params.is_empty() || generics.where_clause.is_some() {
+			return Err(syn::Error::new_spanned(
+				generics,
+				"generic associated constants are not supported in #[dyntable] traits",
+			))
+		}
+
+		let EntryOptions {
+			optional,
+			slot,
+			field,
+		} = EntryOptions::strip_from(&mut attrs)?;
+
+		if let Some(span) = optional {
+			return Err(syn::Error::new(
+				span,
+				"associated constants cannot be optional",
+			))
+		}
+
+		Ok(Self {
+			attrs,
+			const_token,
+			field: field.unwrap_or_else(|| ident.clone()),
+			ident,
+			colon_token,
+			ty,
+			default,
+			slot,
 		})
 	}
 }
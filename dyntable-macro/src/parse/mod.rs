@@ -1,20 +1,29 @@
-use proc_macro2::{Span, TokenStream};
+use proc_macro2::{Span, TokenStream, TokenTree};
 use quote::{format_ident, ToTokens};
 use syn::{
 	parse::ParseStream,
 	punctuated::Punctuated,
 	token,
+	AssocType,
 	Attribute,
+	Block,
 	ConstParam,
+	Expr,
+	GenericArgument,
 	GenericParam,
 	Generics,
 	Ident,
 	Lifetime,
 	LifetimeParam,
+	LitInt,
 	LitStr,
 	Path,
+	PathArguments,
+	PathSegment,
+	Receiver,
 	ReturnType,
 	Token,
+	TraitItemFn,
 	TraitItemType,
 	Type,
 	TypeParam,
@@ -26,6 +35,7 @@ use syn::{
 pub mod attribute;
 pub mod dyntrait;
 
+pub use self::attribute::ComGuid;
 use self::{attribute::AttributeOptions, dyntrait::DynTraitBody};
 
 #[derive(Debug)]
@@ -37,7 +47,32 @@ pub struct DynTraitInfo {
 	pub drop: Option<Abi>,
 	pub relax_abi: bool,
 	pub embed_layout: bool,
+	/// ABI of the VTable's deallocation function, if it has one
+	pub dealloc: Option<Abi>,
+	/// Version stored in the VTable header, if the header is enabled
+	pub header: Option<u32>,
+	/// Number of reserved slots appended to the VTable
+	pub reserved: usize,
+	/// Generate a C representation of the VTable
+	pub c_header: bool,
+	/// Interface ID of a COM interface, if the VTable is laid out as one
+	pub com: Option<ComGuid>,
+	/// Layout of the object holding the VTable, if the VTable is referenced
+	/// through a thin pointer to it
+	pub object: Option<ObjectLayout>,
 	pub entries: Vec<VTableEntry>,
+	/// Order of the fields in the VTable, not including the header
+	/// and reserved slots
+	pub slots: Vec<VTableSlot>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VTableSlot {
+	Drop,
+	Layout,
+	Dealloc,
+	/// Index into `DynTraitInfo::entries`
+	Entry(usize),
 }
 
 #[derive(Debug)]
@@ -51,12 +86,16 @@ pub struct VTableInfo {
 pub struct TraitInfo {
 	pub attrs: Vec<Attribute>,
 	pub ident: Ident,
+	/// Name of the trait holding associated constants
+	pub consts_ident: Ident,
 	pub trait_token: Token![trait],
 	pub colon_token: Option<Token![:]>,
 	pub supertraits: Punctuated<TypeParamBound, Token![+]>,
 	pub generics: Generics,
 	pub vtable_ty_generics: VTableTraitTyGenerics,
 	pub associated_types: Vec<TraitItemType>,
+	/// Methods excluded from the VTable with `where Self: Sized`
+	pub sized_methods: Vec<TraitItemFn>,
 	pub brace_token: token::Brace,
 }
 
@@ -137,6 +176,19 @@ pub enum VTableEntry {
 	// another representation is added which allows that (struct form)
 	Subtable(SubtableEntry),
 	Method(MethodEntry),
+	Const(ConstEntry),
+}
+
+impl VTableEntry {
+	/// Explicit position in the VTable, if specified
+	fn slot(&self) -> Option<&LitInt> {
+		match self {
+			Self::Subtable(_) => None,
+			Self::Method(MethodEntry { slot, .. }) | Self::Const(ConstEntry { slot, .. }) => {
+				slot.as_ref()
+			},
+		}
+	}
 }
 
 /// A VTable's direct subtable, to be used as
@@ -175,23 +227,72 @@ pub struct TopLevelSubtable {
 	pub subtable: Subtable,
 }
 
+/// An associated constant, stored as a data field in the VTable.
+#[derive(Debug)]
+pub struct ConstEntry {
+	pub attrs: Vec<Attribute>,
+	pub const_token: Token![const],
+	pub ident: Ident,
+	pub colon_token: Token![:],
+	pub ty: Type,
+	pub default: Option<(Token![=], Expr)>,
+	/// Name of the VTable field holding the constant
+	pub field: Ident,
+	/// Explicit position in the VTable (`#[dyntable(slot = N)]`)
+	pub slot: Option<LitInt>,
+}
+
+impl ToTokens for ConstEntry {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let Self {
+			attrs,
+			const_token,
+			ident,
+			colon_token,
+			ty,
+			default,
+			..
+		} = self;
+
+		let default = default
+			.as_ref()
+			.map(|(eq, expr)| quote::quote! { #eq #expr });
+
+		tokens.extend(quote::quote! {
+			#(#attrs)*
+			#const_token #ident #colon_token #ty #default;
+		});
+	}
+}
+
 #[derive(Debug)]
 pub struct MethodEntry {
+	pub attrs: Vec<Attribute>,
 	pub unsafety: Option<Token![unsafe]>,
 	pub abi: Option<syn::Abi>,
 	pub fn_token: Token![fn],
 	pub ident: Ident,
 	pub generics: Generics,
-	pub receiver: MethodReceiver,
+	/// `None` for static functions, which are called without an instance
+	pub receiver: Option<MethodReceiver>,
 	/// # Note
 	/// does not include receiver
 	pub inputs: Vec<MethodParam>,
 	pub output: ReturnType,
+	/// Default implementation provided by the trait
+	pub default: Option<Block>,
+	/// The VTable entry is nullable (`#[dyntable(optional)]`)
+	pub optional: bool,
+	/// Name of the VTable field holding the method
+	pub field: Ident,
+	/// Explicit position in the VTable (`#[dyntable(slot = N)]`)
+	pub slot: Option<LitInt>,
 }
 
 #[derive(Debug)]
 pub enum MethodReceiver {
 	Reference(ReceiverReference),
+	Pinned(ReceiverPinned),
 	Value(Token![self]),
 }
 
@@ -202,6 +303,16 @@ pub struct ReceiverReference {
 	pub self_token: Token![self],
 }
 
+/// `self: Pin<&Self>` or `self: Pin<&mut Self>`
+#[derive(Debug)]
+pub struct ReceiverPinned {
+	/// Reference inside the `Pin`
+	pub reference: (Token![&], Option<Lifetime>),
+	pub mutability: Option<Token![mut]>,
+	/// Receiver as written, including the `Pin` type
+	pub receiver: Receiver,
+}
+
 impl ToTokens for ReceiverReference {
 	fn to_tokens(&self, tokens: &mut TokenStream) {
 		self.reference.0.to_tokens(tokens);
@@ -216,6 +327,7 @@ impl ToTokens for MethodReceiver {
 		match self {
 			Self::Value(x) => x.to_tokens(tokens),
 			Self::Reference(x) => x.to_tokens(tokens),
+			Self::Pinned(x) => x.receiver.to_tokens(tokens),
 		}
 	}
 }
@@ -270,6 +382,7 @@ impl ToTokens for MethodParam {
 impl ToTokens for MethodEntry {
 	fn to_tokens(&self, tokens: &mut TokenStream) {
 		let Self {
+			attrs,
 			unsafety,
 			abi,
 			fn_token,
@@ -278,13 +391,31 @@ impl ToTokens for MethodEntry {
 			receiver,
 			inputs,
 			output,
+			default,
+			..
 		} = self;
 
 		let (_, ty_generics, where_clause) = generics.split_for_impl();
 
+		let body = match default {
+			Some(block) => block.to_token_stream(),
+			None => quote::quote! { ; },
+		};
+
+		// Static functions must be excluded from the trait's native vtable
+		// for it to stay object safe. Where clauses are not otherwise
+		// permitted on VTable methods, so there is no clause to extend.
+		let where_clause = match receiver {
+			Some(_) => where_clause.to_token_stream(),
+			None => quote::quote! { where Self: ::core::marker::Sized },
+		};
+
+		let receiver = receiver.iter();
+
 		tokens.extend(quote::quote! {
-			#unsafety #abi #fn_token #ident #ty_generics (#receiver, #(#inputs),*) #output
-			#where_clause;
+			#(#attrs)*
+			#unsafety #abi #fn_token #ident #ty_generics (#(#receiver,)* #(#inputs),*) #output
+			#where_clause #body
 		});
 	}
 }
@@ -298,7 +429,11 @@ impl DynTraitInfo {
 		let trait_body = syn::parse::<DynTraitBody>(item)?;
 
 		if !attr_options.relax_abi {
-			for method in &trait_body.methods {
+			for entry in &trait_body.entries {
+				let VTableEntry::Method(method) = entry else {
+					continue
+				};
+
 				if method.abi.is_none() {
 					return Err(syn::Error::new_spanned(
 						method.fn_token,
@@ -308,6 +443,86 @@ impl DynTraitInfo {
 			}
 		}
 
+		// Constants are stored in the VTable, which must be borrowed for `'static`
+		// as `DynTrait::STATIC_VTABLE`. This is only possible if the const's type
+		// is known not to contain interior mutability, which is not the case for
+		// generic types.
+		let generic_idents = trait_body
+			.generics
+			.params
+			.iter()
+			.filter_map(|param| match param {
+				GenericParam::Type(TypeParam { ident, .. }) => Some(ident.to_string()),
+				_ => None,
+			})
+			.chain(Some(String::from("Self")))
+			.collect::<Vec<_>>();
+
+		for entry in &trait_body.entries {
+			let VTableEntry::Const(entry) = entry else {
+				continue
+			};
+
+			if let Some(ident) = find_ident(entry.ty.to_token_stream(), &generic_idents) {
+				return Err(syn::Error::new_spanned(
+					ident,
+					"the type of an associated constant in a #[dyntable] trait cannot depend on generics or associated types",
+				))
+			}
+		}
+
+		if attr_options.c_header {
+			check_c_header(&attr_options, &trait_body)?;
+		}
+
+		let object = match (&attr_options.com, attr_options.itanium) {
+			(Some(_), _) => Some(ObjectLayout::Com),
+			(None, true) => Some(ObjectLayout::Itanium),
+			(None, false) => None,
+		};
+
+		if let Some(object) = object {
+			check_object(&trait_body, object)?;
+		}
+
+		let entries = trait_body
+			.subtables
+			.into_iter()
+			.map(VTableEntry::Subtable)
+			.chain(trait_body.entries)
+			.collect::<Vec<_>>();
+
+		let slots = order_slots(
+			attr_options
+				.drop
+				.as_ref()
+				.map(|_| (VTableSlot::Drop, attr_options.drop_slot.as_ref())),
+			attr_options
+				.embed_layout
+				.then(|| (VTableSlot::Layout, attr_options.layout_slot.as_ref())),
+			attr_options
+				.dealloc
+				.as_ref()
+				.map(|_| (VTableSlot::Dealloc, attr_options.dealloc_slot.as_ref())),
+			&entries,
+		)?;
+
+		// the parent is always the only subtable, and the first entry
+		if let Some(object) = object {
+			if let Some(VTableSlot::Entry(i)) = slots.first() {
+				if let Some(slot) = entries[*i].slot() {
+					return Err(syn::Error::new_spanned(
+						slot,
+						format!(
+							"slot 0 of a {} is taken by its parent {}",
+							object.name(),
+							object.parent_name(),
+						),
+					))
+				}
+			}
+		}
+
 		Ok(Self {
 			vis: trait_body.vis,
 			unsafety: trait_body.unsafety,
@@ -328,6 +543,13 @@ impl DynTraitInfo {
 			},
 			dyntrait: TraitInfo {
 				attrs: trait_body.attrs,
+				consts_ident: Ident::new(
+					&match attr_options.consts_name {
+						Some(ident) => ident.to_string(),
+						None => format!("{}Consts", &trait_body.ident.to_string()),
+					},
+					trait_body.ident.span(),
+				),
 				ident: trait_body.ident,
 				trait_token: trait_body.trait_token,
 				colon_token: trait_body.colon_token,
@@ -338,21 +560,33 @@ impl DynTraitInfo {
 				),
 				generics: trait_body.generics,
 				associated_types: trait_body.associated_types,
+				sized_methods: trait_body.sized_methods,
 				brace_token: trait_body.brace_token,
 			},
 			drop: attr_options.drop,
 			relax_abi: attr_options.relax_abi,
 			embed_layout: attr_options.embed_layout,
-			entries: trait_body
-				.subtables
-				.into_iter()
-				.map(VTableEntry::Subtable)
-				.chain(trait_body.methods.into_iter().map(VTableEntry::Method))
-				.collect(),
+			dealloc: attr_options.dealloc,
+			header: attr_options.header,
+			reserved: attr_options.reserved,
+			c_header: attr_options.c_header,
+			com: attr_options.com,
+			object,
+			entries,
+			slots,
 		})
 	}
 }
 
+/// Find the first occurrence of any of the given idents in a token stream
+fn find_ident(tokens: TokenStream, idents: &[String]) -> Option<Ident> {
+	tokens.into_iter().find_map(|token| match token {
+		TokenTree::Ident(ident) if idents.iter().any(|x| ident == x) => Some(ident),
+		TokenTree::Group(group) => find_ident(group.stream(), idents),
+		_ => None,
+	})
+}
+
 /// Subtable parent-child relation
 pub struct SubtableChildGraph<'a> {
 	pub parent: &'a Subtable,
@@ -461,6 +695,59 @@ pub fn make_trait_vtable_ty_generics(
 #[derive(Debug)]
 pub struct VTableTraitTyGenerics(pub Punctuated<GenericParam, Token![,]>);
 
+impl VTableTraitTyGenerics {
+	/// Create a copy of these generics usable in the `AsDyn` disambiguator,
+	/// with all associated types bound to a placeholder.
+	///
+	/// Associated types cannot be bound to their `__DynAssociated_*` parameter
+	/// in the disambiguator, as the parameter would then only be constrained
+	/// by a projection depending on itself.
+	pub fn as_disambiguator(&self) -> Self {
+		Self(
+			self.0
+				.iter()
+				.cloned()
+				.map(|mut param| {
+					if let GenericParam::Type(TypeParam {
+						default: Some(default),
+						..
+					}) = &mut param
+					{
+						*default = associated_disambiguator();
+					}
+					param
+				})
+				.collect(),
+		)
+	}
+}
+
+/// Create a copy of a trait path usable in the `AsDyn` disambiguator, with
+/// all associated type bindings replaced by a placeholder.
+///
+/// See [`VTableTraitTyGenerics::as_disambiguator`].
+pub fn path_as_disambiguator(path: &Path) -> Path {
+	let mut path = path.clone();
+
+	if let Some(PathSegment {
+		arguments: PathArguments::AngleBracketed(arguments),
+		..
+	}) = path.segments.last_mut()
+	{
+		for argument in &mut arguments.args {
+			if let GenericArgument::AssocType(AssocType { ty, .. }) = argument {
+				*ty = associated_disambiguator();
+			}
+		}
+	}
+
+	path
+}
+
+fn associated_disambiguator() -> Type {
+	syn::parse_quote! { ::dyntable::__private::AssociatedDisambiguator }
+}
+
 impl ToTokens for VTableTraitTyGenerics {
 	fn to_tokens(&self, tokens: &mut TokenStream) {
 		if !self.0.is_empty() {
@@ -469,3 +756,325 @@ impl ToTokens for VTableTraitTyGenerics {
 		}
 	}
 }
+
+/// Order the fields of a VTable, placing fields with an explicit slot at
+/// their given position and filling the remaining slots in declaration order.
+fn order_slots(
+	drop: Option<(VTableSlot, Option<&LitInt>)>,
+	layout: Option<(VTableSlot, Option<&LitInt>)>,
+	dealloc: Option<(VTableSlot, Option<&LitInt>)>,
+	entries: &[VTableEntry],
+) -> syn::Result<Vec<VTableSlot>> {
+	let fields = drop
+		.into_iter()
+		.chain(layout)
+		.chain(dealloc)
+		.chain(
+			entries
+				.iter()
+				.enumerate()
+				.map(|(i, entry)| (VTableSlot::Entry(i), entry.slot())),
+		)
+		.collect::<Vec<_>>();
+
+	let mut slots = vec![None; fields.len()];
+
+	for (field, slot) in &fields {
+		let Some(slot) = slot else { continue };
+		let index = slot.base10_parse::<usize>()?;
+
+		match slots.get_mut(index) {
+			None => {
+				return Err(syn::Error::new_spanned(
+					slot,
+					format!("slot is out of range, the VTable has {} slots", fields.len()),
+				))
+			},
+			Some(Some(_)) => {
+				return Err(syn::Error::new_spanned(slot, "slot is already taken by another entry"))
+			},
+			Some(entry) => *entry = Some(*field),
+		}
+	}
+
+	let mut free = slots.iter_mut().filter(|slot| slot.is_none());
+
+	for (field, slot) in &fields {
+		if slot.is_none() {
+			*free.next().unwrap() = Some(*field);
+		}
+	}
+
+	Ok(slots.into_iter().map(Option::unwrap).collect())
+}
+
+/// Returns the C calling convention attribute matching a Rust ABI,
+/// or `None` if the ABI cannot be represented in C.
+pub fn c_calling_convention(abi: &str) -> Option<&'static str> {
+	Some(match abi {
+		"C" | "C-unwind" => "",
+		"system" | "system-unwind" => "DYNTABLE_SYSTEM",
+		"cdecl" | "cdecl-unwind" => "__cdecl",
+		"stdcall" | "stdcall-unwind" => "__stdcall",
+		"fastcall" | "fastcall-unwind" => "__fastcall",
+		"thiscall" | "thiscall-unwind" => "__thiscall",
+		"vectorcall" | "vectorcall-unwind" => "__vectorcall",
+		"sysv64" | "sysv64-unwind" => "__attribute__((sysv_abi))",
+		"win64" | "win64-unwind" => "__attribute__((ms_abi))",
+		_ => return None,
+	})
+}
+
+/// Returns the name of a method's ABI, as written in `extern "..."`
+pub fn abi_name(abi: &syn::Abi) -> String {
+	match &abi.name {
+		Some(name) => name.value(),
+		None => String::from("C"),
+	}
+}
+
+/// Check that a trait's VTable can be represented in C
+fn check_c_header(options: &AttributeOptions, trait_body: &DynTraitBody) -> syn::Result<()> {
+	match &options.repr {
+		Abi::Explicit(repr) if repr == "C" => {},
+		Abi::Explicit(repr) => {
+			return Err(syn::Error::new_spanned(
+				repr,
+				"C headers can only be generated for `repr = C` VTables",
+			))
+		},
+		Abi::ImplicitRust => {
+			return Err(syn::Error::new_spanned(
+				&trait_body.ident,
+				"C headers can only be generated for `repr = C` VTables",
+			))
+		},
+	}
+
+	for param in &trait_body.generics.params {
+		if let GenericParam::Type(_) | GenericParam::Const(_) = param {
+			return Err(syn::Error::new_spanned(
+				param,
+				"C headers cannot be generated for generic traits",
+			))
+		}
+	}
+
+	if let Some(item) = trait_body.associated_types.first() {
+		return Err(syn::Error::new_spanned(
+			&item.ident,
+			"C headers cannot be generated for traits with associated types",
+		))
+	}
+
+	for abi in [&options.drop, &options.dealloc] {
+		if let Some(Abi::Explicit(abi)) = abi {
+			if c_calling_convention(&abi.to_string()).is_none() {
+				return Err(syn::Error::new_spanned(
+					abi,
+					"this ABI cannot be represented in a C header",
+				))
+			}
+		}
+	}
+
+	for entry in &trait_body.entries {
+		let VTableEntry::Method(method) = entry else { continue };
+
+		match &method.abi {
+			None => {
+				return Err(syn::Error::new_spanned(
+					method.fn_token,
+					"the Rust ABI cannot be represented in a C header",
+				))
+			},
+			Some(abi) if c_calling_convention(&abi_name(abi)).is_none() => {
+				return Err(syn::Error::new_spanned(
+					abi,
+					"this ABI cannot be represented in a C header",
+				))
+			},
+			Some(_) => {},
+		}
+	}
+
+	Ok(())
+}
+
+/// A VTable stored in the object it belongs to, and referenced through a thin
+/// pointer to the object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectLayout {
+	/// A COM interface
+	Com,
+	/// A polymorphic C++ class, using the Itanium C++ ABI
+	Itanium,
+}
+
+impl ObjectLayout {
+	/// Name of the object kind, as used in error messages
+	fn name(self) -> &'static str {
+		match self {
+			Self::Com => "COM interface",
+			Self::Itanium => "C++ class",
+		}
+	}
+
+	/// Plural name of the object kind, as used in error messages
+	fn plural_name(self) -> &'static str {
+		match self {
+			Self::Com => "COM interfaces",
+			Self::Itanium => "C++ classes",
+		}
+	}
+
+	/// Name of the object kind's parents, as used in error messages
+	fn parent_name(self) -> &'static str {
+		match self {
+			Self::Com => "interface",
+			Self::Itanium => "class",
+		}
+	}
+
+	/// The root object all others are derived from
+	fn root(self) -> &'static str {
+		match self {
+			Self::Com => "IUnknown",
+			Self::Itanium => "Polymorphic",
+		}
+	}
+
+	/// The ABI methods must use
+	fn abi(self) -> &'static str {
+		match self {
+			Self::Com => "system",
+			Self::Itanium => "C",
+		}
+	}
+}
+
+/// Check that a trait's VTable can be laid out as the VTable of an object
+fn check_object(trait_body: &DynTraitBody, layout: ObjectLayout) -> syn::Result<()> {
+	let name = layout.name();
+	let plural = layout.plural_name();
+	let parent = layout.parent_name();
+
+	if let Some(param) = trait_body.generics.params.first() {
+		return Err(syn::Error::new_spanned(
+			param,
+			format!("{plural} cannot be generic"),
+		))
+	}
+
+	if let Some(item) = trait_body.associated_types.first() {
+		return Err(syn::Error::new_spanned(
+			&item.ident,
+			format!("{plural} cannot have associated types"),
+		))
+	}
+
+	// The object is referenced by the same pointer for every parent, which
+	// is only valid if each parent is stored at the start of the VTable
+	match &trait_body.subtables[..] {
+		[] => {
+			return Err(syn::Error::new_spanned(
+				&trait_body.ident,
+				format!(
+					"{plural} must have a parent {parent}, such as `{}`, bounded by `dyn Parent:`",
+					layout.root(),
+				),
+			))
+		},
+		[SubtableEntry {
+			subtable: TopLevelSubtable {
+				ref_token: Some(ref_token),
+				..
+			},
+			..
+		}] => {
+			return Err(syn::Error::new_spanned(
+				ref_token,
+				format!("the parent {parent} of a {name} must be stored inline"),
+			))
+		},
+		[_] => {},
+		[_, second, ..] => {
+			return Err(syn::Error::new_spanned(
+				&second.subtable.subtable.path,
+				format!("{plural} can only have a single parent {parent}"),
+			))
+		},
+	}
+
+	for entry in &trait_body.entries {
+		match entry {
+			VTableEntry::Const(ConstEntry { ident, .. }) => {
+				return Err(syn::Error::new_spanned(
+					ident,
+					format!("{plural} cannot have associated constants"),
+				))
+			},
+			VTableEntry::Method(MethodEntry {
+				ident,
+				fn_token,
+				abi,
+				receiver,
+				..
+			}) => {
+				// COM objects are shared by all of their references, while C++
+				// objects may be borrowed mutably
+				let receiver_error = match (layout, receiver) {
+					(
+						ObjectLayout::Com,
+						Some(
+							MethodReceiver::Reference(ReceiverReference {
+								mutability: None, ..
+							})
+							| MethodReceiver::Pinned(ReceiverPinned {
+								mutability: None, ..
+							}),
+						),
+					) => None,
+					(ObjectLayout::Com, receiver) => Some((
+						receiver.to_token_stream(),
+						"COM interface methods must take `&self`, as COM objects are shared",
+					)),
+					(
+						ObjectLayout::Itanium,
+						Some(MethodReceiver::Reference(_) | MethodReceiver::Pinned(_)),
+					) => None,
+					(ObjectLayout::Itanium, receiver) => Some((
+						receiver.to_token_stream(),
+						"C++ class methods must take `&self` or `&mut self`",
+					)),
+				};
+
+				if let Some((receiver, message)) = receiver_error {
+					return Err(match receiver.is_empty() {
+						true => syn::Error::new_spanned(ident, message),
+						false => syn::Error::new_spanned(receiver, message),
+					})
+				}
+
+				match abi {
+					Some(abi) if abi_name(abi) == layout.abi() => {},
+					Some(abi) => {
+						return Err(syn::Error::new_spanned(
+							abi,
+							format!("{name} methods must use the `{}` ABI", layout.abi()),
+						))
+					},
+					None => {
+						return Err(syn::Error::new_spanned(
+							fn_token,
+							format!("{name} methods must use the `{}` ABI", layout.abi()),
+						))
+					},
+				}
+			},
+			VTableEntry::Subtable(_) => {},
+		}
+	}
+
+	Ok(())
+}
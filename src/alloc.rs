@@ -1,7 +1,15 @@
 //! Stand-in memory allocation types for the ones provided by
 //! the `allocator_api` rust unstable feature.
 
-use core::{alloc::Layout, fmt, ptr::NonNull};
+use core::{
+	alloc::Layout,
+	ffi::c_void,
+	fmt,
+	mem,
+	ptr::{self, NonNull},
+};
+
+use crate::{AssociatedLayout, VTable};
 
 /// An implementation of `Deallocator` can deallocate a
 /// block of memory allocated in a compatible allocator
@@ -32,6 +40,92 @@ pub trait Allocator: Deallocator {
 	fn allocate(&self, layout: MemoryLayout) -> Result<NonNull<[u8]>, AllocError>;
 }
 
+/// An implementation of `FreeDeallocator` can deallocate a block of memory
+/// without knowing its layout, such as C's `free`.
+///
+/// Wrapped in [`Free`], it can be used as the deallocator of a
+/// [`DynBox`](crate::DynBox) whose VTable does not embed a layout.
+pub trait FreeDeallocator {
+	/// Deallocate a compatible block of memory.
+	///
+	/// # Safety
+	/// The given pointer must be allocated by an allocator compatible
+	/// with this deallocator.
+	unsafe fn free(&self, ptr: NonNull<u8>);
+}
+
+impl FreeDeallocator for unsafe extern "C" fn(*mut c_void) {
+	#[inline(always)]
+	unsafe fn free(&self, ptr: NonNull<u8>) {
+		self(ptr.as_ptr() as *mut c_void)
+	}
+}
+
+/// Adapts a [`FreeDeallocator`] into the deallocator of a box, allowing
+/// the box to own values of VTables without an embedded layout.
+///
+/// # Examples
+/// ```
+/// # use dyntable::{*, alloc::Free};
+/// # use core::ffi::c_void;
+/// #[dyntable(embed_layout = false)]
+/// trait MyTrait {}
+///
+/// extern "C" {
+///     fn malloc(size: usize) -> *mut c_void;
+///     fn free(ptr: *mut c_void);
+/// }
+///
+/// // usually a value allocated by a C library
+/// # impl MyTrait for u32 {}
+/// # let ptr = unsafe {
+/// #     let ptr = malloc(4) as *mut u32;
+/// #     ptr.write(0);
+/// #     DynPtr::<dyn MyTrait>::new(ptr)
+/// # };
+/// let free: unsafe extern "C" fn(*mut c_void) = free;
+/// let x: DynBox<dyn MyTrait, _> = unsafe { DynBox::from_raw_in(ptr, Free(free)) };
+/// ```
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Free<D>(pub D);
+
+/// Deallocates the values of boxes with VTables of type `V`.
+///
+/// Implemented for any [`Deallocator`] when `V` embeds the layout of its
+/// values, and for [`Free`] deallocators with any VTable.
+///
+/// # Safety
+/// `deallocate_value` must deallocate the given pointer in the same way as
+/// the wrapped deallocator.
+pub unsafe trait BoxDeallocator<V: VTable> {
+	/// Deallocate the memory of a value associated with `vtable`.
+	///
+	/// # Safety
+	/// The given pointer must be allocated by this allocator for a
+	/// value associated with `vtable`.
+	unsafe fn deallocate_value(&self, ptr: NonNull<u8>, vtable: &V);
+}
+
+unsafe impl<A: Deallocator, V: AssociatedLayout> BoxDeallocator<V> for A {
+	#[inline(always)]
+	unsafe fn deallocate_value(&self, ptr: NonNull<u8>, vtable: &V) {
+		let layout = vtable.virtual_layout();
+
+		// zero sized values are not allocated
+		if !layout.is_zero_sized() {
+			self.deallocate(ptr, layout);
+		}
+	}
+}
+
+unsafe impl<D: FreeDeallocator, V: VTable> BoxDeallocator<V> for Free<D> {
+	#[inline(always)]
+	unsafe fn deallocate_value(&self, ptr: NonNull<u8>, _: &V) {
+		self.0.free(ptr);
+	}
+}
+
 /// Layout of a block of memory
 ///
 /// Stand-in for [`core::alloc::Layout`]
@@ -58,6 +152,46 @@ impl MemoryLayout {
 	pub const fn is_zero_sized(&self) -> bool {
 		self.size == 0
 	}
+
+	/// Returns the layout of `header` directly followed by a value with this
+	/// layout, and the offset of the value. The size of `header` must be a
+	/// multiple of its alignment.
+	#[cfg(feature = "alloc")]
+	pub(crate) const fn with_header(self, header: MemoryLayout) -> (MemoryLayout, usize) {
+		// Rounding up to the value's alignment keeps the offset aligned for
+		// the header as well, as both alignments are powers of two.
+		let offset = (header.size + self.align - 1) & !(self.align - 1);
+		let align = match header.align > self.align {
+			true => header.align,
+			false => self.align,
+		};
+
+		let layout = MemoryLayout {
+			size: offset + self.size,
+			align,
+		};
+
+		(layout, offset)
+	}
+}
+
+/// Allocates a block of memory, without calling the allocator for zero
+/// sized layouts.
+pub(crate) fn allocate<A: Allocator>(
+	alloc: &A,
+	layout: MemoryLayout,
+) -> Result<NonNull<u8>, AllocError> {
+	match layout.is_zero_sized() {
+		true => Ok(unsafe { NonNull::new_unchecked(layout.align as *mut u8) }),
+		false => Ok(alloc.allocate(layout)?.cast::<u8>()),
+	}
+}
+
+/// Deallocates a block of memory allocated with [`allocate`].
+pub(crate) unsafe fn deallocate<A: Deallocator>(alloc: &A, ptr: NonNull<u8>, layout: MemoryLayout) {
+	if !layout.is_zero_sized() {
+		alloc.deallocate(ptr, layout);
+	}
 }
 
 /// The `AllocError` error indicates an allocation failure
@@ -99,6 +233,7 @@ impl From<MemoryLayout> for Layout {
 pub use std_alloc::alloc::Global as GlobalAllocator;
 #[cfg(any(doc, not(feature = "allocator_api")))]
 /// The global memory allocator
+#[derive(Copy, Clone)]
 pub struct GlobalAllocator;
 
 #[cfg(all(not(doc), feature = "allocator_api"))]
@@ -149,3 +284,298 @@ impl Deallocator for GlobalAllocator {
 		std_alloc::alloc::dealloc(ptr.as_ptr(), layout.into());
 	}
 }
+
+/// An FFI safe, type erased allocator.
+///
+/// A `DynAllocator` is a context pointer along with functions allocating,
+/// freeing and resizing memory using it. Containers using it, such as a
+/// `DynBox<dyn Trait, DynAllocator>`, can be passed across FFI and dylib
+/// boundaries and freed by the side that did not allocate them.
+///
+/// The functions return a null pointer if an allocation fails. `reallocate`
+/// moves the contents of the block up to the smaller of both sizes into a
+/// new block, freeing the old block only if it succeeds.
+///
+/// Zero sized blocks are never allocated: they are represented by a dangling
+/// pointer with the alignment of their layout as its address. The containers
+/// of this crate only call `allocate` and `deallocate` with layouts of a
+/// non-zero size. `reallocate` may be given such a pointer along with a zero sized
+/// `old_layout`, which must not be freed, and must return such a pointer
+/// without allocating for a zero sized `new_layout`.
+///
+/// A `DynAllocator` is created safely from a Rust allocator using
+/// [`DynAllocator::new`], or from its raw functions using
+/// [`DynAllocator::from_raw_parts`].
+///
+/// # Examples
+/// ```
+/// # use dyntable::{*, alloc::DynAllocator};
+/// #[dyntable]
+/// trait MyTrait {}
+/// impl MyTrait for u8 {}
+///
+/// let x: DynBox<dyn MyTrait, _> = DynBox::new_in(0u8, DynAllocator::global());
+/// ```
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct DynAllocator {
+	ctx: *mut c_void,
+	allocate: unsafe extern "C" fn(ctx: *mut c_void, layout: MemoryLayout) -> *mut c_void,
+	deallocate: unsafe extern "C" fn(ctx: *mut c_void, ptr: *mut c_void, layout: MemoryLayout),
+	reallocate: unsafe extern "C" fn(
+		ctx: *mut c_void,
+		ptr: *mut c_void,
+		old_layout: MemoryLayout,
+		new_layout: MemoryLayout,
+	) -> *mut c_void,
+}
+
+// SAFETY: the functions of a `DynAllocator` are safe to call with its
+// context from any thread, which is required by `from_raw_parts`.
+unsafe impl Send for DynAllocator {}
+unsafe impl Sync for DynAllocator {}
+
+unsafe extern "C" fn dyn_allocate<A: Allocator>(ctx: *mut c_void, layout: MemoryLayout) -> *mut c_void {
+	match (*(ctx as *const A)).allocate(layout) {
+		Ok(ptr) => ptr.as_ptr() as *mut c_void,
+		Err(_) => ptr::null_mut(),
+	}
+}
+
+unsafe extern "C" fn dyn_deallocate<A: Allocator>(
+	ctx: *mut c_void,
+	ptr: *mut c_void,
+	layout: MemoryLayout,
+) {
+	(*(ctx as *const A)).deallocate(NonNull::new_unchecked(ptr as *mut u8), layout);
+}
+
+unsafe extern "C" fn dyn_reallocate<A: Allocator>(
+	ctx: *mut c_void,
+	ptr: *mut c_void,
+	old_layout: MemoryLayout,
+	new_layout: MemoryLayout,
+) -> *mut c_void {
+	match reallocate(
+		&*(ctx as *const A),
+		NonNull::new_unchecked(ptr as *mut u8),
+		old_layout,
+		new_layout,
+	) {
+		Ok(ptr) => ptr.as_ptr() as *mut c_void,
+		Err(_) => ptr::null_mut(),
+	}
+}
+
+/// Moves a block of memory into a new block allocated in `alloc`,
+/// deallocating the old block if the allocation succeeds. Zero sized blocks
+/// are neither allocated nor deallocated.
+unsafe fn reallocate<A: Allocator>(
+	alloc: &A,
+	ptr: NonNull<u8>,
+	old_layout: MemoryLayout,
+	new_layout: MemoryLayout,
+) -> Result<NonNull<[u8]>, AllocError> {
+	let new = allocate(alloc, new_layout)?;
+	ptr::copy_nonoverlapping(
+		ptr.as_ptr(),
+		new.as_ptr(),
+		usize::min(old_layout.size, new_layout.size),
+	);
+	deallocate(alloc, ptr, old_layout);
+	Ok(NonNull::new_unchecked(ptr::slice_from_raw_parts_mut(
+		new.as_ptr(),
+		new_layout.size,
+	)))
+}
+
+impl DynAllocator {
+	/// Creates a handle to a Rust allocator.
+	///
+	/// # Examples
+	/// ```
+	/// # use dyntable::alloc::{DynAllocator, GlobalAllocator};
+	/// static ALLOCATOR: GlobalAllocator = GlobalAllocator;
+	///
+	/// let alloc = DynAllocator::new(&ALLOCATOR);
+	/// ```
+	pub const fn new<A: Allocator + Sync>(alloc: &'static A) -> Self {
+		// SAFETY: the functions are called with a context of type `A`, which is
+		// `Sync` and lives forever.
+		unsafe {
+			Self::from_raw_parts(
+				alloc as *const A as *mut c_void,
+				dyn_allocate::<A>,
+				dyn_deallocate::<A>,
+				dyn_reallocate::<A>,
+			)
+		}
+	}
+
+	/// Creates a `DynAllocator` from a context pointer and functions
+	/// allocating, freeing and resizing memory using it.
+	///
+	/// # Safety
+	/// The functions must behave as described in the
+	/// [type docs](DynAllocator), and must be safe to call with `ctx` from
+	/// any thread for as long as the `DynAllocator` or any copy of it is used.
+	pub const unsafe fn from_raw_parts(
+		ctx: *mut c_void,
+		allocate: unsafe extern "C" fn(ctx: *mut c_void, layout: MemoryLayout) -> *mut c_void,
+		deallocate: unsafe extern "C" fn(ctx: *mut c_void, ptr: *mut c_void, layout: MemoryLayout),
+		reallocate: unsafe extern "C" fn(
+			ctx: *mut c_void,
+			ptr: *mut c_void,
+			old_layout: MemoryLayout,
+			new_layout: MemoryLayout,
+		) -> *mut c_void,
+	) -> Self {
+		Self {
+			ctx,
+			allocate,
+			deallocate,
+			reallocate,
+		}
+	}
+
+	/// Creates a handle to the global allocator.
+	#[cfg(feature = "alloc")]
+	#[cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+	pub const fn global() -> Self {
+		Self::new(&GlobalAllocator)
+	}
+
+	/// Creates a handle to C allocation functions, such as `malloc` and
+	/// `free`. See [`CAllocator`].
+	pub const fn from_c(alloc: &'static CAllocator) -> Self {
+		Self::new(alloc)
+	}
+
+	/// Moves a block of memory into a new block with the given layout,
+	/// returning an error and keeping the old block if the allocation fails.
+	///
+	/// # Safety
+	/// The given pointer must be allocated by this allocator,
+	/// and representable by `old_layout`.
+	pub unsafe fn reallocate(
+		&self,
+		ptr: NonNull<u8>,
+		old_layout: MemoryLayout,
+		new_layout: MemoryLayout,
+	) -> Result<NonNull<[u8]>, AllocError> {
+		let ptr = (self.reallocate)(self.ctx, ptr.as_ptr() as *mut c_void, old_layout, new_layout);
+
+		match ptr.is_null() {
+			true => Err(AllocError),
+			false => Ok(NonNull::new_unchecked(ptr::slice_from_raw_parts_mut(
+				ptr as *mut u8,
+				new_layout.size,
+			))),
+		}
+	}
+}
+
+impl Deallocator for DynAllocator {
+	#[inline(always)]
+	unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: MemoryLayout) {
+		(self.deallocate)(self.ctx, ptr.as_ptr() as *mut c_void, layout);
+	}
+}
+
+impl Allocator for DynAllocator {
+	#[inline]
+	fn allocate(&self, layout: MemoryLayout) -> Result<NonNull<[u8]>, AllocError> {
+		let ptr = unsafe { (self.allocate)(self.ctx, layout) };
+
+		match ptr.is_null() {
+			true => Err(AllocError),
+			false => Ok(unsafe {
+				NonNull::new_unchecked(ptr::slice_from_raw_parts_mut(ptr as *mut u8, layout.size))
+			}),
+		}
+	}
+}
+
+/// An allocator using C allocation functions with the signatures of `malloc`
+/// and `free`.
+///
+/// Blocks aligned to at most twice the size of a pointer, which `malloc`
+/// guarantees on common platforms, are allocated directly with `malloc`, and
+/// may be freed with `free` from C. More aligned blocks are padded, and must
+/// be freed through the allocator.
+///
+/// # Examples
+/// ```
+/// # use dyntable::alloc::{CAllocator, DynAllocator};
+/// # use core::ffi::c_void;
+/// extern "C" {
+///     fn malloc(size: usize) -> *mut c_void;
+///     fn free(ptr: *mut c_void);
+/// }
+///
+/// static MALLOC: CAllocator = CAllocator { malloc, free };
+/// let alloc = DynAllocator::from_c(&MALLOC);
+/// ```
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct CAllocator {
+	pub malloc: unsafe extern "C" fn(size: usize) -> *mut c_void,
+	pub free: unsafe extern "C" fn(ptr: *mut c_void),
+}
+
+/// Alignment of blocks returned by `malloc`.
+const MALLOC_ALIGN: usize = 2 * mem::size_of::<usize>();
+
+impl Deallocator for CAllocator {
+	unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: MemoryLayout) {
+		if layout.is_zero_sized() {
+			return
+		}
+
+		let ptr = match layout.align > MALLOC_ALIGN {
+			// the block was padded, and the allocated pointer stored before it
+			true => *(ptr.as_ptr() as *mut *mut c_void).sub(1),
+			false => ptr.as_ptr() as *mut c_void,
+		};
+
+		(self.free)(ptr);
+	}
+}
+
+impl Allocator for CAllocator {
+	fn allocate(&self, layout: MemoryLayout) -> Result<NonNull<[u8]>, AllocError> {
+		if layout.is_zero_sized() {
+			return Ok(unsafe {
+				NonNull::new_unchecked(ptr::slice_from_raw_parts_mut(layout.align as *mut u8, 0))
+			})
+		}
+
+		let ptr = match layout.align > MALLOC_ALIGN {
+			false => unsafe { (self.malloc)(layout.size) as *mut u8 },
+			true => {
+				// Padding by the alignment leaves room for the allocated
+				// pointer before the aligned block, as the alignment is larger
+				// than a pointer.
+				let size = layout.size.checked_add(layout.align).ok_or(AllocError)?;
+				let allocated = unsafe { (self.malloc)(size) as *mut u8 };
+
+				match allocated.is_null() {
+					true => allocated,
+					false => unsafe {
+						let offset = layout.align - (allocated as usize & (layout.align - 1));
+						let ptr = allocated.add(offset);
+						*(ptr as *mut *mut u8).sub(1) = allocated;
+						ptr
+					},
+				}
+			},
+		};
+
+		match ptr.is_null() {
+			true => Err(AllocError),
+			false => Ok(unsafe {
+				NonNull::new_unchecked(ptr::slice_from_raw_parts_mut(ptr, layout.size))
+			}),
+		}
+	}
+}
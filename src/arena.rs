@@ -0,0 +1,582 @@
+//! Bump allocation arena for short-lived dyntable trait objects.
+//!
+//! An [`Arena`] allocates memory by bumping a pointer through large chunks
+//! obtained from a backing allocator, and frees all of it at once when it is
+//! [reset](Arena::reset) or dropped. Individual deallocations do nothing.
+//!
+//! Values can be placed in an arena using an [`ArenaBox`], which drops its
+//! value when dropped without freeing any memory. Values of boxes that are
+//! [leaked](ArenaBox::leak) or forgotten are dropped in bulk when the arena is
+//! reset or dropped. As the arena implements [`Allocator`], it can also back
+//! other containers, such as a `DynBox<dyn Trait, &Arena>`.
+//!
+//! A benchmark comparing arena allocation against individually allocated
+//! `DynBox`es can be run with `cargo bench --bench arena`.
+//!
+//! # Examples
+//! ```
+//! # use dyntable::{*, arena::*};
+//! #[dyntable]
+//! trait Node {
+//!     extern "C" fn eval(&self) -> u32;
+//! }
+//!
+//! impl Node for u32 {
+//!     extern "C" fn eval(&self) -> u32 {
+//!         *self
+//!     }
+//! }
+//!
+//! let mut arena = Arena::new();
+//!
+//! for _frame in 0..3 {
+//!     let nodes = (0..100u32)
+//!         .map(|i| ArenaBox::<dyn Node>::new_in(i, &arena))
+//!         .collect::<Vec<_>>();
+//!
+//!     assert_eq!(nodes.iter().map(|node| node.eval()).sum::<u32>(), 4950);
+//!
+//!     // the nodes are dropped without freeing their memory
+//!     drop(nodes);
+//!     // all memory is reused by the next frame
+//!     arena.reset();
+//! }
+//! ```
+//!
+//! # Layout
+//! An `ArenaBox`'s value is allocated directly after a record of its pending
+//! drop, which is used by [`Arena::reset`] to drop leaked values and cleared
+//! once the box drops the value itself.
+
+#![cfg(feature = "alloc")]
+#![cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+
+use core::{
+	alloc::Layout,
+	cell::Cell,
+	ffi::c_void,
+	marker::PhantomData,
+	mem,
+	ptr::{self, NonNull},
+};
+
+use std_alloc::alloc::handle_alloc_error;
+
+use crate::{
+	alloc::{AllocError, Allocator, Deallocator, GlobalAllocator, MemoryLayout},
+	AsDyn,
+	AssociatedDrop,
+	DynPtr,
+	DynRef,
+	DynRefMut,
+	DynTrait,
+	SubTable,
+	VTable,
+	VTableRepr,
+};
+
+/// Usable size of the first chunk of an arena created with [`Arena::new_in`].
+const DEFAULT_CAPACITY: usize = 4096;
+
+/// Header of a chunk, directly followed by the chunk's usable memory.
+#[repr(C)]
+struct Chunk {
+	prev: *mut Chunk,
+	layout: MemoryLayout,
+}
+
+/// Drops the value at the first argument, given its VTable.
+type DropFn = unsafe fn(*mut c_void, *const c_void);
+
+/// Pending drop of an [`ArenaBox`]'s value, stored directly before the value.
+#[repr(C)]
+struct Record {
+	next: *mut Record,
+	drop: Option<DropFn>,
+	vtable: *const c_void,
+}
+
+unsafe fn drop_value<V>(ptr: *mut c_void, vtable: *const c_void)
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	(*(vtable as *const V::VTable)).virtual_drop(ptr);
+}
+
+/// Returns the pending drop record of the value pointed to by `ptr`.
+#[inline(always)]
+unsafe fn record<V>(ptr: DynPtr<V>) -> *mut Record
+where
+	V: VTableRepr + ?Sized,
+{
+	(ptr.ptr as *mut Record).sub(1)
+}
+
+/// A bump allocation arena, backed by the allocator `A`.
+/// See the [module docs](self).
+pub struct Arena<A = GlobalAllocator>
+where
+	A: Deallocator,
+{
+	alloc: A,
+	/// The most recently allocated chunk, linked to the previous chunks.
+	chunk: Cell<*mut Chunk>,
+	next: Cell<*mut u8>,
+	end: Cell<*mut u8>,
+	/// Usable size of the next allocated chunk.
+	capacity: Cell<usize>,
+	/// The most recently allocated drop record, linked to the previous records.
+	drops: Cell<*mut Record>,
+}
+
+impl Arena<GlobalAllocator> {
+	/// Creates an empty arena backed by the global allocator.
+	///
+	/// No memory is allocated until the first allocation.
+	#[inline(always)]
+	pub const fn new() -> Self {
+		Self::new_in(GlobalAllocator)
+	}
+
+	/// Creates an empty arena backed by the global allocator, which will
+	/// allocate its first chunk with room for `capacity` bytes.
+	#[inline(always)]
+	pub const fn with_capacity(capacity: usize) -> Self {
+		Self::with_capacity_in(capacity, GlobalAllocator)
+	}
+}
+
+impl Default for Arena<GlobalAllocator> {
+	#[inline(always)]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<A> Arena<A>
+where
+	A: Deallocator,
+{
+	/// Creates an empty arena backed by the given allocator.
+	///
+	/// No memory is allocated until the first allocation.
+	#[inline(always)]
+	pub const fn new_in(alloc: A) -> Self {
+		Self::with_capacity_in(DEFAULT_CAPACITY, alloc)
+	}
+
+	/// Creates an empty arena backed by the given allocator, which will
+	/// allocate its first chunk with room for `capacity` bytes.
+	///
+	/// Each following chunk is twice as large as the previous one.
+	#[inline(always)]
+	pub const fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+		Self {
+			alloc,
+			chunk: Cell::new(ptr::null_mut()),
+			next: Cell::new(ptr::null_mut()),
+			end: Cell::new(ptr::null_mut()),
+			capacity: Cell::new(capacity),
+			drops: Cell::new(ptr::null_mut()),
+		}
+	}
+
+	/// Drops the values of all leaked [`ArenaBox`]es and frees all allocated
+	/// memory for reuse, except for the most recently allocated chunk.
+	///
+	/// Values are dropped in reverse allocation order.
+	pub fn reset(&mut self) {
+		self.drop_pending();
+
+		let chunk = self.chunk.get();
+		if chunk.is_null() {
+			return
+		}
+
+		unsafe {
+			// The most recent chunk is the largest one.
+			self.free_chunks((*chunk).prev);
+			(*chunk).prev = ptr::null_mut();
+			self.next.set(chunk.add(1) as *mut u8);
+		}
+	}
+
+	/// Returns a reference to the backing allocator.
+	#[inline(always)]
+	pub fn allocator(&self) -> &A {
+		&self.alloc
+	}
+
+	fn drop_pending(&self) {
+		let mut record = self.drops.replace(ptr::null_mut());
+
+		while !record.is_null() {
+			unsafe {
+				let Record { next, drop, vtable } = record.read();
+
+				if let Some(drop) = drop {
+					drop(record.add(1) as *mut c_void, vtable);
+				}
+
+				record = next;
+			}
+		}
+	}
+
+	unsafe fn free_chunks(&self, mut chunk: *mut Chunk) {
+		while !chunk.is_null() {
+			let Chunk { prev, layout } = chunk.read();
+			self.alloc
+				.deallocate(NonNull::new_unchecked(chunk as *mut u8), layout);
+			chunk = prev;
+		}
+	}
+
+	#[inline(always)]
+	fn try_bump(&self, layout: MemoryLayout) -> Option<NonNull<u8>> {
+		let next = self.next.get();
+		let padding = (next as usize).wrapping_neg() & (layout.align - 1);
+		let remaining = self.end.get() as usize - next as usize;
+
+		match padding.checked_add(layout.size)? <= remaining {
+			true => unsafe {
+				self.next.set(next.add(padding + layout.size));
+				Some(NonNull::new_unchecked(next.add(padding)))
+			},
+			false => None,
+		}
+	}
+}
+
+impl<A> Arena<A>
+where
+	A: Allocator,
+{
+	#[inline]
+	fn bump(&self, layout: MemoryLayout) -> Result<NonNull<u8>, AllocError> {
+		if layout.is_zero_sized() {
+			return Ok(unsafe { NonNull::new_unchecked(layout.align as *mut u8) })
+		}
+
+		match self.try_bump(layout) {
+			Some(ptr) => Ok(ptr),
+			None => {
+				self.grow(layout)?;
+				// the new chunk has room for the layout at any alignment
+				Ok(self.try_bump(layout).ok_or(AllocError)?)
+			},
+		}
+	}
+
+	/// Allocates a new chunk with room for at least `layout`.
+	#[cold]
+	fn grow(&self, layout: MemoryLayout) -> Result<(), AllocError> {
+		let required = layout.size.checked_add(layout.align).ok_or(AllocError)?;
+		let capacity = usize::max(self.capacity.get(), required);
+		let size = capacity
+			.checked_add(mem::size_of::<Chunk>())
+			.ok_or(AllocError)?;
+		let chunk_layout: MemoryLayout = Layout::from_size_align(size, mem::align_of::<Chunk>())
+			.map_err(|_| AllocError)?
+			.into();
+
+		unsafe {
+			let chunk = self.alloc.allocate(chunk_layout)?.cast::<Chunk>().as_ptr();
+			chunk.write(Chunk {
+				prev: self.chunk.get(),
+				layout: chunk_layout,
+			});
+
+			let start = chunk.add(1) as *mut u8;
+			self.chunk.set(chunk);
+			self.next.set(start);
+			self.end.set(start.add(capacity));
+		}
+
+		self.capacity.set(capacity.saturating_mul(2));
+		Ok(())
+	}
+}
+
+impl<A> Deallocator for Arena<A>
+where
+	A: Deallocator,
+{
+	/// Does nothing, as memory is freed when the arena is reset or dropped.
+	#[inline(always)]
+	unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: MemoryLayout) {}
+}
+
+impl<A> Allocator for Arena<A>
+where
+	A: Allocator,
+{
+	#[inline]
+	fn allocate(&self, layout: MemoryLayout) -> Result<NonNull<[u8]>, AllocError> {
+		let ptr = self.bump(layout)?;
+
+		Ok(unsafe {
+			NonNull::new_unchecked(ptr::slice_from_raw_parts_mut(ptr.as_ptr(), layout.size))
+		})
+	}
+}
+
+impl<A> Deallocator for &Arena<A>
+where
+	A: Deallocator,
+{
+	/// Does nothing, as memory is freed when the arena is reset or dropped.
+	#[inline(always)]
+	unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: MemoryLayout) {}
+}
+
+impl<A> Allocator for &Arena<A>
+where
+	A: Allocator,
+{
+	#[inline(always)]
+	fn allocate(&self, layout: MemoryLayout) -> Result<NonNull<[u8]>, AllocError> {
+		(**self).allocate(layout)
+	}
+}
+
+impl<A> Drop for Arena<A>
+where
+	A: Deallocator,
+{
+	fn drop(&mut self) {
+		self.drop_pending();
+		unsafe { self.free_chunks(self.chunk.get()) };
+	}
+}
+
+/// An owned dyntable trait object allocated in an [`Arena`].
+///
+/// Dropping an `ArenaBox` drops its value, but its memory is only freed when
+/// the arena is reset or dropped. See the [module docs](self).
+#[repr(C)]
+pub struct ArenaBox<'a, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	ptr: DynPtr<V>,
+	_lt: PhantomData<&'a ()>,
+}
+
+unsafe impl<V> Send for ArenaBox<'_, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+	<V::VTable as VTable>::Bounds: Send,
+{
+}
+
+unsafe impl<V> Sync for ArenaBox<'_, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+	<V::VTable as VTable>::Bounds: Sync,
+{
+}
+
+unsafe impl<R, V> AsDyn<R> for ArenaBox<'_, V>
+where
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	type Repr = V;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.ptr.ptr
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		self.ptr.vtable.as_ptr()
+	}
+
+	fn dyn_dealloc(self) {
+		// the value has been moved out, and must not be dropped on reset
+		unsafe { (*record(self.ptr)).drop = None };
+		mem::forget(self);
+	}
+}
+
+impl<'a, V> ArenaBox<'a, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	/// Allocates memory in the given arena and moves `data` into
+	/// the allocated memory, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, arena::*};
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let arena = Arena::new();
+	/// let x: ArenaBox<dyn MyTrait> = ArenaBox::new_in(0u8, &arena);
+	/// ```
+	#[inline]
+	pub fn new_in<'v, T, A>(data: T, arena: &'a Arena<A>) -> Self
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		match Self::try_new_in(data, arena) {
+			Ok(b) => b,
+			Err(_) => handle_alloc_error(Layout::new::<T>()),
+		}
+	}
+
+	/// Allocates memory in the given arena and moves `data` into
+	/// the allocated memory, upcasting it to `V`, and returning an error
+	/// if the allocation fails.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, arena::*};
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let arena = Arena::new();
+	/// let x: ArenaBox<dyn MyTrait> = ArenaBox::try_new_in(0u8, &arena)?;
+	/// # Ok::<_, dyntable::alloc::AllocError>(())
+	/// ```
+	#[inline]
+	pub fn try_new_in<'v, T, A>(data: T, arena: &'a Arena<A>) -> Result<Self, AllocError>
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let (layout, offset) = MemoryLayout::new::<T>().with_header(MemoryLayout::new::<Record>());
+
+		unsafe {
+			let ptr = arena.bump(layout)?.as_ptr().add(offset) as *mut T;
+			let record = (ptr as *mut Record).sub(1);
+			record.write(Record {
+				next: arena.drops.get(),
+				drop: Some(drop_value::<V>),
+				vtable: T::STATIC_VTABLE as *const V::VTable as *const c_void,
+			});
+			arena.drops.set(record);
+			ptr.write(data);
+
+			Ok(Self {
+				ptr: DynPtr::new(ptr),
+				_lt: PhantomData,
+			})
+		}
+	}
+
+	/// Leaks the box, returning a reference to its value which lives as long
+	/// as the arena's borrow.
+	///
+	/// The value will be dropped when the arena is reset or dropped.
+	#[inline(always)]
+	pub fn leak(b: Self) -> DynRefMut<'a, V> {
+		let ptr = b.ptr;
+		mem::forget(b);
+
+		DynRefMut {
+			ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Immutably borrows the wrapped value.
+	#[inline(always)]
+	pub fn borrow(b: &Self) -> DynRef<'_, V> {
+		DynRef {
+			ptr: b.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Mutably borrows the wrapped value.
+	#[inline(always)]
+	pub fn borrow_mut(b: &mut Self) -> DynRefMut<'_, V> {
+		DynRefMut {
+			ptr: b.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Returns a reference to the VTable of the wrapped value.
+	///
+	/// This can be used to read VTable data fields, such as
+	/// [associated constants](crate::dyntable#associated-constants).
+	#[inline(always)]
+	pub fn vtable(b: &Self) -> &V::VTable {
+		unsafe { b.ptr.vtable.as_ref() }
+	}
+
+	/// Upcast the box to a bounded dyntrait box.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, arena::*};
+	/// #[dyntable]
+	/// trait Animal {}
+	///
+	/// #[dyntable]
+	/// trait Feline: Animal
+	/// where
+	///     dyn Animal:,
+	/// {}
+	///
+	/// struct Cat;
+	///
+	/// impl Feline for Cat {}
+	/// impl Animal for Cat {}
+	///
+	/// let arena = Arena::new();
+	/// let feline: ArenaBox<dyn Feline> = ArenaBox::new_in(Cat, &arena);
+	/// let animal: ArenaBox<dyn Animal> = ArenaBox::upcast(feline);
+	/// ```
+	#[inline(always)]
+	pub fn upcast<U>(b: Self) -> ArenaBox<'a, U>
+	where
+		U: VTableRepr + ?Sized,
+		U::VTable: AssociatedDrop,
+		V::VTable: SubTable<U::VTable>,
+	{
+		let ptr = b.ptr;
+		mem::forget(b);
+
+		ArenaBox {
+			ptr: DynPtr::upcast(ptr),
+			_lt: PhantomData,
+		}
+	}
+}
+
+impl<V> Drop for ArenaBox<'_, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	fn drop(&mut self) {
+		unsafe {
+			// cleared first, so a panicking drop is not repeated on reset
+			(*record(self.ptr)).drop = None;
+			self.ptr.vtable.as_ref().virtual_drop(self.ptr.ptr);
+		}
+	}
+}
@@ -0,0 +1,472 @@
+//! Collection of dyntable trait objects grouped by VTable for batch dispatch.
+//!
+//! A [`DynBatch`] keeps its values in buckets, one per VTable pointer, each
+//! storing values of the same concrete type contiguously as an array.
+//! [`DynBatch::for_each`] and [`DynBatch::for_each_mut`] visit every value of
+//! a bucket before moving to the next one, so consecutive virtual calls go
+//! through the same VTable entry and walk through dense memory.
+//!
+//! Buckets are created in the order their first value is pushed, and the
+//! order of values within a bucket is not preserved when values are removed.
+//!
+//! VTables are compared by address. Distinct types may share a VTable if the
+//! compiler merges identical VTables, which is harmless as their layout and
+//! behavior through the VTable are identical. A type may also use several
+//! buckets if its VTable is duplicated, which only costs some locality.
+//!
+//! A benchmark comparing batch dispatch against a `Vec` of `DynBox`es holding
+//! values of interleaved types can be run with `cargo bench --bench batch`.
+//!
+//! # Examples
+//! ```
+//! # use dyntable::{*, batch::*};
+//! #[dyntable]
+//! trait Entity {
+//!     extern "C" fn update(&mut self, dt: u32);
+//!     extern "C" fn position(&self) -> u32;
+//! }
+//!
+//! struct Walker(u32);
+//! struct Runner(u32);
+//!
+//! impl Entity for Walker {
+//!     extern "C" fn update(&mut self, dt: u32) {
+//!         self.0 += dt;
+//!     }
+//!
+//!     extern "C" fn position(&self) -> u32 {
+//!         self.0
+//!     }
+//! }
+//!
+//! impl Entity for Runner {
+//!     extern "C" fn update(&mut self, dt: u32) {
+//!         self.0 += dt * 3;
+//!     }
+//!
+//!     extern "C" fn position(&self) -> u32 {
+//!         self.0
+//!     }
+//! }
+//!
+//! let mut entities = DynBatch::<dyn Entity>::new();
+//! entities.push(Walker(0));
+//! entities.push(Runner(0));
+//! entities.push(Walker(10));
+//!
+//! // both walkers are updated before the runner
+//! entities.for_each_mut(|mut entity| entity.update(1));
+//!
+//! let mut positions = Vec::new();
+//! entities.for_each(|entity| positions.push(entity.position()));
+//! assert_eq!(positions, [1, 11, 3]);
+//!
+//! entities.retain(|entity| entity.position() < 10);
+//! assert_eq!(entities.len(), 2);
+//! ```
+
+#![cfg(feature = "alloc")]
+#![cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+
+use core::{
+	alloc::Layout,
+	ffi::c_void,
+	marker::PhantomData,
+	ptr::{self, NonNull},
+	slice,
+};
+
+use std_alloc::alloc::handle_alloc_error;
+
+use crate::{
+	alloc::{
+		allocate,
+		deallocate,
+		AllocError,
+		Allocator,
+		Deallocator,
+		GlobalAllocator,
+		MemoryLayout,
+	},
+	AssociatedDrop,
+	AssociatedLayout,
+	DynPtr,
+	DynRef,
+	DynRefMut,
+	DynTrait,
+	VTable,
+	VTableRepr,
+};
+
+/// Array of values sharing the same VTable.
+struct Bucket<V>
+where
+	V: VTableRepr + ?Sized,
+{
+	vtable: NonNull<V::VTable>,
+	/// Layout of a single value.
+	layout: MemoryLayout,
+	data: NonNull<u8>,
+	cap: usize,
+	len: usize,
+}
+
+impl<V> Bucket<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	#[inline(always)]
+	fn value_ptr(&self, index: usize) -> *mut c_void {
+		unsafe { self.data.as_ptr().add(index * self.layout.size) as *mut c_void }
+	}
+
+	#[inline(always)]
+	fn dyn_ptr(&self, index: usize) -> DynPtr<V> {
+		DynPtr {
+			ptr: self.value_ptr(index),
+			vtable: self.vtable,
+		}
+	}
+
+	#[inline(always)]
+	fn data_layout(&self) -> MemoryLayout {
+		MemoryLayout {
+			size: self.layout.size * self.cap,
+			align: self.layout.align,
+		}
+	}
+
+	/// Drops all values in the bucket.
+	fn clear(&mut self) {
+		let len = self.len;
+		self.len = 0;
+
+		let vtable = unsafe { self.vtable.as_ref() };
+		for i in 0..len {
+			unsafe { vtable.virtual_drop(self.value_ptr(i)) };
+		}
+	}
+
+	#[cold]
+	fn grow<A: Allocator>(&mut self, alloc: &A) -> Result<(), AllocError> {
+		let cap = match self.layout.is_zero_sized() {
+			true => usize::MAX,
+			false => usize::max(4, self.cap.saturating_mul(2)),
+		};
+
+		let size = self.layout.size.checked_mul(cap).ok_or(AllocError)?;
+		let layout: MemoryLayout = Layout::from_size_align(size, self.layout.align)
+			.map_err(|_| AllocError)?
+			.into();
+		let data = allocate(alloc, layout)?;
+
+		unsafe {
+			ptr::copy_nonoverlapping(
+				self.data.as_ptr(),
+				data.as_ptr(),
+				self.len * self.layout.size,
+			);
+			deallocate(alloc, self.data, self.data_layout());
+		}
+
+		self.data = data;
+		self.cap = cap;
+		Ok(())
+	}
+}
+
+/// A collection of dyntable trait objects grouped by VTable.
+/// See the [module docs](self).
+pub struct DynBatch<V, A = GlobalAllocator>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	alloc: A,
+	buckets: NonNull<Bucket<V>>,
+	buckets_cap: usize,
+	buckets_len: usize,
+	len: usize,
+}
+
+unsafe impl<V, A> Send for DynBatch<V, A>
+where
+	A: Deallocator + Send,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	<V::VTable as VTable>::Bounds: Send,
+{
+}
+
+unsafe impl<V, A> Sync for DynBatch<V, A>
+where
+	A: Deallocator + Sync,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	<V::VTable as VTable>::Bounds: Sync,
+{
+}
+
+impl<V> DynBatch<V, GlobalAllocator>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Creates an empty collection using the global allocator.
+	///
+	/// No memory is allocated until values are pushed to the collection.
+	#[inline(always)]
+	pub const fn new() -> Self {
+		Self::new_in(GlobalAllocator)
+	}
+}
+
+impl<V> Default for DynBatch<V, GlobalAllocator>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	#[inline(always)]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<V, A> DynBatch<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Creates an empty collection using the given allocator.
+	///
+	/// No memory is allocated until values are pushed to the collection.
+	#[inline(always)]
+	pub const fn new_in(alloc: A) -> Self {
+		Self {
+			alloc,
+			buckets: NonNull::dangling(),
+			buckets_cap: 0,
+			buckets_len: 0,
+			len: 0,
+		}
+	}
+
+	/// Returns the number of values in the collection.
+	#[inline(always)]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns `true` if the collection contains no values.
+	#[inline(always)]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns the number of buckets in the collection, which is usually
+	/// the number of distinct types that have been pushed to it.
+	#[inline(always)]
+	pub fn bucket_count(&self) -> usize {
+		self.buckets_len
+	}
+
+	/// Moves `data` into the bucket of its VTable, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	#[inline]
+	pub fn push<'v, T>(&mut self, data: T)
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		if self.try_push(data).is_err() {
+			handle_alloc_error(Layout::new::<T>());
+		}
+	}
+
+	/// Moves `data` into the bucket of its VTable, upcasting it to `V`, and
+	/// returning an error if the allocation fails.
+	pub fn try_push<'v, T>(&mut self, data: T) -> Result<(), AllocError>
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let vtable = NonNull::from(T::STATIC_VTABLE);
+
+		let index = match self.buckets().iter().position(|b| b.vtable == vtable) {
+			Some(index) => index,
+			None => self.add_bucket(vtable, MemoryLayout::new::<T>())?,
+		};
+
+		let bucket = unsafe { &mut *self.buckets.as_ptr().add(index) };
+		if bucket.len == bucket.cap {
+			bucket.grow(&self.alloc)?;
+		}
+
+		unsafe { (bucket.value_ptr(bucket.len) as *mut T).write(data) };
+		bucket.len += 1;
+		self.len += 1;
+		Ok(())
+	}
+
+	/// Calls `f` with an immutable reference to each value, visiting all
+	/// values of a bucket before the next bucket.
+	#[inline]
+	pub fn for_each<F>(&self, mut f: F)
+	where
+		F: FnMut(DynRef<'_, V>),
+	{
+		for bucket in self.buckets() {
+			for i in 0..bucket.len {
+				f(DynRef {
+					ptr: bucket.dyn_ptr(i),
+					_lt: PhantomData,
+				});
+			}
+		}
+	}
+
+	/// Calls `f` with a mutable reference to each value, visiting all
+	/// values of a bucket before the next bucket.
+	#[inline]
+	pub fn for_each_mut<F>(&mut self, mut f: F)
+	where
+		F: FnMut(DynRefMut<'_, V>),
+	{
+		for bucket in self.buckets() {
+			for i in 0..bucket.len {
+				f(DynRefMut {
+					ptr: bucket.dyn_ptr(i),
+					_lt: PhantomData,
+				});
+			}
+		}
+	}
+
+	/// Drops every value for which `f` returns `false`.
+	///
+	/// Removed values are replaced by the last value of their bucket, so the
+	/// order of values within a bucket is not preserved.
+	pub fn retain<F>(&mut self, mut f: F)
+	where
+		F: FnMut(DynRefMut<'_, V>) -> bool,
+	{
+		for b in 0..self.buckets_len {
+			let bucket = unsafe { &mut *self.buckets.as_ptr().add(b) };
+			let mut i = 0;
+
+			while i < bucket.len {
+				let keep = f(DynRefMut {
+					ptr: bucket.dyn_ptr(i),
+					_lt: PhantomData,
+				});
+
+				if keep {
+					i += 1;
+					continue
+				}
+
+				bucket.len -= 1;
+				self.len -= 1;
+
+				unsafe {
+					// The removed value is moved out of the bucket before it is
+					// dropped, so a panicking drop cannot cause a double drop.
+					if i != bucket.len {
+						ptr::swap_nonoverlapping(
+							bucket.value_ptr(i) as *mut u8,
+							bucket.value_ptr(bucket.len) as *mut u8,
+							bucket.layout.size,
+						);
+					}
+
+					bucket.vtable.as_ref().virtual_drop(bucket.value_ptr(bucket.len));
+				}
+			}
+		}
+	}
+
+	/// Drops all values in the collection, keeping its allocated memory.
+	pub fn clear(&mut self) {
+		self.len = 0;
+
+		for b in 0..self.buckets_len {
+			unsafe { (*self.buckets.as_ptr().add(b)).clear() };
+		}
+	}
+
+	#[inline(always)]
+	fn buckets(&self) -> &[Bucket<V>] {
+		unsafe { slice::from_raw_parts(self.buckets.as_ptr(), self.buckets_len) }
+	}
+
+	/// Adds an empty bucket and returns its index.
+	#[cold]
+	fn add_bucket(
+		&mut self,
+		vtable: NonNull<V::VTable>,
+		layout: MemoryLayout,
+	) -> Result<usize, AllocError>
+	where
+		A: Allocator,
+	{
+		if self.buckets_len == self.buckets_cap {
+			let cap = usize::max(4, self.buckets_cap.saturating_mul(2));
+			let buckets_layout: MemoryLayout = Layout::array::<Bucket<V>>(cap)
+				.map_err(|_| AllocError)?
+				.into();
+			let buckets = allocate(&self.alloc, buckets_layout)?.cast::<Bucket<V>>();
+
+			unsafe {
+				ptr::copy_nonoverlapping(self.buckets.as_ptr(), buckets.as_ptr(), self.buckets_len);
+				self.dealloc_buckets();
+			}
+
+			self.buckets = buckets;
+			self.buckets_cap = cap;
+		}
+
+		unsafe {
+			self.buckets.as_ptr().add(self.buckets_len).write(Bucket {
+				vtable,
+				layout,
+				data: NonNull::new_unchecked(layout.align as *mut u8),
+				cap: 0,
+				len: 0,
+			});
+		}
+
+		self.buckets_len += 1;
+		Ok(self.buckets_len - 1)
+	}
+
+	unsafe fn dealloc_buckets(&self) {
+		let layout = Layout::array::<Bucket<V>>(self.buckets_cap).unwrap_unchecked();
+		deallocate(&self.alloc, self.buckets.cast(), layout.into());
+	}
+}
+
+impl<V, A> Drop for DynBatch<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	fn drop(&mut self) {
+		self.clear();
+
+		unsafe {
+			for bucket in self.buckets() {
+				deallocate(&self.alloc, bucket.data, bucket.data_layout());
+			}
+
+			self.dealloc_buckets();
+		}
+	}
+}
@@ -6,22 +6,27 @@ use core::{
 	ffi::c_void,
 	marker::PhantomData,
 	mem::{self, MaybeUninit},
-	ptr::NonNull,
+	ops::Deref,
+	pin::Pin,
+	ptr::{self, NonNull},
 };
 
 use std_alloc::{alloc::handle_alloc_error, boxed::Box};
 
 use crate::{
-	alloc::{AllocError, Allocator, Deallocator, GlobalAllocator, MemoryLayout},
+	alloc::{allocate, AllocError, Allocator, BoxDeallocator, GlobalAllocator, MemoryLayout},
 	AsDyn,
 	AssociatedDrop,
+	AssociatedHeader,
 	AssociatedLayout,
 	DynPtr,
 	DynRef,
+	DynRefCallProxy,
 	DynRefMut,
 	DynTrait,
 	SubTable,
 	VTable,
+	VTableHeader,
 	VTableRepr,
 };
 
@@ -30,9 +35,9 @@ use crate::{
 #[cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
 pub struct DynBox<V, A = GlobalAllocator>
 where
-	A: Deallocator,
+	A: BoxDeallocator<V::VTable>,
 	V: VTableRepr + ?Sized,
-	V::VTable: AssociatedDrop + AssociatedLayout,
+	V::VTable: AssociatedDrop,
 {
 	alloc: A,
 	ptr: DynPtr<V>,
@@ -40,28 +45,28 @@ where
 
 unsafe impl<V, A> Send for DynBox<V, A>
 where
-	A: Deallocator,
+	A: BoxDeallocator<V::VTable>,
 	V: VTableRepr + ?Sized,
-	V::VTable: AssociatedDrop + AssociatedLayout,
+	V::VTable: AssociatedDrop,
 	<V::VTable as VTable>::Bounds: Send,
 {
 }
 
 unsafe impl<V, A> Sync for DynBox<V, A>
 where
-	A: Deallocator,
+	A: BoxDeallocator<V::VTable>,
 	V: VTableRepr + ?Sized,
-	V::VTable: AssociatedDrop + AssociatedLayout,
+	V::VTable: AssociatedDrop,
 	<V::VTable as VTable>::Bounds: Sync,
 {
 }
 
 unsafe impl<R, V, A> AsDyn<R> for DynBox<V, A>
 where
-	A: Deallocator,
-	R: VTableRepr + ?Sized,
+	A: BoxDeallocator<V::VTable>,
+	R: ?Sized,
 	V: VTableRepr + ?Sized,
-	V::VTable: AssociatedDrop + AssociatedLayout,
+	V::VTable: AssociatedDrop,
 {
 	type Repr = V;
 
@@ -72,14 +77,14 @@ where
 
 	#[inline(always)]
 	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
-		self.ptr.vtable
+		self.ptr.vtable.as_ptr()
 	}
 
 	fn dyn_dealloc(self) {
 		unsafe {
-			self.alloc.deallocate(
-				NonNull::new_unchecked(self.ptr.ptr as *mut _ as *mut u8),
-				(*self.ptr.vtable).virtual_layout(),
+			self.alloc.deallocate_value(
+				NonNull::new_unchecked(self.ptr.ptr as *mut u8),
+				self.ptr.vtable.as_ref(),
 			);
 		}
 
@@ -117,6 +122,33 @@ where
 		DynBox::new_in(data, GlobalAllocator)
 	}
 
+	/// Allocates memory using the global allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V` and pinning it.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::*;
+	/// use core::pin::Pin;
+	///
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let x: Pin<DynBox<dyn MyTrait>> = DynBox::pin(0u8);
+	/// ```
+	#[inline]
+	pub fn pin<'v, T>(data: T) -> Pin<Self>
+	where
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		DynBox::into_pin(DynBox::new(data))
+	}
+
 	/// Constructs a `DynBox` from a raw dynptr in the global allocator.
 	///
 	/// After calling this function, the raw dynptr is considered to be
@@ -148,9 +180,9 @@ where
 
 impl<V, A> DynBox<V, A>
 where
-	A: Deallocator,
+	A: BoxDeallocator<V::VTable>,
 	V: VTableRepr + ?Sized,
-	V::VTable: AssociatedDrop + AssociatedLayout,
+	V::VTable: AssociatedDrop,
 {
 	/// Allocates memory using the given allocator and moves `data` into
 	/// the allocated memory, upcasting it to `V`.
@@ -209,7 +241,7 @@ where
 		let layout = MemoryLayout::new::<T>();
 
 		unsafe {
-			let ptr = alloc.allocate(layout)?.cast::<T>().as_ptr();
+			let ptr = allocate(&alloc, layout)?.cast::<T>().as_ptr();
 			ptr.write(data);
 
 			Ok(Self::from_raw_in(DynPtr::new(ptr), alloc))
@@ -273,13 +305,77 @@ where
 	pub fn upcast<U>(b: Self) -> DynBox<U, A>
 	where
 		U: VTableRepr + ?Sized,
-		U::VTable: AssociatedDrop + AssociatedLayout,
+		U::VTable: AssociatedDrop,
 		V::VTable: SubTable<U::VTable>,
+		A: BoxDeallocator<U::VTable>,
 	{
 		let (ptr, alloc) = Self::into_raw_with_allocator(b);
 		unsafe { DynBox::from_raw_in(DynPtr::upcast(ptr), alloc) }
 	}
 
+	/// Pins a `DynBox`. The wrapped value will not be moved until it is
+	/// dropped.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::*;
+	/// use core::pin::Pin;
+	///
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let x: DynBox<dyn MyTrait> = DynBox::new(0u8);
+	/// let x: Pin<DynBox<dyn MyTrait>> = DynBox::into_pin(x);
+	/// ```
+	#[inline(always)]
+	pub fn into_pin(b: Self) -> Pin<Self> {
+		// SAFETY: the wrapped value cannot be moved out of a DynBox
+		// without leaking it, which is not possible through a `Pin`.
+		unsafe { Pin::new_unchecked(b) }
+	}
+
+	/// Immutably borrows the wrapped value of a pinned `DynBox`,
+	/// keeping it pinned.
+	#[inline(always)]
+	pub fn as_pin_ref(b: &Pin<Self>) -> Pin<DynRef<'_, V>> {
+		// SAFETY: the value referenced by the returned pin is already pinned.
+		unsafe { Pin::new_unchecked(DynRef::from_raw(Pin::get_ref(b.as_ref()).ptr)) }
+	}
+
+	/// Mutably borrows the wrapped value of a pinned `DynBox`,
+	/// keeping it pinned.
+	///
+	/// # Examples
+	/// Call a method taking `self: Pin<&mut Self>`:
+	///
+	/// ```
+	/// # use dyntable::*;
+	/// use core::pin::Pin;
+	///
+	/// #[dyntable]
+	/// trait Counter {
+	///     extern "C" fn next(self: Pin<&mut Self>) -> u32;
+	/// }
+	///
+	/// impl Counter for u32 {
+	///     extern "C" fn next(mut self: Pin<&mut Self>) -> u32 {
+	///         *self += 1;
+	///         *self
+	///     }
+	/// }
+	///
+	/// let mut counter: Pin<DynBox<dyn Counter>> = DynBox::pin(0u32);
+	/// assert_eq!(DynBox::as_pin_mut(&mut counter).as_mut().next(), 1);
+	/// ```
+	#[inline(always)]
+	pub fn as_pin_mut(b: &mut Pin<Self>) -> Pin<DynRefMut<'_, V>> {
+		// SAFETY: the value referenced by the returned pin is already pinned,
+		// and is mutably borrowed for the lifetime of the returned pin.
+		unsafe { Pin::new_unchecked(DynRefMut::from_raw(Pin::get_ref(b.as_ref()).ptr)) }
+	}
+
 	/// Leak a DynBox, returning its DynPtr and Allocator
 	///
 	/// # Examples
@@ -328,6 +424,57 @@ where
 		Self::into_raw_with_allocator(b).0
 	}
 
+	/// Moves the wrapped value into memory allocated by `new_alloc`,
+	/// deallocating its original memory.
+	///
+	/// This allows a value to outlive its original allocator, such as one
+	/// provided by a plugin which is about to be unloaded.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::*;
+	/// use dyntable::alloc::{DynAllocator, GlobalAllocator};
+	///
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let x: DynBox<dyn MyTrait, DynAllocator> = DynBox::new_in(0u8, DynAllocator::global());
+	/// let x: DynBox<dyn MyTrait> = DynBox::reallocate_in(x, GlobalAllocator);
+	/// ```
+	pub fn reallocate_in<B>(b: Self, new_alloc: B) -> DynBox<V, B>
+	where
+		B: Allocator,
+		V::VTable: AssociatedLayout,
+	{
+		let (ptr, alloc) = Self::into_raw_with_allocator(b);
+
+		unsafe {
+			let vtable = ptr.vtable.as_ref();
+			let layout = vtable.virtual_layout();
+
+			let memory = match allocate(&new_alloc, layout) {
+				Ok(memory) => memory,
+				Err(_) => handle_alloc_error(layout.into()),
+			};
+
+			ptr::copy_nonoverlapping(ptr.ptr as *const u8, memory.as_ptr(), layout.size);
+			alloc.deallocate_value(NonNull::new_unchecked(ptr.ptr as *mut u8), vtable);
+
+			DynBox::from_raw_in(
+				DynPtr {
+					ptr: memory.as_ptr() as *mut c_void,
+					vtable: ptr.vtable,
+				},
+				new_alloc,
+			)
+		}
+	}
+
 	/// Immutably borrows the wrapped value.
 	#[inline(always)]
 	pub fn borrow(b: &Self) -> DynRef<V> {
@@ -337,6 +484,38 @@ where
 		}
 	}
 
+	/// Returns a reference to the VTable of the wrapped value.
+	///
+	/// This can be used to read VTable data fields, such as
+	/// [associated constants](crate::dyntable#associated-constants).
+	#[inline(always)]
+	pub fn vtable(b: &Self) -> &V::VTable {
+		unsafe { b.ptr.vtable.as_ref() }
+	}
+
+	/// Reads the header of the VTable, which may be from an older or newer
+	/// version of the trait.
+	#[inline(always)]
+	pub fn header(b: &Self) -> VTableHeader
+	where
+		V::VTable: AssociatedHeader,
+	{
+		unsafe { DynPtr::header(b.ptr) }
+	}
+
+	/// Returns `true` if the VTable contains an entry ending at `end`.
+	/// Use [`entry_end!`](crate::entry_end) to find the end of an entry.
+	///
+	/// Entries missing from the VTable must not be called.
+	/// See [VTable Headers](crate::dyntable#vtable-headers).
+	#[inline(always)]
+	pub fn has_entry(b: &Self, end: usize) -> bool
+	where
+		V::VTable: AssociatedHeader,
+	{
+		Self::header(b).contains(end)
+	}
+
 	/// Mutably borrows the wrapped value.
 	#[inline(always)]
 	pub fn borrow_mut(b: &mut Self) -> DynRefMut<V> {
@@ -373,22 +552,313 @@ where
 	}
 }
 
+// `DerefMut` is intentionally not implemented, as swapping the targets of two
+// boxes would mismatch their allocators.
+impl<V, A> Deref for DynBox<V, A>
+where
+	A: BoxDeallocator<V::VTable>,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	// The proxy's lifetime only restricts how long the proxy may be held,
+	// which is already bound by the returned reference.
+	type Target = DynRefCallProxy<'static, V>;
+
+	#[inline(always)]
+	fn deref(&self) -> &Self::Target {
+		DynRefCallProxy::from_raw(&self.ptr)
+	}
+}
+
+impl<V, A> From<DynBox<V, A>> for Pin<DynBox<V, A>>
+where
+	A: BoxDeallocator<V::VTable>,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	/// Pins a `DynBox`, see [`DynBox::into_pin`].
+	#[inline(always)]
+	fn from(value: DynBox<V, A>) -> Self {
+		DynBox::into_pin(value)
+	}
+}
+
 impl<V, A> Drop for DynBox<V, A>
 where
-	A: Deallocator,
+	A: BoxDeallocator<V::VTable>,
 	V: VTableRepr + ?Sized,
-	V::VTable: AssociatedDrop + AssociatedLayout,
+	V::VTable: AssociatedDrop,
 {
 	fn drop(&mut self) {
 		unsafe {
-			let vtable = &*self.ptr.vtable;
+			let vtable = self.ptr.vtable.as_ref();
 			vtable.virtual_drop(self.ptr.ptr);
 
-			let layout = vtable.virtual_layout();
-			if !layout.is_zero_sized() {
-				self.alloc
-					.deallocate(NonNull::new_unchecked(self.ptr.ptr as *mut u8), layout);
-			}
+			self.alloc
+				.deallocate_value(NonNull::new_unchecked(self.ptr.ptr as *mut u8), vtable);
+		}
+	}
+}
+
+/// Referencing [`NoDrop::ERROR`] fails compilation if `T` needs to be
+/// dropped, and so cannot be moved into a `NoDropBox`.
+struct NoDrop<T>(PhantomData<T>);
+
+impl<T> NoDrop<T> {
+	const ERROR: () = if mem::needs_drop::<T>() {
+		panic!("values moved into a `NoDropBox` must not need to be dropped")
+	};
+}
+
+/// An FFI safe Box for dyntable traits without a `drop` function, which
+/// deallocates its value without dropping it.
+///
+/// This allows owning values of compact foreign VTables declared with
+/// `drop = none`, which usually only hold plain data. Values moved into a
+/// `NoDropBox` from Rust must not need to be dropped, which is checked at
+/// compile time. Foreign values adopted using [`NoDropBox::from_raw_in`]
+/// are never dropped, as if passed to [`mem::forget`].
+///
+/// # Examples
+/// ```
+/// # use dyntable::{*, boxed::NoDropBox};
+/// #[dyntable(drop = none)]
+/// trait Point {
+///     extern "C" fn x(&self) -> i32;
+/// }
+///
+/// impl Point for (i32, i32) {
+///     extern "C" fn x(&self) -> i32 {
+///         self.0
+///     }
+/// }
+///
+/// let point: NoDropBox<dyn Point> = NoDropBox::new((1, 2));
+/// assert_eq!(point.x(), 1);
+/// ```
+#[repr(C)]
+#[cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+pub struct NoDropBox<V, A = GlobalAllocator>
+where
+	A: BoxDeallocator<V::VTable>,
+	V: VTableRepr + ?Sized,
+{
+	alloc: A,
+	ptr: DynPtr<V>,
+}
+
+unsafe impl<V, A> Send for NoDropBox<V, A>
+where
+	A: BoxDeallocator<V::VTable>,
+	V: VTableRepr + ?Sized,
+	<V::VTable as VTable>::Bounds: Send,
+{
+}
+
+unsafe impl<V, A> Sync for NoDropBox<V, A>
+where
+	A: BoxDeallocator<V::VTable>,
+	V: VTableRepr + ?Sized,
+	<V::VTable as VTable>::Bounds: Sync,
+{
+}
+
+unsafe impl<R, V, A> AsDyn<R> for NoDropBox<V, A>
+where
+	A: BoxDeallocator<V::VTable>,
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+{
+	type Repr = V;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.ptr.ptr
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		self.ptr.vtable.as_ptr()
+	}
+
+	fn dyn_dealloc(self) {
+		// dropping only deallocates the value
+		drop(self);
+	}
+}
+
+impl<V> NoDropBox<V, GlobalAllocator>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedLayout,
+{
+	/// Allocates memory using the global allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	#[inline]
+	pub fn new<'v, T>(data: T) -> Self
+	where
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let () = NoDrop::<T>::ERROR;
+		NoDropBox::new_in(data, GlobalAllocator)
+	}
+
+	/// Constructs a `NoDropBox` from a raw dynptr in the global allocator.
+	///
+	/// After calling this function, the raw dynptr is considered to be
+	/// owned by the `NoDropBox` and will be deallocated as such.
+	///
+	/// # Safety
+	/// The pointer `ptr` must be an owned dynptr to memory allocated
+	/// by the rust global allocator.
+	#[inline(always)]
+	pub unsafe fn from_raw(ptr: DynPtr<V>) -> Self {
+		Self::from_raw_in(ptr, GlobalAllocator)
+	}
+}
+
+impl<V, A> NoDropBox<V, A>
+where
+	A: BoxDeallocator<V::VTable>,
+	V: VTableRepr + ?Sized,
+{
+	/// Allocates memory using the given allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	#[inline]
+	pub fn new_in<'v, T>(data: T, alloc: A) -> Self
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let () = NoDrop::<T>::ERROR;
+
+		match Self::try_new_in(data, alloc) {
+			Ok(nodropbox) => nodropbox,
+			Err(_) => handle_alloc_error(Layout::new::<MaybeUninit<T>>()),
+		}
+	}
+
+	/// Allocates memory using the given allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V`, and returning an error
+	/// if the allocation fails.
+	#[inline]
+	pub fn try_new_in<'v, T>(data: T, alloc: A) -> Result<Self, AllocError>
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let () = NoDrop::<T>::ERROR;
+		let layout = MemoryLayout::new::<T>();
+
+		unsafe {
+			let ptr = allocate(&alloc, layout)?.cast::<T>().as_ptr();
+			ptr.write(data);
+
+			Ok(Self::from_raw_in(DynPtr::new(ptr), alloc))
+		}
+	}
+
+	/// Constructs a `NoDropBox` from a raw dynptr in the given allocator.
+	///
+	/// After calling this function, the raw dynptr is considered to be
+	/// owned by the `NoDropBox` and will be deallocated as such.
+	///
+	/// # Safety
+	/// The pointer `ptr` must be an owned dynptr to memory allocated
+	/// by the allocator `alloc`.
+	#[inline(always)]
+	pub unsafe fn from_raw_in(ptr: DynPtr<V>, alloc: A) -> Self {
+		Self { ptr, alloc }
+	}
+
+	/// Upcast the box to a bounded dyntrait box.
+	#[inline(always)]
+	pub fn upcast<U>(b: Self) -> NoDropBox<U, A>
+	where
+		U: VTableRepr + ?Sized,
+		V::VTable: SubTable<U::VTable>,
+		A: BoxDeallocator<U::VTable>,
+	{
+		let (ptr, alloc) = Self::into_raw_with_allocator(b);
+		unsafe { NoDropBox::from_raw_in(DynPtr::upcast(ptr), alloc) }
+	}
+
+	/// Leak a NoDropBox, returning its DynPtr and Allocator
+	#[inline(always)]
+	pub fn into_raw_with_allocator(b: Self) -> (DynPtr<V>, A) {
+		// SAFETY: the original value is forgotten
+		let alloc = unsafe { (&b.alloc as *const A).read() };
+		let ptr = b.ptr;
+
+		mem::forget(b);
+
+		(ptr, alloc)
+	}
+
+	/// Leak a NoDropBox into a DynPtr
+	#[inline(always)]
+	pub fn into_raw(b: Self) -> DynPtr<V> {
+		Self::into_raw_with_allocator(b).0
+	}
+
+	/// Immutably borrows the wrapped value.
+	#[inline(always)]
+	pub fn borrow(b: &Self) -> DynRef<'_, V> {
+		DynRef {
+			ptr: b.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Mutably borrows the wrapped value.
+	#[inline(always)]
+	pub fn borrow_mut(b: &mut Self) -> DynRefMut<'_, V> {
+		DynRefMut {
+			ptr: b.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Returns a reference to the VTable of the wrapped value.
+	#[inline(always)]
+	pub fn vtable(b: &Self) -> &V::VTable {
+		unsafe { b.ptr.vtable.as_ref() }
+	}
+}
+
+impl<V, A> Deref for NoDropBox<V, A>
+where
+	A: BoxDeallocator<V::VTable>,
+	V: VTableRepr + ?Sized,
+{
+	type Target = DynRefCallProxy<'static, V>;
+
+	#[inline(always)]
+	fn deref(&self) -> &Self::Target {
+		DynRefCallProxy::from_raw(&self.ptr)
+	}
+}
+
+impl<V, A> Drop for NoDropBox<V, A>
+where
+	A: BoxDeallocator<V::VTable>,
+	V: VTableRepr + ?Sized,
+{
+	fn drop(&mut self) {
+		unsafe {
+			self.alloc.deallocate_value(
+				NonNull::new_unchecked(self.ptr.ptr as *mut u8),
+				self.ptr.vtable.as_ref(),
+			);
 		}
 	}
 }
@@ -0,0 +1,1095 @@
+//! C header generation for dyntable traits.
+//!
+//! Traits annotated with `#[dyntable(c_header = true)]` can be added to a
+//! [`CHeader`], which generates C definitions of their VTables and dynptrs
+//! matching the layout of the Rust definitions. This is intended to be used
+//! from a build script or test, writing the header next to the C sources
+//! that include it.
+//!
+//! # Examples
+//! ```
+//! # use dyntable::{*, cheader::CHeader};
+//! #[dyntable(c_header = true)]
+//! trait Counter {
+//!     extern "C" fn increment(&mut self, amount: u32) -> u32;
+//! }
+//!
+//! let header = CHeader::new().add::<dyn Counter>().generate("COUNTER_H");
+//! assert!(header.contains("uint32_t (*increment)(void *self, uint32_t amount);"));
+//! assert!(header.contains("struct CounterDynPtr {"));
+//! ```
+//!
+//! Types used as method parameters, return values or associated constants
+//! must implement [`CType`]. It is implemented for primitive types, pointers,
+//! references and dyntable containers, and may be implemented for your own
+//! `#[repr(C)]` types. Optional dyntable containers use the same C struct as
+//! the container, with a null `vtable` representing `None`.
+//!
+//! # C++
+//! When included from C++, the header additionally defines wrapper classes
+//! for each trait:
+//!
+//! - `{Trait}Ref` and `{Trait}Mut` - borrowing views mirroring [`DynRef`]
+//!   and [`DynRefMut`], forwarding member functions to the VTable. Views
+//!   can be upcast to bounded traits using `as_{Bound}()`.
+//! - `{Trait}Box<Dealloc>` - an owning class mirroring [`DynBox`], generated
+//!   when the VTable has a drop function and an embedded layout. Its
+//!   destructor drops the object and deallocates it using `Dealloc`, which
+//!   defaults to `DyntableFreeDeallocator` (`free`). Methods taking `self`
+//!   by value can be called on an rvalue box. Like [`DynBox`], helpers are
+//!   static functions (`into_raw`, `borrow`, `borrow_mut` and `vtable`)
+//!   to avoid conflicting with the trait's methods.
+//! - `{Trait}Impl<T>` - builds a VTable for the C++ type `T`, which must
+//!   have member functions (and static members for associated constants)
+//!   matching the trait's entries, as well as implement all bounded traits.
+//!   It may be used as a CRTP base class of `T`. The deallocation function
+//!   of VTables created with the `dealloc` option frees the value with
+//!   `free`, matching `new_box`.
+//!
+//! ```cpp
+//! struct MyCounter {
+//!     uint32_t value = 0;
+//!
+//!     uint32_t increment(uint32_t amount) {
+//!         return value += amount;
+//!     }
+//! };
+//!
+//! MyCounter counter;
+//! CounterMut counter_ref = CounterImpl<MyCounter>::borrow_mut(counter);
+//! counter_ref.increment(1);
+//! ```
+
+#![cfg(feature = "alloc")]
+#![cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+
+use core::ffi::c_void;
+
+use std_alloc::{
+	collections::BTreeSet,
+	format,
+	string::{String, ToString},
+	vec,
+	vec::Vec,
+};
+
+use crate::{
+	__private::{SendSyncVTable, SendVTable, SyncVTable},
+	alloc::{DynAllocator, GlobalAllocator, MemoryLayout},
+	owned::DynOwned,
+	AssociatedDealloc,
+	AssociatedDrop,
+	AssociatedLayout,
+	DynBox,
+	DynPtr,
+	DynRef,
+	DynRefMut,
+	DynSelf,
+	VTable,
+	VTableHeader,
+	VTableRepr,
+};
+
+/// A type with a C representation, used to generate C headers.
+pub trait CType {
+	/// Returns the name of the C type, adding any definitions it
+	/// requires to `header`.
+	fn c_type(header: &mut CHeader) -> String;
+}
+
+/// A VTable with a C representation.
+///
+/// # Notes
+/// This trait is implemented by the [`dyntable`](crate::dyntable) macro
+/// when the `c_header` option is enabled.
+pub trait CVTable: VTable {
+	/// The name of the dyntrait, used as a prefix for C++ class names.
+	const TRAIT_NAME: &'static str;
+	/// The name of the VTable's C struct.
+	const C_NAME: &'static str;
+	/// The name of the C struct of a dynptr to this VTable.
+	const C_PTR_NAME: &'static str;
+
+	/// Returns the field declarations of the VTable's C struct in order,
+	/// adding any definitions they require to `header`.
+	fn c_fields(header: &mut CHeader) -> Vec<String>;
+
+	/// Returns a description of the VTable used to generate its C++
+	/// wrapper classes, adding any definitions it requires to `header`.
+	fn cpp_trait(header: &mut CHeader) -> CppTrait;
+}
+
+/// Description of a dyntrait's VTable, used to generate C++ wrapper classes.
+pub struct CppTrait {
+	/// C calling convention of the drop function, if the VTable has one.
+	pub drop: Option<&'static str>,
+	/// The VTable has an embedded layout.
+	pub layout: bool,
+	/// C calling convention of the deallocation function, if the VTable
+	/// has one.
+	pub dealloc: Option<&'static str>,
+	/// Version of the VTable header and the name of the last field of
+	/// the VTable, if the header is enabled.
+	pub header: Option<(u32, &'static str)>,
+	/// Entries of the VTable, in declaration order.
+	pub entries: Vec<CppEntry>,
+	/// Parent-child relations between nested subtables, used to upcast
+	/// through a direct subtable. Given as trait names.
+	pub upcasts: Vec<(&'static str, &'static str)>,
+}
+
+/// An entry of a [`CppTrait`].
+pub enum CppEntry {
+	/// A direct subtable.
+	Subtable {
+		/// Name of the VTable field.
+		field: &'static str,
+		/// Name of the subtable's trait.
+		name: &'static str,
+		/// The subtable is stored behind a pointer.
+		pointer: bool,
+	},
+	/// A method or static function.
+	Method(CppMethod),
+	/// An associated constant.
+	Const {
+		/// Name of the VTable field.
+		field: &'static str,
+		/// Name of the constant.
+		name: &'static str,
+	},
+}
+
+/// A method or static function of a [`CppTrait`].
+pub struct CppMethod {
+	/// Name of the method.
+	pub name: &'static str,
+	/// Name of the VTable field.
+	pub field: &'static str,
+	/// C calling convention of the method, which may be empty.
+	pub convention: &'static str,
+	/// `None` for static functions.
+	pub receiver: Option<CppReceiver>,
+	/// C return type.
+	pub output: String,
+	/// C types and names of the parameters, not including the receiver.
+	/// Unnamed parameters have empty names.
+	pub params: Vec<(String, &'static str)>,
+	/// The VTable entry is nullable.
+	pub optional: bool,
+}
+
+/// The receiver of a [`CppMethod`].
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum CppReceiver {
+	/// `&self` or `Pin<&Self>`
+	Ref,
+	/// `&mut self` or `Pin<&mut Self>`
+	Mut,
+	/// `self`
+	Value,
+}
+
+/// Generator for a C header containing dyntable VTables and dynptrs.
+#[derive(Default)]
+pub struct CHeader {
+	defined: BTreeSet<String>,
+	declarations: Vec<String>,
+	definitions: Vec<String>,
+	classes: Vec<String>,
+}
+
+impl CHeader {
+	/// Create an empty header.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add the VTable of the given dyntrait, and a dynptr to it, to the header.
+	/// Bounded traits are added as well.
+	pub fn add<V>(&mut self) -> &mut Self
+	where
+		V: VTableRepr + ?Sized,
+		V::VTable: CVTable,
+	{
+		<DynPtr<V> as CType>::c_type(self);
+		self
+	}
+
+	/// Add the given VTable's C struct, the C struct of a dynptr to it, and
+	/// their C++ wrapper classes to the header, returning the name of the
+	/// VTable struct.
+	pub fn vtable<V: CVTable>(&mut self) -> String {
+		self.define_struct(V::C_NAME, |header| {
+			header.define_struct(V::C_PTR_NAME, |_| {
+				vec![
+					c_field("void *", "ptr"),
+					c_field(&format!("{} const *", V::C_NAME), "vtable"),
+				]
+			});
+
+			let fields = V::c_fields(header);
+			let cpp = V::cpp_trait(header);
+			header.classes.push(cpp_classes::<V>(&cpp));
+			fields
+		})
+	}
+
+	/// Add a struct named `name` to the header, with the fields returned by
+	/// `fields`, returning `name`.
+	///
+	/// Structs are declared at the start of the header, so they may be used
+	/// behind pointers or in function signatures before they are defined.
+	pub fn define_struct(
+		&mut self,
+		name: &str,
+		fields: impl FnOnce(&mut Self) -> Vec<String>,
+	) -> String {
+		self.define(name, |header| {
+			header
+				.declarations
+				.push(format!("typedef struct {name} {name};\n"));
+			c_struct(name, &fields(header))
+		})
+	}
+
+	/// Add a definition of the type `name` to the header, returning `name`.
+	///
+	/// `define` is only called the first time a name is defined. Any
+	/// definitions it adds are placed before its own definition.
+	pub fn define(&mut self, name: &str, define: impl FnOnce(&mut Self) -> String) -> String {
+		if self.defined.insert(name.to_string()) {
+			let definition = define(self);
+			self.definitions.push(definition);
+		}
+
+		name.to_string()
+	}
+
+	/// Generate the header, using `include_guard` as the name of its
+	/// include guard macro.
+	pub fn generate(&self, include_guard: &str) -> String {
+		let mut header = format!(
+			"// Generated by dyntable. Do not edit.\n\n\
+			 #ifndef {include_guard}\n\
+			 #define {include_guard}\n\n\
+			 #include <stdbool.h>\n\
+			 #include <stddef.h>\n\
+			 #include <stdint.h>\n\n\
+			 // calling convention of `extern \"system\"` functions\n\
+			 #ifndef DYNTABLE_SYSTEM\n\
+			 #if defined(_WIN32) && (defined(_M_IX86) || defined(__i386__))\n\
+			 #define DYNTABLE_SYSTEM __stdcall\n\
+			 #else\n\
+			 #define DYNTABLE_SYSTEM\n\
+			 #endif\n\
+			 #endif\n"
+		);
+
+		if !self.declarations.is_empty() {
+			header.push('\n');
+		}
+
+		for declaration in &self.declarations {
+			header.push_str(declaration);
+		}
+
+		for definition in &self.definitions {
+			header.push('\n');
+			header.push_str(definition);
+		}
+
+		if !self.classes.is_empty() {
+			header.push_str(CPP_PREAMBLE);
+
+			for class in &self.classes {
+				header.push('\n');
+				header.push_str(class);
+			}
+
+			header.push_str("\n#endif // __cplusplus\n");
+		}
+
+		header.push_str(&format!("\n#endif // {include_guard}\n"));
+		header
+	}
+}
+
+/// Format a C struct definition.
+pub fn c_struct(name: &str, fields: &[String]) -> String {
+	let mut definition = format!("struct {name} {{\n");
+
+	for field in fields {
+		definition.push('\t');
+		definition.push_str(field);
+		definition.push('\n');
+	}
+
+	definition.push_str("};\n");
+	definition
+}
+
+/// Format a C variable or parameter declaration.
+pub fn c_param(ty: &str, name: &str) -> String {
+	match (name, ty.ends_with('*')) {
+		("", _) => ty.to_string(),
+		(_, true) => format!("{ty}{name}"),
+		(_, false) => format!("{ty} {name}"),
+	}
+}
+
+/// Format a C struct field declaration.
+pub fn c_field(ty: &str, name: &str) -> String {
+	format!("{};", c_param(ty, name))
+}
+
+/// Format a C function pointer field declaration.
+///
+/// `convention` is the C calling convention attribute of the function,
+/// which may be empty.
+pub fn c_fn_field(name: &str, convention: &str, output: &str, params: &[String]) -> String {
+	let params = match params.is_empty() {
+		true => String::from("void"),
+		false => params.join(", "),
+	};
+
+	match convention {
+		"" => format!("{} (*{name})({params});", output),
+		_ => format!("{} ({convention} *{name})({params});", output),
+	}
+}
+
+const CPP_PREAMBLE: &str = "\n\
+	#ifdef __cplusplus\n\
+	#include <cstddef>\n\
+	#include <cstdlib>\n\
+	#include <new>\n\
+	#include <utility>\n\
+	\n\
+	// Deallocator for objects allocated with `malloc`.\n\
+	struct DyntableFreeDeallocator {\n\
+	\ttemplate <typename Layout>\n\
+	\tvoid deallocate(void *ptr, Layout const &) const {\n\
+	\t\tstd::free(ptr);\n\
+	\t}\n\
+	};\n";
+
+/// Format the C++ parameter declarations and call arguments of a method.
+fn cpp_params(method: &CppMethod) -> (Vec<String>, Vec<String>) {
+	method
+		.params
+		.iter()
+		.enumerate()
+		.map(|(i, (ty, name))| {
+			let name = match *name {
+				"" => format!("arg{i}"),
+				name => name.to_string(),
+			};
+
+			(c_param(ty, &name), name)
+		})
+		.unzip()
+}
+
+/// Format a C++ call expression of a VTable entry, through the dynptr
+/// expression `ptr`.
+fn cpp_call(method: &CppMethod, ptr: &str, args: &[String]) -> String {
+	let mut call_args = vec![format!("{ptr}.ptr")];
+	call_args.extend_from_slice(args);
+	format!(
+		"{ptr}.vtable->{}({})",
+		method.field,
+		call_args.join(", ")
+	)
+}
+
+/// Generate the C++ wrapper classes of a VTable.
+///
+/// - `{Trait}Ref` and `{Trait}Mut` are borrowing views, mirroring
+///   [`DynRef`] and [`DynRefMut`].
+/// - `{Trait}Box<Dealloc>` owns its object, mirroring [`DynBox`]. It is only
+///   generated for VTables with a drop function and an embedded layout.
+/// - `{Trait}Impl<T>` builds a VTable from a C++ type implementing the
+///   trait's methods.
+fn cpp_classes<V: CVTable>(cpp: &CppTrait) -> String {
+	let mut classes = cpp_view::<V>(cpp, false);
+	classes.push('\n');
+	classes.push_str(&cpp_view::<V>(cpp, true));
+
+	if cpp.drop.is_some() && cpp.layout {
+		classes.push('\n');
+		classes.push_str(&cpp_box::<V>(cpp));
+	}
+
+	classes.push('\n');
+	classes.push_str(&cpp_impl::<V>(cpp));
+	classes
+}
+
+/// Generate a borrowing view class.
+fn cpp_view<V: CVTable>(cpp: &CppTrait, mutable: bool) -> String {
+	let name = V::TRAIT_NAME;
+	let vtable = V::C_NAME;
+	let ptr = V::C_PTR_NAME;
+
+	let (class, view, data, init) = match mutable {
+		false => (
+			format!("{name}Ref"),
+			"Ref",
+			"void const *",
+			"const_cast<void *>(data)",
+		),
+		true => (format!("{name}Mut"), "Mut", "void *", "data"),
+	};
+
+	let mut out = format!(
+		"class {class} {{\n\
+		 public:\n\
+		 \t{ptr} ptr;\n\
+		 \n\
+		 \texplicit {class}({ptr} ptr) : ptr(ptr) {{}}\n\
+		 \t{class}({data}data, {vtable} const *table) : ptr{{{init}, table}} {{}}\n\
+		 \n\
+		 \tstatic {vtable} const &vtable({class} view) {{\n\
+		 \t\treturn *view.ptr.vtable;\n\
+		 \t}}\n"
+	);
+
+	if mutable {
+		out.push_str(&format!(
+			"\n\
+			 \toperator {name}Ref() const {{\n\
+			 \t\treturn {name}Ref(ptr);\n\
+			 \t}}\n"
+		));
+	}
+
+	for entry in &cpp.entries {
+		match entry {
+			CppEntry::Method(
+				method @ CppMethod {
+					receiver: Some(receiver @ (CppReceiver::Ref | CppReceiver::Mut)),
+					..
+				},
+			) => {
+				let qualifier = match receiver {
+					CppReceiver::Ref => " const",
+					_ if mutable => "",
+					_ => continue,
+				};
+
+				let (params, args) = cpp_params(method);
+				out.push_str(&format!(
+					"\n\
+					 \t{} {}({}){qualifier} {{\n\
+					 \t\treturn {};\n\
+					 \t}}\n",
+					method.output,
+					method.name,
+					params.join(", "),
+					cpp_call(method, "ptr", &args),
+				));
+
+				if method.optional {
+					out.push_str(&format!(
+						"\n\
+						 \tbool has_{}() const {{\n\
+						 \t\treturn ptr.vtable->{} != nullptr;\n\
+						 \t}}\n",
+						method.name, method.field,
+					));
+				}
+			},
+			CppEntry::Subtable {
+				field,
+				name: subtable,
+				pointer,
+			} => {
+				let reference = match pointer {
+					true => "",
+					false => "&",
+				};
+
+				out.push_str(&format!(
+					"\n\
+					 \t{subtable}{view} as_{subtable}() const {{\n\
+					 \t\treturn {subtable}{view}(ptr.ptr, {reference}ptr.vtable->{field});\n\
+					 \t}}\n"
+				));
+			},
+			_ => {},
+		}
+	}
+
+	for (parent, child) in &cpp.upcasts {
+		out.push_str(&format!(
+			"\n\
+			 \t{child}{view} as_{child}() const {{\n\
+			 \t\treturn as_{parent}().as_{child}();\n\
+			 \t}}\n"
+		));
+	}
+
+	out.push_str("};\n");
+	out
+}
+
+/// Generate an owning class.
+fn cpp_box<V: CVTable>(cpp: &CppTrait) -> String {
+	let name = V::TRAIT_NAME;
+	let vtable = V::C_NAME;
+	let ptr = V::C_PTR_NAME;
+	let class = format!("{name}Box");
+
+	let mut out = format!(
+		"template <typename Dealloc = DyntableFreeDeallocator>\n\
+		 class {class} {{\n\
+		 public:\n\
+		 \t{ptr} ptr;\n\
+		 \tDealloc dealloc;\n\
+		 \n\
+		 \texplicit {class}({ptr} ptr, Dealloc dealloc = Dealloc())\n\
+		 \t\t: ptr(ptr), dealloc(std::move(dealloc)) {{}}\n\
+		 \n\
+		 \t{class}({class} &&other) : ptr(into_raw(other)), dealloc(std::move(other.dealloc)) {{}}\n\
+		 \n\
+		 \t{class} &operator=({class} &&other) {{\n\
+		 \t\tif (this != &other) {{\n\
+		 \t\t\t__dyntable_reset();\n\
+		 \t\t\tptr = into_raw(other);\n\
+		 \t\t\tdealloc = std::move(other.dealloc);\n\
+		 \t\t}}\n\
+		 \n\
+		 \t\treturn *this;\n\
+		 \t}}\n\
+		 \n\
+		 \t{class}({class} const &) = delete;\n\
+		 \t{class} &operator=({class} const &) = delete;\n\
+		 \n\
+		 \t~{class}() {{\n\
+		 \t\t__dyntable_reset();\n\
+		 \t}}\n\
+		 \n\
+		 \t// Take the object without dropping or deallocating it, leaving the box empty.\n\
+		 \tstatic {ptr} into_raw({class} &b) {{\n\
+		 \t\t{ptr} raw = b.ptr;\n\
+		 \t\tb.ptr.vtable = nullptr;\n\
+		 \t\treturn raw;\n\
+		 \t}}\n\
+		 \n\
+		 \tstatic {name}Ref borrow({class} const &b) {{\n\
+		 \t\treturn {name}Ref(b.ptr);\n\
+		 \t}}\n\
+		 \n\
+		 \tstatic {name}Mut borrow_mut({class} &b) {{\n\
+		 \t\treturn {name}Mut(b.ptr);\n\
+		 \t}}\n\
+		 \n\
+		 \tstatic {vtable} const &vtable({class} const &b) {{\n\
+		 \t\treturn *b.ptr.vtable;\n\
+		 \t}}\n\
+		 \n\
+		 \t// Boxes are empty after being moved from.\n\
+		 \texplicit operator bool() const {{\n\
+		 \t\treturn ptr.vtable != nullptr;\n\
+		 \t}}\n"
+	);
+
+	for entry in &cpp.entries {
+		let CppEntry::Method(method @ CppMethod { receiver: Some(receiver), .. }) = entry else {
+			continue
+		};
+
+		let (params, args) = cpp_params(method);
+		let params = params.join(", ");
+
+		match receiver {
+			CppReceiver::Ref | CppReceiver::Mut => {
+				let qualifier = match receiver {
+					CppReceiver::Ref => " const",
+					_ => "",
+				};
+
+				out.push_str(&format!(
+					"\n\
+					 \t{} {}({params}){qualifier} {{\n\
+					 \t\treturn {};\n\
+					 \t}}\n",
+					method.output,
+					method.name,
+					cpp_call(method, "ptr", &args),
+				));
+			},
+			// The object is moved into the method, which drops it.
+			// Only its memory is deallocated afterwards.
+			CppReceiver::Value => out.push_str(&format!(
+				"\n\
+				 \t{} {}({params}) && {{\n\
+				 \t\tstruct Guard {{\n\
+				 \t\t\t{ptr} ptr;\n\
+				 \t\t\tDealloc &dealloc;\n\
+				 \n\
+				 \t\t\t~Guard() {{\n\
+				 \t\t\t\tif (ptr.vtable->__layout.size != 0) {{\n\
+				 \t\t\t\t\tdealloc.deallocate(ptr.ptr, ptr.vtable->__layout);\n\
+				 \t\t\t\t}}\n\
+				 \t\t\t}}\n\
+				 \t\t}} guard{{into_raw(*this), dealloc}};\n\
+				 \n\
+				 \t\treturn {};\n\
+				 \t}}\n",
+				method.output,
+				method.name,
+				cpp_call(method, "guard.ptr", &args),
+			)),
+		}
+
+		if method.optional {
+			out.push_str(&format!(
+				"\n\
+				 \tbool has_{}() const {{\n\
+				 \t\treturn ptr.vtable->{} != nullptr;\n\
+				 \t}}\n",
+				method.name, method.field,
+			));
+		}
+	}
+
+	out.push_str(&format!(
+		"\n\
+		 private:\n\
+		 \t// Drop and deallocate the object, leaving the box empty.\n\
+		 \tvoid __dyntable_reset() {{\n\
+		 \t\tif (ptr.vtable != nullptr) {{\n\
+		 \t\t\t{ptr} dropped = into_raw(*this);\n\
+		 \t\t\tdropped.vtable->__drop(dropped.ptr);\n\
+		 \n\
+		 \t\t\tif (dropped.vtable->__layout.size != 0) {{\n\
+		 \t\t\t\tdealloc.deallocate(dropped.ptr, dropped.vtable->__layout);\n\
+		 \t\t\t}}\n\
+		 \t\t}}\n\
+		 \t}}\n\
+		 }};\n"
+	));
+	out
+}
+
+/// Generate a class building a VTable from a C++ type.
+fn cpp_impl<V: CVTable>(cpp: &CppTrait) -> String {
+	let name = V::TRAIT_NAME;
+	let vtable = V::C_NAME;
+	let ptr = V::C_PTR_NAME;
+	let class = format!("{name}Impl");
+
+	let convention = |convention: &str| match convention {
+		"" => String::new(),
+		convention => format!("{convention} "),
+	};
+
+	let mut init = String::new();
+	let mut thunks = String::new();
+
+	if let Some((version, last)) = cpp.header {
+		init.push_str(&format!(
+			"\t\ttable.__header.size = offsetof({vtable}, {last}) + sizeof(table.{last});\n\
+			 \t\ttable.__header.version = {version};\n"
+		));
+	}
+
+	if let Some(drop) = cpp.drop {
+		init.push_str(&format!("\t\ttable.__drop = &{class}::__dyntable_drop;\n"));
+		thunks.push_str(&format!(
+			"\n\
+			 \tstatic void {}__dyntable_drop(void *self) {{\n\
+			 \t\tstatic_cast<T *>(self)->~T();\n\
+			 \t}}\n",
+			convention(drop),
+		));
+	}
+
+	if cpp.layout {
+		init.push_str(
+			"\t\ttable.__layout.size = sizeof(T);\n\
+			 \t\ttable.__layout.align = alignof(T);\n",
+		);
+	}
+
+	// values boxed from C++ are allocated with `malloc`
+	if let Some(dealloc) = cpp.dealloc {
+		init.push_str(&format!("\t\ttable.__dealloc = &{class}::__dyntable_dealloc;\n"));
+		thunks.push_str(&format!(
+			"\n\
+			 \tstatic void {}__dyntable_dealloc(void *self) {{\n\
+			 \t\tstd::free(self);\n\
+			 \t}}\n",
+			convention(dealloc),
+		));
+	}
+
+	for entry in &cpp.entries {
+		match entry {
+			CppEntry::Subtable {
+				field,
+				name: subtable,
+				pointer: true,
+			} => init.push_str(&format!(
+				"\t\ttable.{field} = {subtable}Impl<T>::vtable();\n"
+			)),
+			CppEntry::Subtable {
+				field,
+				name: subtable,
+				pointer: false,
+			} => init.push_str(&format!(
+				"\t\ttable.{field} = {subtable}Impl<T>::make_vtable();\n"
+			)),
+			CppEntry::Const { field, name } => {
+				init.push_str(&format!("\t\ttable.{field} = T::{name};\n"))
+			},
+			CppEntry::Method(method) => {
+				let field = method.field;
+				init.push_str(&format!(
+					"\t\ttable.{field} = &{class}::__dyntable_{field};\n"
+				));
+
+				let (mut params, args) = cpp_params(method);
+				let args = args.join(", ");
+				let method_name = method.name;
+
+				let body = match method.receiver {
+					Some(CppReceiver::Ref) => format!(
+						"\t\treturn static_cast<T const *>(self)->{method_name}({args});\n"
+					),
+					Some(CppReceiver::Mut) => format!(
+						"\t\treturn static_cast<T *>(self)->{method_name}({args});\n"
+					),
+					Some(CppReceiver::Value) => format!(
+						"\t\tT *value = static_cast<T *>(self);\n\
+						 \t\tT moved(std::move(*value));\n\
+						 \t\tvalue->~T();\n\
+						 \t\treturn std::move(moved).{method_name}({args});\n"
+					),
+					None => format!("\t\treturn T::{method_name}({args});\n"),
+				};
+
+				if method.receiver.is_some() {
+					params.insert(0, String::from("void *self"));
+				}
+
+				thunks.push_str(&format!(
+					"\n\
+					 \tstatic {} {}__dyntable_{field}({}) {{\n\
+					 {body}\
+					 \t}}\n",
+					method.output,
+					convention(method.convention),
+					params.join(", "),
+				));
+			},
+		}
+	}
+
+	let mut out = format!(
+		"template <typename T>\n\
+		 class {class} {{\n\
+		 public:\n\
+		 \tstatic {vtable} make_vtable() {{\n\
+		 \t\t{vtable} table{{}};\n\
+		 {init}\
+		 \t\treturn table;\n\
+		 \t}}\n\
+		 \n\
+		 \tstatic {vtable} const *vtable() {{\n\
+		 \t\tstatic {vtable} const table = make_vtable();\n\
+		 \t\treturn &table;\n\
+		 \t}}\n\
+		 \n\
+		 \tstatic {name}Ref borrow(T const &value) {{\n\
+		 \t\treturn {name}Ref(&value, vtable());\n\
+		 \t}}\n\
+		 \n\
+		 \tstatic {name}Mut borrow_mut(T &value) {{\n\
+		 \t\treturn {name}Mut(&value, vtable());\n\
+		 \t}}\n"
+	);
+
+	if cpp.drop.is_some() && cpp.layout {
+		out.push_str(&format!(
+			"\n\
+			 \t// Move `value` into a box allocated with `malloc`.\n\
+			 \tstatic {name}Box<> new_box(T value) {{\n\
+			 \t\tstatic_assert(alignof(T) <= alignof(std::max_align_t), \"over-aligned type\");\n\
+			 \n\
+			 \t\tvoid *data = std::malloc(sizeof(T));\n\
+			 \t\tif (data == nullptr) {{\n\
+			 \t\t\tthrow std::bad_alloc();\n\
+			 \t\t}}\n\
+			 \n\
+			 \t\tnew (data) T(std::move(value));\n\
+			 \t\treturn {name}Box<>({ptr}{{data, vtable()}});\n\
+			 \t}}\n"
+		));
+	}
+
+	out.push_str("\nprivate:");
+	out.push_str(&thunks);
+	out.push_str("};\n");
+	out
+}
+
+macro_rules! impl_ctype {
+	($($ty:ty => $name:literal,)*) => {
+		$(impl CType for $ty {
+			fn c_type(_: &mut CHeader) -> String {
+				String::from($name)
+			}
+		})*
+	};
+}
+
+impl_ctype! {
+	i8 => "int8_t",
+	i16 => "int16_t",
+	i32 => "int32_t",
+	i64 => "int64_t",
+	isize => "intptr_t",
+	u8 => "uint8_t",
+	u16 => "uint16_t",
+	u32 => "uint32_t",
+	u64 => "uint64_t",
+	usize => "size_t",
+	f32 => "float",
+	f64 => "double",
+	bool => "bool",
+	char => "uint32_t",
+	() => "void",
+	c_void => "void",
+	DynSelf<'_> => "void *",
+}
+
+impl<T: CType + ?Sized> CType for *const T {
+	fn c_type(header: &mut CHeader) -> String {
+		format!("{} const *", T::c_type(header))
+	}
+}
+
+impl<T: CType + ?Sized> CType for *mut T {
+	fn c_type(header: &mut CHeader) -> String {
+		format!("{} *", T::c_type(header))
+	}
+}
+
+impl<T: CType + ?Sized> CType for &T {
+	fn c_type(header: &mut CHeader) -> String {
+		<*const T>::c_type(header)
+	}
+}
+
+impl<T: CType + ?Sized> CType for &mut T {
+	fn c_type(header: &mut CHeader) -> String {
+		<*mut T>::c_type(header)
+	}
+}
+
+impl<T: CType + ?Sized> CType for Option<&T> {
+	fn c_type(header: &mut CHeader) -> String {
+		<*const T>::c_type(header)
+	}
+}
+
+impl<T: CType + ?Sized> CType for Option<&mut T> {
+	fn c_type(header: &mut CHeader) -> String {
+		<*mut T>::c_type(header)
+	}
+}
+
+impl<T: CType + ?Sized> CType for core::ptr::NonNull<T> {
+	fn c_type(header: &mut CHeader) -> String {
+		<*mut T>::c_type(header)
+	}
+}
+
+impl CType for MemoryLayout {
+	fn c_type(header: &mut CHeader) -> String {
+		header.define_struct("DyntableMemoryLayout", |_| {
+			vec![c_field("size_t", "size"), c_field("size_t", "align")]
+		})
+	}
+}
+
+impl CType for DynAllocator {
+	fn c_type(header: &mut CHeader) -> String {
+		header.define_struct("DyntableAllocator", |header| {
+			let layout = MemoryLayout::c_type(header);
+
+			vec![
+				c_field("void *", "ctx"),
+				c_fn_field("allocate", "", "void *", &[
+					c_param("void *", "ctx"),
+					c_param(&layout, "layout"),
+				]),
+				c_fn_field("deallocate", "", "void", &[
+					c_param("void *", "ctx"),
+					c_param("void *", "ptr"),
+					c_param(&layout, "layout"),
+				]),
+				c_fn_field("reallocate", "", "void *", &[
+					c_param("void *", "ctx"),
+					c_param("void *", "ptr"),
+					c_param(&layout, "old_layout"),
+					c_param(&layout, "new_layout"),
+				]),
+			]
+		})
+	}
+}
+
+impl CType for VTableHeader {
+	fn c_type(header: &mut CHeader) -> String {
+		header.define_struct("DyntableVTableHeader", |_| {
+			vec![c_field("size_t", "size"), c_field("uint32_t", "version")]
+		})
+	}
+}
+
+impl<V> CType for DynPtr<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		header.vtable::<V::VTable>();
+		V::VTable::C_PTR_NAME.to_string()
+	}
+}
+
+impl<V> CType for DynRef<'_, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		<DynPtr<V>>::c_type(header)
+	}
+}
+
+impl<V> CType for DynRefMut<'_, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		<DynPtr<V>>::c_type(header)
+	}
+}
+
+// The global allocator is zero sized, leaving only the dynptr.
+impl<V> CType for DynBox<V, GlobalAllocator>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable + AssociatedDrop + AssociatedLayout,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		<DynPtr<V>>::c_type(header)
+	}
+}
+
+impl<V> CType for DynBox<V, DynAllocator>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable + AssociatedDrop + AssociatedLayout,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		let name = format!("{}DynBox", V::VTable::TRAIT_NAME);
+
+		header.define_struct(&name, |header| {
+			vec![
+				c_field(&DynAllocator::c_type(header), "alloc"),
+				c_field(&<DynPtr<V>>::c_type(header), "ptr"),
+			]
+		})
+	}
+}
+
+impl<V> CType for DynOwned<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable + AssociatedDrop + AssociatedDealloc,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		<DynPtr<V>>::c_type(header)
+	}
+}
+
+// Optional dynptrs share the C representation of a dynptr, using a null
+// `vtable` for `None`.
+impl<V> CType for Option<DynPtr<V>>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		<DynPtr<V>>::c_type(header)
+	}
+}
+
+impl<V> CType for Option<DynRef<'_, V>>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		<DynPtr<V>>::c_type(header)
+	}
+}
+
+impl<V> CType for Option<DynRefMut<'_, V>>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		<DynPtr<V>>::c_type(header)
+	}
+}
+
+impl<V> CType for Option<DynBox<V, GlobalAllocator>>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable + AssociatedDrop + AssociatedLayout,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		<DynPtr<V>>::c_type(header)
+	}
+}
+
+impl<V> CType for Option<DynOwned<V>>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CVTable + AssociatedDrop + AssociatedDealloc,
+{
+	fn c_type(header: &mut CHeader) -> String {
+		<DynPtr<V>>::c_type(header)
+	}
+}
+
+// Send and Sync VTables are transparent wrappers, and share the C
+// representation of the wrapped VTable.
+macro_rules! impl_wrapper_cvtable {
+	($($wrapper:ident),*) => {
+		$(impl<V: CVTable> CVTable for $wrapper<V> {
+			const TRAIT_NAME: &'static str = V::TRAIT_NAME;
+			const C_NAME: &'static str = V::C_NAME;
+			const C_PTR_NAME: &'static str = V::C_PTR_NAME;
+
+			fn c_fields(header: &mut CHeader) -> Vec<String> {
+				V::c_fields(header)
+			}
+
+			fn cpp_trait(header: &mut CHeader) -> CppTrait {
+				V::cpp_trait(header)
+			}
+		})*
+	};
+}
+
+impl_wrapper_cvtable!(SendVTable, SyncVTable, SendSyncVTable);
@@ -0,0 +1,403 @@
+//! COM compatible interfaces.
+//!
+//! Traits annotated with `#[dyntable(com = "<GUID>")]` have a VTable laid out
+//! like a COM interface: the [`IUnknown`] methods come first, followed by the
+//! methods of each parent interface and finally the trait's own methods.
+//!
+//! Unlike other dyntable traits, COM objects are referenced by a thin `this`
+//! pointer to the object, which stores its VTable pointer as its first field.
+//! Objects are reference counted and accessed through a [`ComPtr`].
+//! Rust implementations are allocated in a [`ComObject`].
+//!
+//! # Examples
+//! ```
+//! # use dyntable::{*, com::*};
+//! # use core::sync::atomic::{AtomicI32, Ordering};
+//! #[dyntable(com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c")]
+//! trait ICounter: IUnknown
+//! where
+//!     dyn IUnknown:,
+//! {
+//!     extern "system" fn increment(&self) -> i32;
+//! }
+//!
+//! struct Counter(AtomicI32);
+//!
+//! impl ICounter for Counter {
+//!     extern "system" fn increment(&self) -> i32 {
+//!         self.0.fetch_add(1, Ordering::Relaxed) + 1
+//!     }
+//! }
+//!
+//! let counter = ComPtr::<dyn ICounter>::new(Counter(AtomicI32::new(0)));
+//! let clone = counter.clone();
+//! assert_eq!(counter.increment(), 1);
+//! assert_eq!(clone.increment(), 2);
+//!
+//! let unknown = ComPtr::query::<dyn IUnknown>(&counter).unwrap();
+//! let counter = ComPtr::query::<dyn ICounter>(&unknown).unwrap();
+//! assert_eq!(counter.increment(), 3);
+//! ```
+//!
+//! # Notes
+//! COM objects are shared between every pointer referencing them, so
+//! interface methods must take `&self`. Use interior mutability to
+//! modify the object.
+//!
+//! Interfaces may only have a single parent interface, as QueryInterface
+//! returns the same `this` pointer for every supported interface.
+
+#![cfg(feature = "alloc")]
+#![cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+
+use core::{
+	ffi::c_void,
+	fmt,
+	marker::PhantomData,
+	ptr::{self, NonNull},
+	sync::atomic::{self, AtomicU32, Ordering},
+};
+
+use std_alloc::boxed::Box;
+
+use crate::{
+	__private::{DynImplTarget, DynTraitProxy, NoBounds},
+	AsDyn,
+	DynTrait,
+	SubTable,
+	VTable,
+	VTableRepr,
+};
+
+/// A COM `HRESULT`.
+pub type HResult = i32;
+
+/// Operation successful.
+pub const S_OK: HResult = 0;
+/// The requested interface is not supported.
+pub const E_NOINTERFACE: HResult = 0x80004002_u32 as i32;
+/// A required pointer was null.
+pub const E_POINTER: HResult = 0x80004003_u32 as i32;
+
+/// A COM GUID, used to identify interfaces.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Guid {
+	pub data1: u32,
+	pub data2: u16,
+	pub data3: u16,
+	pub data4: [u8; 8],
+}
+
+impl fmt::Display for Guid {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let [a, b, c, d, e, g, h, i] = self.data4;
+
+		write!(
+			f,
+			"{:08x}-{:04x}-{:04x}-{a:02x}{b:02x}-{c:02x}{d:02x}{e:02x}{g:02x}{h:02x}{i:02x}",
+			self.data1, self.data2, self.data3,
+		)
+	}
+}
+
+/// Interface ID of [`IUnknown`].
+pub const IID_IUNKNOWN: Guid = Guid {
+	data1: 0x00000000,
+	data2: 0x0000,
+	data3: 0x0000,
+	data4: [0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46],
+};
+
+/// The base COM interface, which all COM interfaces derive from.
+///
+/// `IUnknown` has no Rust methods, as its entries are implemented by the
+/// object holding the interface. Use [`ComPtr::query`] to query for another
+/// interface. Every sized type implements `IUnknown`.
+pub trait IUnknown {}
+
+impl<T> IUnknown for T {}
+
+/// The VTable of [`IUnknown`].
+#[repr(C)]
+pub struct IUnknownVTable {
+	pub query_interface:
+		unsafe extern "system" fn(this: *mut c_void, iid: *const Guid, out: *mut *mut c_void) -> HResult,
+	pub add_ref: unsafe extern "system" fn(this: *mut c_void) -> u32,
+	pub release: unsafe extern "system" fn(this: *mut c_void) -> u32,
+}
+
+unsafe impl VTable for IUnknownVTable {
+	type Bounds = dyn NoBounds;
+}
+
+impl VTableRepr for dyn IUnknown {
+	type VTable = IUnknownVTable;
+}
+
+/// A VTable laid out as a COM interface.
+///
+/// # Safety
+/// The VTable must start with an [`IUnknownVTable`], and every interface
+/// it is a [`SubTable`] of must be stored at its start.
+///
+/// # Notes
+/// This trait is implemented by the [`dyntable`](crate::dyntable) macro
+/// when the `com` option is used.
+pub unsafe trait ComInterface: VTable + 'static {
+	/// The interface ID.
+	const IID: Guid;
+
+	/// Returns `true` if `iid` is the ID of this interface or one of
+	/// its parents.
+	fn supports(iid: &Guid) -> bool;
+}
+
+unsafe impl ComInterface for IUnknownVTable {
+	const IID: Guid = IID_IUNKNOWN;
+
+	#[inline]
+	fn supports(iid: &Guid) -> bool {
+		*iid == IID_IUNKNOWN
+	}
+}
+
+/// A reference counted COM object holding a Rust value.
+///
+/// A pointer to the object is the `this` pointer of its interfaces, and
+/// the object's first field is its VTable pointer.
+#[repr(C)]
+pub struct ComObject<T> {
+	vtable: *const c_void,
+	refs: AtomicU32,
+	supports: fn(&Guid) -> bool,
+	value: T,
+}
+
+impl<T> ComObject<T> {
+	/// Returns a pointer to the value held by the object at `this`.
+	///
+	/// # Safety
+	/// `this` must point to a live `ComObject<T>`.
+	#[inline(always)]
+	pub unsafe fn value(this: *mut c_void) -> *mut T {
+		ptr::addr_of_mut!((*(this as *mut Self)).value)
+	}
+
+	unsafe extern "system" fn query_interface(
+		this: *mut c_void,
+		iid: *const Guid,
+		out: *mut *mut c_void,
+	) -> HResult {
+		if out.is_null() {
+			return E_POINTER
+		}
+
+		if iid.is_null() || !((*(this as *mut Self)).supports)(&*iid) {
+			*out = ptr::null_mut();
+			return E_NOINTERFACE
+		}
+
+		// Every supported interface is a prefix of the object's VTable,
+		// so the same pointer is returned for all of them.
+		Self::add_ref(this);
+		*out = this;
+		S_OK
+	}
+
+	unsafe extern "system" fn add_ref(this: *mut c_void) -> u32 {
+		(*(this as *mut Self)).refs.fetch_add(1, Ordering::Relaxed) + 1
+	}
+
+	unsafe extern "system" fn release(this: *mut c_void) -> u32 {
+		let refs = (*(this as *mut Self)).refs.fetch_sub(1, Ordering::Release) - 1;
+
+		if refs == 0 {
+			atomic::fence(Ordering::Acquire);
+			drop(Box::from_raw(this as *mut Self));
+		}
+
+		refs
+	}
+}
+
+unsafe impl<'v, T> DynTraitProxy<'v, IUnknownVTable> for DynImplTarget<ComObject<T>, IUnknownVTable> {
+	const STATIC_VTABLE: &'v IUnknownVTable = &Self::VTABLE;
+	const VTABLE: IUnknownVTable = IUnknownVTable {
+		query_interface: ComObject::<T>::query_interface,
+		add_ref: ComObject::<T>::add_ref,
+		release: ComObject::<T>::release,
+	};
+}
+
+/// A reference counted pointer to a COM interface.
+///
+/// Cloning the pointer calls `AddRef`, and dropping it calls `Release`.
+#[repr(transparent)]
+pub struct ComPtr<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: ComInterface,
+{
+	this: NonNull<*const V::VTable>,
+	_marker: PhantomData<*const V::VTable>,
+}
+
+unsafe impl<R, V> AsDyn<R> for ComPtr<V>
+where
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+	V::VTable: ComInterface,
+{
+	type Repr = V;
+
+	const OWNS_TARGET: bool = false;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.this.as_ptr() as *mut c_void
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		unsafe { *self.this.as_ptr() }
+	}
+
+	fn dyn_dealloc(self) {
+		unreachable!("COM objects cannot be taken by value");
+	}
+}
+
+impl<V> ComPtr<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: ComInterface,
+{
+	/// Moves `value` into a new [`ComObject`] implementing `V`, returning
+	/// the only reference to it.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	pub fn new<T>(value: T) -> Self
+	where
+		T: 'static,
+		ComObject<T>: DynTrait<'static, V::VTable>,
+	{
+		let object = Box::new(ComObject {
+			vtable: <ComObject<T> as DynTrait<'static, V::VTable>>::STATIC_VTABLE
+				as *const V::VTable as *const c_void,
+			refs: AtomicU32::new(1),
+			supports: <V::VTable as ComInterface>::supports,
+			value,
+		});
+
+		// SAFETY: the object has a reference count of 1, owned by the ComPtr
+		unsafe { Self::from_raw(Box::into_raw(object) as *mut c_void) }
+	}
+
+	/// Creates a `ComPtr` from the `this` pointer of an object, taking
+	/// ownership of one of its references.
+	///
+	/// # Safety
+	/// `this` must point to a live COM object implementing `V`, and the
+	/// caller must own a reference to it.
+	#[inline(always)]
+	pub unsafe fn from_raw(this: *mut c_void) -> Self {
+		Self {
+			this: NonNull::new_unchecked(this as *mut *const V::VTable),
+			_marker: PhantomData,
+		}
+	}
+
+	/// Returns the `this` pointer of the object, transferring ownership of
+	/// the pointer's reference to the caller.
+	#[inline(always)]
+	pub fn into_raw(p: Self) -> *mut c_void {
+		let this = Self::as_raw(&p);
+		core::mem::forget(p);
+		this
+	}
+
+	/// Returns the `this` pointer of the object, without affecting its
+	/// reference count.
+	#[inline(always)]
+	pub fn as_raw(p: &Self) -> *mut c_void {
+		p.this.as_ptr() as *mut c_void
+	}
+
+	/// Returns a reference to the interface's VTable.
+	#[inline(always)]
+	pub fn vtable(p: &Self) -> &V::VTable {
+		unsafe { &**p.this.as_ptr() }
+	}
+
+	/// Returns the object's [`IUnknown`] entries.
+	#[inline(always)]
+	fn unknown(p: &Self) -> &IUnknownVTable {
+		// SAFETY: COM VTables always start with the IUnknown entries
+		unsafe { &*(*p.this.as_ptr() as *const IUnknownVTable) }
+	}
+
+	/// Queries the object for the interface `U` using `QueryInterface`,
+	/// returning `None` if the object does not support it.
+	pub fn query<U>(p: &Self) -> Option<ComPtr<U>>
+	where
+		U: VTableRepr + ?Sized,
+		U::VTable: ComInterface,
+	{
+		let mut out = ptr::null_mut();
+
+		unsafe {
+			let result =
+				(Self::unknown(p).query_interface)(Self::as_raw(p), &U::VTable::IID, &mut out);
+
+			match result == S_OK && !out.is_null() {
+				true => Some(ComPtr::from_raw(out)),
+				false => None,
+			}
+		}
+	}
+
+	/// Converts the pointer into a pointer to one of the interface's parents.
+	/// This is always successful and does not call `QueryInterface`.
+	#[inline(always)]
+	pub fn upcast<U>(p: Self) -> ComPtr<U>
+	where
+		U: VTableRepr + ?Sized,
+		U::VTable: ComInterface,
+		V::VTable: SubTable<U::VTable>,
+	{
+		// parent interfaces are always stored at the start of the VTable
+		debug_assert_eq!(
+			Self::vtable(&p).subtable() as *const U::VTable as *const c_void,
+			Self::vtable(&p) as *const V::VTable as *const c_void,
+		);
+
+		unsafe { ComPtr::from_raw(Self::into_raw(p)) }
+	}
+}
+
+impl<V> Clone for ComPtr<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: ComInterface,
+{
+	fn clone(&self) -> Self {
+		unsafe {
+			(Self::unknown(self).add_ref)(Self::as_raw(self));
+			Self::from_raw(Self::as_raw(self))
+		}
+	}
+}
+
+impl<V> Drop for ComPtr<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: ComInterface,
+{
+	fn drop(&mut self) {
+		unsafe {
+			(Self::unknown(self).release)(Self::as_raw(self));
+		}
+	}
+}
@@ -0,0 +1,916 @@
+//! Growable vector of dyntable trait objects stored contiguously.
+//!
+//! A [`DynVec`] stores values of different types implementing the same trait
+//! in a single buffer, each padded to the alignment in its VTable's
+//! [`AssociatedLayout`], alongside a side table of value offsets and VTable
+//! pointers. Unlike a `Vec<DynBox<dyn Trait>>`, pushing a value does not
+//! allocate once the buffer has room, and iterating over the values walks
+//! through a single region of memory.
+//!
+//! Values removed from the vector are returned as a [`Taken`], which owns the
+//! value while it is still stored in the vector's buffer, and borrows the
+//! vector until it is dropped. The space of removed values is reclaimed when
+//! the buffer grows.
+//!
+//! # Examples
+//! ```
+//! # use dyntable::{*, dynvec::*};
+//! #[dyntable]
+//! trait Node {
+//!     extern "C" fn eval(&self) -> u64;
+//! }
+//!
+//! struct Constant(u8);
+//! struct Sum(u64, u64);
+//!
+//! impl Node for Constant {
+//!     extern "C" fn eval(&self) -> u64 {
+//!         self.0 as u64
+//!     }
+//! }
+//!
+//! impl Node for Sum {
+//!     extern "C" fn eval(&self) -> u64 {
+//!         self.0 + self.1
+//!     }
+//! }
+//!
+//! let mut nodes = DynVec::<dyn Node>::new();
+//! nodes.push(Constant(1));
+//! nodes.push(Sum(2, 3));
+//! nodes.push(Constant(4));
+//!
+//! assert_eq!(nodes.iter().map(|node| node.eval()).sum::<u64>(), 10);
+//!
+//! let removed = nodes.swap_remove(0);
+//! assert_eq!(removed.eval(), 1);
+//! drop(removed);
+//!
+//! assert_eq!(nodes.get(0).unwrap().eval(), 4);
+//! assert_eq!(nodes.len(), 2);
+//! ```
+
+#![cfg(feature = "alloc")]
+#![cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+
+use core::{
+	alloc::Layout,
+	ffi::c_void,
+	iter::FusedIterator,
+	marker::PhantomData,
+	mem,
+	ops::{Bound, RangeBounds},
+	ptr::{self, NonNull},
+	slice,
+};
+
+use std_alloc::alloc::handle_alloc_error;
+
+use crate::{
+	alloc::{
+		allocate,
+		deallocate,
+		AllocError,
+		Allocator,
+		Deallocator,
+		GlobalAllocator,
+		MemoryLayout,
+	},
+	AsDyn,
+	AssociatedDrop,
+	AssociatedLayout,
+	DynPtr,
+	DynRef,
+	DynRefMut,
+	DynTrait,
+	VTable,
+	VTableRepr,
+};
+
+/// Side table entry describing a value in the buffer.
+struct Entry<V>
+where
+	V: VTableRepr + ?Sized,
+{
+	offset: usize,
+	vtable: NonNull<V::VTable>,
+}
+
+impl<V> Clone for Entry<V>
+where
+	V: VTableRepr + ?Sized,
+{
+	#[inline(always)]
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<V> Copy for Entry<V> where V: VTableRepr + ?Sized {}
+
+#[inline(always)]
+const fn align_up(offset: usize, align: usize) -> usize {
+	(offset + align - 1) & !(align - 1)
+}
+
+/// A growable vector of dyntable trait objects, stored contiguously.
+/// See the [module docs](self).
+pub struct DynVec<V, A = GlobalAllocator>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	alloc: A,
+	/// Buffer the values are stored in, aligned to the largest alignment
+	/// of any value pushed to the vector.
+	data: NonNull<u8>,
+	data_layout: MemoryLayout,
+	/// End of the last value in `data`.
+	end: usize,
+	entries: NonNull<Entry<V>>,
+	entries_cap: usize,
+	len: usize,
+}
+
+unsafe impl<V, A> Send for DynVec<V, A>
+where
+	A: Deallocator + Send,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	<V::VTable as VTable>::Bounds: Send,
+{
+}
+
+unsafe impl<V, A> Sync for DynVec<V, A>
+where
+	A: Deallocator + Sync,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	<V::VTable as VTable>::Bounds: Sync,
+{
+}
+
+impl<V> DynVec<V, GlobalAllocator>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Creates an empty vector using the global allocator.
+	///
+	/// No memory is allocated until values are pushed to the vector.
+	#[inline(always)]
+	pub const fn new() -> Self {
+		Self::new_in(GlobalAllocator)
+	}
+}
+
+impl<V> Default for DynVec<V, GlobalAllocator>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	#[inline(always)]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<V, A> DynVec<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Creates an empty vector using the given allocator.
+	///
+	/// No memory is allocated until values are pushed to the vector.
+	#[inline(always)]
+	pub const fn new_in(alloc: A) -> Self {
+		Self {
+			alloc,
+			data: NonNull::dangling(),
+			data_layout: MemoryLayout { size: 0, align: 1 },
+			end: 0,
+			entries: NonNull::dangling(),
+			entries_cap: 0,
+			len: 0,
+		}
+	}
+
+	/// Returns the number of values in the vector.
+	#[inline(always)]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns `true` if the vector contains no values.
+	#[inline(always)]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Moves `data` to the end of the vector, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	#[inline]
+	pub fn push<'v, T>(&mut self, data: T)
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		if self.try_push(data).is_err() {
+			handle_alloc_error(Layout::new::<T>());
+		}
+	}
+
+	/// Moves `data` to the end of the vector, upcasting it to `V`, and
+	/// returning an error if the allocation fails.
+	pub fn try_push<'v, T>(&mut self, data: T) -> Result<(), AllocError>
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let layout = MemoryLayout::new::<T>();
+
+		if self.len == self.entries_cap {
+			self.grow_entries()?;
+		}
+
+		let offset = match self.fit(layout) {
+			Some(offset) => offset,
+			None => self.grow_data(layout)?,
+		};
+
+		unsafe {
+			(self.data.as_ptr().add(offset) as *mut T).write(data);
+			self.entries.as_ptr().add(self.len).write(Entry {
+				offset,
+				vtable: NonNull::from(T::STATIC_VTABLE),
+			});
+		}
+
+		self.end = offset + layout.size;
+		self.len += 1;
+		Ok(())
+	}
+
+	/// Removes the last value from the vector and returns it, or `None` if
+	/// the vector is empty.
+	#[inline]
+	pub fn pop(&mut self) -> Option<Taken<'_, V>> {
+		if self.len == 0 {
+			return None
+		}
+
+		self.len -= 1;
+		let entry = unsafe { self.entries.as_ptr().add(self.len).read() };
+		Some(self.take(entry))
+	}
+
+	/// Removes the value at `index` from the vector and returns it,
+	/// replacing it with the last value of the vector.
+	///
+	/// # Panics
+	/// This method panics if `index` is out of bounds.
+	pub fn swap_remove(&mut self, index: usize) -> Taken<'_, V> {
+		let len = self.len;
+		if index >= len {
+			panic!("swap_remove index (is {index}) should be < len (is {len})");
+		}
+
+		self.len -= 1;
+
+		let entry = unsafe {
+			let entries = self.entries.as_ptr();
+			let entry = entries.add(index).read();
+			entries.add(index).write(entries.add(self.len).read());
+			entry
+		};
+
+		self.take(entry)
+	}
+
+	/// Removes the values in `range` from the vector, returning them
+	/// through an iterator.
+	///
+	/// Values not consumed by the iterator are dropped when the iterator is
+	/// dropped. If the iterator is leaked, the values after the range are
+	/// leaked as well.
+	///
+	/// # Panics
+	/// This method panics if the range is out of bounds.
+	pub fn drain<R>(&mut self, range: R) -> Drain<'_, V, A>
+	where
+		R: RangeBounds<usize>,
+	{
+		let start = match range.start_bound() {
+			Bound::Included(&start) => start,
+			Bound::Excluded(&start) => start
+				.checked_add(1)
+				.expect("attempted to drain from after usize::MAX"),
+			Bound::Unbounded => 0,
+		};
+
+		let end = match range.end_bound() {
+			Bound::Included(&end) => end
+				.checked_add(1)
+				.expect("attempted to drain up to usize::MAX"),
+			Bound::Excluded(&end) => end,
+			Bound::Unbounded => self.len,
+		};
+
+		if start > end {
+			panic!("drain index starts at {start} but ends at {end}");
+		}
+
+		if end > self.len {
+			panic!("drain end index {end} out of range for vector of length {}", self.len);
+		}
+
+		let tail_len = self.len - end;
+		// values in the range and after it are owned by the iterator
+		self.len = start;
+
+		Drain {
+			vec: NonNull::from(self),
+			start,
+			next: start,
+			end,
+			tail_start: end,
+			tail_len,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Drops all values in the vector, keeping its allocated memory.
+	pub fn clear(&mut self) {
+		let len = self.len;
+		self.len = 0;
+		self.end = 0;
+
+		for entry in unsafe { slice::from_raw_parts(self.entries.as_ptr(), len) } {
+			unsafe { entry.vtable.as_ref().virtual_drop(self.value_ptr(entry)) };
+		}
+	}
+
+	/// Immutably borrows the value at `index`, or returns `None` if `index`
+	/// is out of bounds.
+	#[inline]
+	pub fn get(&self, index: usize) -> Option<DynRef<'_, V>> {
+		let entry = self.entries().get(index)?;
+
+		Some(DynRef {
+			ptr: self.dyn_ptr(entry),
+			_lt: PhantomData,
+		})
+	}
+
+	/// Mutably borrows the value at `index`, or returns `None` if `index`
+	/// is out of bounds.
+	#[inline]
+	pub fn get_mut(&mut self, index: usize) -> Option<DynRefMut<'_, V>> {
+		let entry = self.entries().get(index)?;
+
+		Some(DynRefMut {
+			ptr: self.dyn_ptr(entry),
+			_lt: PhantomData,
+		})
+	}
+
+	/// Returns an iterator immutably borrowing each value in order.
+	#[inline(always)]
+	pub fn iter(&self) -> Iter<'_, V> {
+		Iter {
+			entries: self.entries().iter(),
+			data: self.data,
+		}
+	}
+
+	/// Returns an iterator mutably borrowing each value in order.
+	#[inline(always)]
+	pub fn iter_mut(&mut self) -> IterMut<'_, V> {
+		IterMut {
+			entries: unsafe { slice::from_raw_parts(self.entries.as_ptr(), self.len) }.iter(),
+			data: self.data,
+			_lt: PhantomData,
+		}
+	}
+
+	#[inline(always)]
+	fn entries(&self) -> &[Entry<V>] {
+		unsafe { slice::from_raw_parts(self.entries.as_ptr(), self.len) }
+	}
+
+	#[inline(always)]
+	fn value_ptr(&self, entry: &Entry<V>) -> *mut c_void {
+		unsafe { self.data.as_ptr().add(entry.offset) as *mut c_void }
+	}
+
+	#[inline(always)]
+	fn dyn_ptr(&self, entry: &Entry<V>) -> DynPtr<V> {
+		DynPtr {
+			ptr: self.value_ptr(entry),
+			vtable: entry.vtable,
+		}
+	}
+
+	/// Returns the value of a removed entry.
+	#[inline]
+	fn take(&mut self, entry: Entry<V>) -> Taken<'_, V> {
+		let layout = unsafe { entry.vtable.as_ref() }.virtual_layout();
+
+		// reclaim the space of the value if it was the last one in the buffer
+		if self.len == 0 {
+			self.end = 0;
+		} else if entry.offset + layout.size == self.end {
+			self.end = entry.offset;
+		}
+
+		Taken {
+			ptr: self.dyn_ptr(&entry),
+			_lt: PhantomData,
+		}
+	}
+
+	/// Returns the offset of a value with the given layout placed after the
+	/// last value, if it fits in the buffer.
+	#[inline(always)]
+	fn fit(&self, layout: MemoryLayout) -> Option<usize> {
+		if layout.align > self.data_layout.align {
+			return None
+		}
+
+		let offset = align_up(self.end, layout.align);
+
+		match offset.checked_add(layout.size)? <= self.data_layout.size {
+			true => Some(offset),
+			false => None,
+		}
+	}
+
+	/// Moves the values into a new buffer with room for another value with
+	/// the given layout, removing the space of removed values, and returns
+	/// the offset of the new value.
+	#[cold]
+	fn grow_data(&mut self, layout: MemoryLayout) -> Result<usize, AllocError>
+	where
+		A: Allocator,
+	{
+		let size = self.entries().iter().fold(0, |end, entry| {
+			let layout = unsafe { entry.vtable.as_ref() }.virtual_layout();
+			align_up(end, layout.align) + layout.size
+		});
+
+		let offset = align_up(size, layout.align);
+		let required = offset.checked_add(layout.size).ok_or(AllocError)?;
+		let size = match required <= self.data_layout.size / 2 {
+			true => self.data_layout.size,
+			false => usize::max(required, self.data_layout.size.saturating_mul(2)),
+		};
+		let align = usize::max(self.data_layout.align, layout.align);
+
+		let new_layout: MemoryLayout = Layout::from_size_align(size, align)
+			.map_err(|_| AllocError)?
+			.into();
+		let data = allocate(&self.alloc, new_layout)?;
+
+		let mut end = 0;
+		for i in 0..self.len {
+			unsafe {
+				let entry = &mut *self.entries.as_ptr().add(i);
+				let layout = entry.vtable.as_ref().virtual_layout();
+				let offset = align_up(end, layout.align);
+
+				ptr::copy_nonoverlapping(
+					self.data.as_ptr().add(entry.offset),
+					data.as_ptr().add(offset),
+					layout.size,
+				);
+
+				entry.offset = offset;
+				end = offset + layout.size;
+			}
+		}
+
+		unsafe { deallocate(&self.alloc, self.data, self.data_layout) };
+		self.data = data;
+		self.data_layout = new_layout;
+		self.end = end;
+
+		Ok(offset)
+	}
+
+	#[cold]
+	fn grow_entries(&mut self) -> Result<(), AllocError>
+	where
+		A: Allocator,
+	{
+		let cap = usize::max(4, self.entries_cap.saturating_mul(2));
+		let layout: MemoryLayout = Layout::array::<Entry<V>>(cap)
+			.map_err(|_| AllocError)?
+			.into();
+		let entries = allocate(&self.alloc, layout)?.cast::<Entry<V>>();
+
+		unsafe {
+			ptr::copy_nonoverlapping(self.entries.as_ptr(), entries.as_ptr(), self.len);
+			self.dealloc_entries();
+		}
+
+		self.entries = entries;
+		self.entries_cap = cap;
+		Ok(())
+	}
+
+	unsafe fn dealloc_entries(&self) {
+		let layout = Layout::array::<Entry<V>>(self.entries_cap).unwrap_unchecked();
+		deallocate(&self.alloc, self.entries.cast(), layout.into());
+	}
+}
+
+impl<V, A> Drop for DynVec<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	fn drop(&mut self) {
+		self.clear();
+
+		unsafe {
+			deallocate(&self.alloc, self.data, self.data_layout);
+			self.dealloc_entries();
+		}
+	}
+}
+
+impl<'a, V, A> IntoIterator for &'a DynVec<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	type IntoIter = Iter<'a, V>;
+	type Item = DynRef<'a, V>;
+
+	#[inline(always)]
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+impl<'a, V, A> IntoIterator for &'a mut DynVec<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	type IntoIter = IterMut<'a, V>;
+	type Item = DynRefMut<'a, V>;
+
+	#[inline(always)]
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter_mut()
+	}
+}
+
+/// Iterator over immutable references to the values of a [`DynVec`].
+pub struct Iter<'a, V>
+where
+	V: VTableRepr + ?Sized,
+{
+	entries: slice::Iter<'a, Entry<V>>,
+	data: NonNull<u8>,
+}
+
+impl<'a, V> Iter<'a, V>
+where
+	V: VTableRepr + ?Sized,
+{
+	#[inline(always)]
+	fn item(&self, entry: &Entry<V>) -> DynRef<'a, V> {
+		DynRef {
+			ptr: DynPtr {
+				ptr: unsafe { self.data.as_ptr().add(entry.offset) as *mut c_void },
+				vtable: entry.vtable,
+			},
+			_lt: PhantomData,
+		}
+	}
+}
+
+impl<'a, V> Iterator for Iter<'a, V>
+where
+	V: VTableRepr + ?Sized,
+{
+	type Item = DynRef<'a, V>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		let entry = self.entries.next()?;
+		Some(self.item(entry))
+	}
+
+	#[inline(always)]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.entries.size_hint()
+	}
+}
+
+impl<V> DoubleEndedIterator for Iter<'_, V>
+where
+	V: VTableRepr + ?Sized,
+{
+	#[inline(always)]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		let entry = self.entries.next_back()?;
+		Some(self.item(entry))
+	}
+}
+
+impl<V> ExactSizeIterator for Iter<'_, V> where V: VTableRepr + ?Sized {}
+impl<V> FusedIterator for Iter<'_, V> where V: VTableRepr + ?Sized {}
+
+/// Iterator over mutable references to the values of a [`DynVec`].
+pub struct IterMut<'a, V>
+where
+	V: VTableRepr + ?Sized,
+{
+	entries: slice::Iter<'a, Entry<V>>,
+	data: NonNull<u8>,
+	_lt: PhantomData<&'a mut ()>,
+}
+
+impl<'a, V> IterMut<'a, V>
+where
+	V: VTableRepr + ?Sized,
+{
+	#[inline(always)]
+	fn item(&self, entry: &Entry<V>) -> DynRefMut<'a, V> {
+		DynRefMut {
+			ptr: DynPtr {
+				ptr: unsafe { self.data.as_ptr().add(entry.offset) as *mut c_void },
+				vtable: entry.vtable,
+			},
+			_lt: PhantomData,
+		}
+	}
+}
+
+impl<'a, V> Iterator for IterMut<'a, V>
+where
+	V: VTableRepr + ?Sized,
+{
+	type Item = DynRefMut<'a, V>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		let entry = self.entries.next()?;
+		Some(self.item(entry))
+	}
+
+	#[inline(always)]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.entries.size_hint()
+	}
+}
+
+impl<V> DoubleEndedIterator for IterMut<'_, V>
+where
+	V: VTableRepr + ?Sized,
+{
+	#[inline(always)]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		let entry = self.entries.next_back()?;
+		Some(self.item(entry))
+	}
+}
+
+impl<V> ExactSizeIterator for IterMut<'_, V> where V: VTableRepr + ?Sized {}
+impl<V> FusedIterator for IterMut<'_, V> where V: VTableRepr + ?Sized {}
+
+/// Draining iterator over the values removed from a [`DynVec`] by
+/// [`DynVec::drain`].
+pub struct Drain<'a, V, A = GlobalAllocator>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	vec: NonNull<DynVec<V, A>>,
+	/// Start of the drained range.
+	start: usize,
+	/// Next value to be returned.
+	next: usize,
+	/// End of the drained range.
+	end: usize,
+	/// Start and length of the values after the drained range.
+	tail_start: usize,
+	tail_len: usize,
+	_lt: PhantomData<&'a mut DynVec<V, A>>,
+}
+
+impl<'a, V, A> Drain<'a, V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	#[inline(always)]
+	fn item(&self, index: usize) -> Taken<'a, V> {
+		unsafe {
+			let vec = self.vec.as_ref();
+			let entry = &*vec.entries.as_ptr().add(index);
+
+			Taken {
+				ptr: vec.dyn_ptr(entry),
+				_lt: PhantomData,
+			}
+		}
+	}
+}
+
+impl<'a, V, A> Iterator for Drain<'a, V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	type Item = Taken<'a, V>;
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.next == self.end {
+			return None
+		}
+
+		self.next += 1;
+		Some(self.item(self.next - 1))
+	}
+
+	#[inline(always)]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.end - self.next;
+		(len, Some(len))
+	}
+}
+
+impl<V, A> DoubleEndedIterator for Drain<'_, V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		if self.next == self.end {
+			return None
+		}
+
+		self.end -= 1;
+		Some(self.item(self.end))
+	}
+}
+
+impl<V, A> ExactSizeIterator for Drain<'_, V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+}
+
+impl<V, A> FusedIterator for Drain<'_, V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+}
+
+impl<V, A> Drop for Drain<'_, V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	fn drop(&mut self) {
+		// drop the values not returned by the iterator
+		for item in &mut *self {
+			drop(item);
+		}
+
+		unsafe {
+			let vec = self.vec.as_mut();
+			let entries = vec.entries.as_ptr();
+			ptr::copy(
+				entries.add(self.tail_start),
+				entries.add(self.start),
+				self.tail_len,
+			);
+
+			vec.len = self.start + self.tail_len;
+			if vec.len == 0 {
+				vec.end = 0;
+			}
+		}
+	}
+}
+
+/// A value removed from a [`DynVec`], which is still stored in the
+/// vector's buffer.
+///
+/// The value is dropped when the `Taken` is dropped, and the vector is
+/// borrowed until then.
+#[repr(C)]
+pub struct Taken<'a, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	ptr: DynPtr<V>,
+	_lt: PhantomData<&'a mut ()>,
+}
+
+unsafe impl<V> Send for Taken<'_, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+	<V::VTable as VTable>::Bounds: Send,
+{
+}
+
+unsafe impl<V> Sync for Taken<'_, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+	<V::VTable as VTable>::Bounds: Sync,
+{
+}
+
+unsafe impl<R, V> AsDyn<R> for Taken<'_, V>
+where
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	type Repr = V;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.ptr.ptr
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		self.ptr.vtable.as_ptr()
+	}
+
+	fn dyn_dealloc(self) {
+		// the value has been moved out, and its memory belongs to the vector
+		mem::forget(self);
+	}
+}
+
+impl<V> Taken<'_, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	/// Immutably borrows the wrapped value.
+	#[inline(always)]
+	pub fn borrow(t: &Self) -> DynRef<'_, V> {
+		DynRef {
+			ptr: t.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Mutably borrows the wrapped value.
+	#[inline(always)]
+	pub fn borrow_mut(t: &mut Self) -> DynRefMut<'_, V> {
+		DynRefMut {
+			ptr: t.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Returns a reference to the VTable of the wrapped value.
+	#[inline(always)]
+	pub fn vtable(t: &Self) -> &V::VTable {
+		unsafe { t.ptr.vtable.as_ref() }
+	}
+}
+
+impl<V> Drop for Taken<'_, V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	fn drop(&mut self) {
+		unsafe { self.ptr.vtable.as_ref().virtual_drop(self.ptr.ptr) };
+	}
+}
@@ -0,0 +1,302 @@
+//! Owned dyntable trait objects stored inline, without allocation.
+//!
+//! A [`DynInline`] stores its value in a fixed-capacity buffer next to the
+//! value's VTable pointer, making it usable without the `alloc` feature.
+//! Any type fitting the buffer's size and alignment can be stored in it.
+//!
+//! # Examples
+//! ```
+//! # use dyntable::{*, inline::*};
+//! #[dyntable]
+//! trait Sensor {
+//!     extern "C" fn read(&mut self) -> u32;
+//! }
+//!
+//! struct Counter(u32);
+//!
+//! impl Sensor for Counter {
+//!     extern "C" fn read(&mut self) -> u32 {
+//!         self.0 += 1;
+//!         self.0
+//!     }
+//! }
+//!
+//! // 16 bytes of storage aligned to a `u64`
+//! let mut sensor = DynInline::<dyn Sensor, 16, u64>::new(Counter(0));
+//! assert_eq!(sensor.read(), 1);
+//!
+//! // values too large for the storage are rejected
+//! let sensor = DynInline::<dyn Sensor, 2>::try_new(Counter(0));
+//! assert!(sensor.is_err());
+//! ```
+
+use core::{
+	cell::UnsafeCell,
+	ffi::c_void,
+	marker::PhantomData,
+	mem::{self, MaybeUninit},
+	ptr::{self, NonNull},
+};
+
+use crate::{
+	AsDyn,
+	AssociatedDrop,
+	DynPtr,
+	DynRef,
+	DynRefMut,
+	DynTrait,
+	SubTable,
+	VTable,
+	VTableRepr,
+};
+
+/// Inline storage of `SIZE` bytes, aligned to `Align`.
+///
+/// The bytes are stored in an `UnsafeCell`, as the value is mutated through
+/// the pointer returned by [`AsDyn::dyn_ptr`], which only borrows the
+/// container immutably.
+#[repr(C)]
+struct Storage<const SIZE: usize, Align> {
+	_align: [Align; 0],
+	bytes: UnsafeCell<[MaybeUninit<u8>; SIZE]>,
+}
+
+impl<const SIZE: usize, Align> Storage<SIZE, Align> {
+	#[inline(always)]
+	fn as_ptr(&self) -> *mut c_void {
+		self.bytes.get() as *mut c_void
+	}
+}
+
+/// Referencing [`Fits::ERROR`] fails compilation if `T` does not fit in
+/// the storage of a `DynInline<_, SIZE, Align>`.
+struct Fits<T, const SIZE: usize, Align>(PhantomData<(T, Align)>);
+
+impl<T, const SIZE: usize, Align> Fits<T, SIZE, Align> {
+	const FITS: bool = mem::size_of::<T>() <= SIZE
+		&& mem::align_of::<T>() <= mem::align_of::<Storage<SIZE, Align>>();
+
+	const ERROR: () = if !Self::FITS {
+		panic!("the value is larger or more aligned than the inline storage")
+	};
+}
+
+/// An owned dyntable trait object stored inline in a buffer of `SIZE` bytes
+/// aligned to `Align`, equivalent to a `Box<dyn Trait>` without allocation.
+/// See the [module docs](self).
+///
+/// The value is moved along with the container, so it is never pinned.
+#[repr(C)]
+pub struct DynInline<V, const SIZE: usize, Align = usize>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	vtable: NonNull<V::VTable>,
+	storage: Storage<SIZE, Align>,
+}
+
+unsafe impl<V, const SIZE: usize, Align> Send for DynInline<V, SIZE, Align>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+	<V::VTable as VTable>::Bounds: Send,
+{
+}
+
+unsafe impl<V, const SIZE: usize, Align> Sync for DynInline<V, SIZE, Align>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+	<V::VTable as VTable>::Bounds: Sync,
+{
+}
+
+unsafe impl<R, V, const SIZE: usize, Align> AsDyn<R> for DynInline<V, SIZE, Align>
+where
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	type Repr = V;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.storage.as_ptr()
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		self.vtable.as_ptr()
+	}
+
+	fn dyn_dealloc(self) {
+		// the value has been moved out, and there is nothing to deallocate
+		mem::forget(self);
+	}
+}
+
+impl<V, const SIZE: usize, Align> DynInline<V, SIZE, Align>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	/// Moves `data` into the inline storage, upcasting it to `V`.
+	///
+	/// Using a type that is larger or more aligned than the storage is a
+	/// compile error. Use [`DynInline::try_new`] to check at runtime instead.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, inline::*};
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u32 {}
+	///
+	/// let x: DynInline<dyn MyTrait, 4> = DynInline::new(0u32);
+	/// ```
+	#[inline]
+	pub fn new<'v, T>(data: T) -> Self
+	where
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let () = Fits::<T, SIZE, Align>::ERROR;
+
+		// SAFETY: the value was checked to fit above
+		unsafe { Self::new_unchecked(data) }
+	}
+
+	/// Moves `data` into the inline storage, upcasting it to `V`, or
+	/// returns it if it is larger or more aligned than the storage.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, inline::*};
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u64 {}
+	///
+	/// assert!(DynInline::<dyn MyTrait, 8, u64>::try_new(0u64).is_ok());
+	/// assert_eq!(DynInline::<dyn MyTrait, 4>::try_new(1u64).err(), Some(1));
+	/// ```
+	#[inline]
+	pub fn try_new<'v, T>(data: T) -> Result<Self, T>
+	where
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		match Fits::<T, SIZE, Align>::FITS {
+			// SAFETY: the value was checked to fit
+			true => Ok(unsafe { Self::new_unchecked(data) }),
+			false => Err(data),
+		}
+	}
+
+	/// # Safety
+	/// `T` must fit in the storage.
+	#[inline(always)]
+	unsafe fn new_unchecked<'v, T>(data: T) -> Self
+	where
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let inline = Self {
+			vtable: NonNull::from(T::STATIC_VTABLE),
+			storage: Storage {
+				_align: [],
+				bytes: UnsafeCell::new([MaybeUninit::uninit(); SIZE]),
+			},
+		};
+
+		(inline.storage.as_ptr() as *mut T).write(data);
+		inline
+	}
+
+	/// Immutably borrows the stored value.
+	#[inline(always)]
+	pub fn borrow(i: &Self) -> DynRef<'_, V> {
+		DynRef {
+			ptr: Self::as_ptr(i),
+			_lt: PhantomData,
+		}
+	}
+
+	/// Mutably borrows the stored value.
+	#[inline(always)]
+	pub fn borrow_mut(i: &mut Self) -> DynRefMut<'_, V> {
+		DynRefMut {
+			ptr: Self::as_ptr(i),
+			_lt: PhantomData,
+		}
+	}
+
+	/// Returns a reference to the VTable of the stored value.
+	///
+	/// This can be used to read VTable data fields, such as
+	/// [associated constants](crate::dyntable#associated-constants).
+	#[inline(always)]
+	pub fn vtable(i: &Self) -> &V::VTable {
+		unsafe { i.vtable.as_ref() }
+	}
+
+	/// Upcast the container to a bounded dyntrait container.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, inline::*};
+	/// #[dyntable]
+	/// trait Animal {}
+	///
+	/// #[dyntable]
+	/// trait Feline: Animal
+	/// where
+	///     dyn Animal:,
+	/// {}
+	///
+	/// struct Cat;
+	///
+	/// impl Feline for Cat {}
+	/// impl Animal for Cat {}
+	///
+	/// let feline: DynInline<dyn Feline, 0> = DynInline::new(Cat);
+	/// let animal: DynInline<dyn Animal, 0> = DynInline::upcast(feline);
+	/// ```
+	#[inline(always)]
+	pub fn upcast<U>(i: Self) -> DynInline<U, SIZE, Align>
+	where
+		U: VTableRepr + ?Sized,
+		U::VTable: AssociatedDrop,
+		V::VTable: SubTable<U::VTable>,
+	{
+		let vtable = NonNull::from(Self::vtable(&i).subtable());
+		// SAFETY: the original value is forgotten
+		let storage = unsafe { ptr::read(&i.storage) };
+		mem::forget(i);
+
+		DynInline { vtable, storage }
+	}
+
+	#[inline(always)]
+	fn as_ptr(i: &Self) -> DynPtr<V> {
+		DynPtr {
+			ptr: i.storage.as_ptr(),
+			vtable: i.vtable,
+		}
+	}
+}
+
+impl<V, const SIZE: usize, Align> Drop for DynInline<V, SIZE, Align>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop,
+{
+	fn drop(&mut self) {
+		unsafe {
+			self.vtable.as_ref().virtual_drop(self.storage.as_ptr());
+		}
+	}
+}
@@ -0,0 +1,341 @@
+//! Itanium C++ ABI compatible classes.
+//!
+//! Traits annotated with `#[dyntable(itanium = true)]` have a VTable laid out
+//! like the VTable of a polymorphic C++ class under the Itanium C++ ABI, which
+//! is used by GCC and Clang on all major platforms other than Windows (MSVC).
+//! This allows abstract C++ classes to be used and implemented from Rust.
+//!
+//! The VTable starts with the virtual destructors of [`Polymorphic`],
+//! followed by the virtual methods of each parent class and finally the
+//! trait's own methods. The offset-to-top and RTTI entries are stored before
+//! the VTable, at negative offsets from the VTable pointer (see
+//! [`VTablePrefix`]).
+//!
+//! Like C++ objects, objects are referenced by a thin `this` pointer to the
+//! object, which stores its VTable pointer as its first field. Objects are
+//! owned by a [`CppBox`], which destroys them the same way as `delete` does.
+//! Rust implementations are allocated in a [`CppObject`].
+//!
+//! # Examples
+//! ```
+//! # use dyntable::{*, itanium::*};
+//! #[dyntable(itanium = true)]
+//! trait Shape: Polymorphic
+//! where
+//!     dyn Polymorphic:,
+//! {
+//!     extern "C" fn area(&self) -> f64;
+//!     extern "C" fn scale(&mut self, factor: f64);
+//! }
+//!
+//! struct Square(f64);
+//!
+//! impl Shape for Square {
+//!     extern "C" fn area(&self) -> f64 {
+//!         self.0 * self.0
+//!     }
+//!
+//!     extern "C" fn scale(&mut self, factor: f64) {
+//!         self.0 *= factor;
+//!     }
+//! }
+//!
+//! let mut square = CppBox::<dyn Shape>::new(Square(2.0));
+//! square.scale(1.5);
+//! assert_eq!(square.area(), 9.0);
+//! ```
+//!
+//! The trait above has the same VTable as the following C++ class:
+//! ```cpp
+//! class Shape {
+//! public:
+//!     virtual ~Shape();
+//!     virtual double area() const = 0;
+//!     virtual void scale(double factor) = 0;
+//! };
+//! ```
+//!
+//! # Notes
+//! Classes may only have a single parent class, as secondary base classes
+//! are stored in separate VTables.
+//!
+//! Methods must use the `C` ABI, which matches the calling convention of
+//! C++ member functions for trivially copyable parameter and return types.
+//!
+//! Rust implementations have no RTTI, so `typeid` and `dynamic_cast` cannot
+//! be used on them from C++.
+
+#![cfg(feature = "alloc")]
+#![cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+
+use core::{
+	ffi::c_void,
+	marker::PhantomData,
+	mem,
+	ptr::{self, NonNull},
+};
+
+use std_alloc::boxed::Box;
+
+use crate::{
+	__private::{DynImplTarget, DynTraitProxy, NoBounds},
+	AsDyn,
+	DynTrait,
+	SubTable,
+	VTable,
+	VTableRepr,
+};
+
+/// The base of polymorphic C++ classes with a virtual destructor, which all
+/// classes derive from.
+///
+/// `Polymorphic` has no Rust methods, as its destructors are implemented by
+/// the object holding the class. Every sized type implements `Polymorphic`.
+pub trait Polymorphic {}
+
+impl<T> Polymorphic for T {}
+
+/// The VTable of [`Polymorphic`], holding a class's virtual destructors.
+#[repr(C)]
+pub struct PolymorphicVTable {
+	/// The complete object destructor, which destroys the object without
+	/// deallocating it.
+	pub complete_dtor: unsafe extern "C" fn(this: *mut c_void),
+	/// The deleting destructor, which destroys the object and deallocates it.
+	/// This is called by `delete`.
+	pub deleting_dtor: unsafe extern "C" fn(this: *mut c_void),
+}
+
+unsafe impl VTable for PolymorphicVTable {
+	type Bounds = dyn NoBounds;
+}
+
+impl VTableRepr for dyn Polymorphic {
+	type VTable = PolymorphicVTable;
+}
+
+/// A VTable laid out as the VTable of a polymorphic C++ class.
+///
+/// # Safety
+/// The VTable must start with a [`PolymorphicVTable`], and every class it
+/// is a [`SubTable`] of must be stored at its start. The static VTables of
+/// its [`DynTrait`] implementations must be stored in a [`PrefixedVTable`].
+///
+/// # Notes
+/// This trait is implemented by the [`dyntable`](crate::dyntable) macro
+/// when the `itanium` option is used.
+pub unsafe trait CppVTable: VTable + 'static {}
+
+unsafe impl CppVTable for PolymorphicVTable {}
+
+/// The entries stored before a C++ VTable, at negative offsets from the
+/// VTable pointer.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct VTablePrefix {
+	/// Offset from the VTable pointer to the start of the object,
+	/// always `0` with single inheritance.
+	pub offset_to_top: isize,
+	/// Pointer to the class's `std::type_info`, or null if the class
+	/// has no RTTI.
+	pub type_info: *const c_void,
+}
+
+impl VTablePrefix {
+	/// The prefix of a VTable without RTTI, used by Rust implementations.
+	pub const NO_RTTI: Self = Self {
+		offset_to_top: 0,
+		type_info: ptr::null(),
+	};
+}
+
+/// A C++ VTable, including the entries stored before the VTable pointer.
+///
+/// The static VTables of Rust implementations are stored in a
+/// `PrefixedVTable`.
+#[repr(C)]
+pub struct PrefixedVTable<V> {
+	pub prefix: VTablePrefix,
+	pub vtable: V,
+}
+
+/// A C++ object holding a Rust value.
+///
+/// A pointer to the object is the `this` pointer of its classes, and the
+/// object's first field is its VTable pointer.
+#[repr(C)]
+pub struct CppObject<T> {
+	vtable: *const c_void,
+	value: T,
+}
+
+impl<T> CppObject<T> {
+	/// Returns a pointer to the value held by the object at `this`.
+	///
+	/// # Safety
+	/// `this` must point to a live `CppObject<T>`.
+	#[inline(always)]
+	pub unsafe fn value(this: *mut c_void) -> *mut T {
+		ptr::addr_of_mut!((*(this as *mut Self)).value)
+	}
+
+	unsafe extern "C" fn complete_dtor(this: *mut c_void) {
+		ptr::drop_in_place(this as *mut Self);
+	}
+
+	unsafe extern "C" fn deleting_dtor(this: *mut c_void) {
+		drop(Box::from_raw(this as *mut Self));
+	}
+}
+
+unsafe impl<'v, T> DynTraitProxy<'v, PolymorphicVTable>
+	for DynImplTarget<CppObject<T>, PolymorphicVTable>
+{
+	const STATIC_VTABLE: &'v PolymorphicVTable = &PrefixedVTable {
+		prefix: VTablePrefix::NO_RTTI,
+		vtable: Self::VTABLE,
+	}
+	.vtable;
+	const VTABLE: PolymorphicVTable = PolymorphicVTable {
+		complete_dtor: CppObject::<T>::complete_dtor,
+		deleting_dtor: CppObject::<T>::deleting_dtor,
+	};
+}
+
+/// An owned pointer to a C++ object.
+///
+/// Dropping the pointer calls the object's deleting destructor, the same
+/// as `delete`.
+#[repr(transparent)]
+pub struct CppBox<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CppVTable,
+{
+	this: NonNull<*const V::VTable>,
+	_marker: PhantomData<*const V::VTable>,
+}
+
+unsafe impl<R, V> AsDyn<R> for CppBox<V>
+where
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+	V::VTable: CppVTable,
+{
+	type Repr = V;
+
+	const OWNS_TARGET: bool = false;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.this.as_ptr() as *mut c_void
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		unsafe { *self.this.as_ptr() }
+	}
+
+	fn dyn_dealloc(self) {
+		unreachable!("C++ objects cannot be taken by value");
+	}
+}
+
+impl<V> CppBox<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CppVTable,
+{
+	/// Moves `value` into a new [`CppObject`] implementing `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	pub fn new<T>(value: T) -> Self
+	where
+		T: 'static,
+		CppObject<T>: DynTrait<'static, V::VTable>,
+	{
+		let object = Box::new(CppObject {
+			vtable: <CppObject<T> as DynTrait<'static, V::VTable>>::STATIC_VTABLE
+				as *const V::VTable as *const c_void,
+			value,
+		});
+
+		// SAFETY: the object is owned by the CppBox
+		unsafe { Self::from_raw(Box::into_raw(object) as *mut c_void) }
+	}
+
+	/// Creates a `CppBox` from the `this` pointer of an object, taking
+	/// ownership of it.
+	///
+	/// # Safety
+	/// `this` must point to a live C++ object implementing `V` that may be
+	/// destroyed with `delete`, and the caller must own it.
+	#[inline(always)]
+	pub unsafe fn from_raw(this: *mut c_void) -> Self {
+		Self {
+			this: NonNull::new_unchecked(this as *mut *const V::VTable),
+			_marker: PhantomData,
+		}
+	}
+
+	/// Returns the `this` pointer of the object, transferring ownership of
+	/// it to the caller. The object may be destroyed from C++ with `delete`.
+	#[inline(always)]
+	pub fn into_raw(p: Self) -> *mut c_void {
+		let this = Self::as_raw(&p);
+		mem::forget(p);
+		this
+	}
+
+	/// Returns the `this` pointer of the object, without giving up
+	/// ownership of it.
+	#[inline(always)]
+	pub fn as_raw(p: &Self) -> *mut c_void {
+		p.this.as_ptr() as *mut c_void
+	}
+
+	/// Returns a reference to the class's VTable.
+	#[inline(always)]
+	pub fn vtable(p: &Self) -> &V::VTable {
+		unsafe { &**p.this.as_ptr() }
+	}
+
+	/// Returns the entries stored before the class's VTable.
+	#[inline(always)]
+	pub fn prefix(p: &Self) -> &VTablePrefix {
+		// SAFETY: C++ VTables are always preceded by their prefix
+		unsafe { &*(*p.this.as_ptr() as *const VTablePrefix).sub(1) }
+	}
+
+	/// Converts the box into a box of one of the class's parents.
+	#[inline(always)]
+	pub fn upcast<U>(p: Self) -> CppBox<U>
+	where
+		U: VTableRepr + ?Sized,
+		U::VTable: CppVTable,
+		V::VTable: SubTable<U::VTable>,
+	{
+		// parent classes are always stored at the start of the VTable
+		debug_assert_eq!(
+			Self::vtable(&p).subtable() as *const U::VTable as *const c_void,
+			Self::vtable(&p) as *const V::VTable as *const c_void,
+		);
+
+		unsafe { CppBox::from_raw(Self::into_raw(p)) }
+	}
+}
+
+impl<V> Drop for CppBox<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: CppVTable,
+{
+	fn drop(&mut self) {
+		unsafe {
+			// SAFETY: C++ VTables always start with their destructors
+			let dtors = &*(*self.this.as_ptr() as *const PolymorphicVTable);
+			(dtors.deleting_dtor)(Self::as_raw(self));
+		}
+	}
+}
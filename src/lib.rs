@@ -99,6 +99,65 @@
 //! ## FFI Usage
 //! Examples of usage with the C FFI can be found in `tests/ffi.rs` and `tests/ffi.c`
 //!
+//! Optional dynptrs, references and boxes, such as `Option<DynBox<dyn Trait>>`,
+//! are FFI safe and represent `None` as a null VTable pointer. See [`DynPtr`].
+//!
+//! Boxes allocated on one side of an FFI or dylib boundary can be freed on
+//! the other side by using the FFI safe [`DynAllocator`](alloc::DynAllocator)
+//! as their allocator, which can wrap any Rust allocator or C `malloc` and
+//! `free` functions.
+//!
+//! Objects created by foreign code can be owned without knowing their
+//! allocator by enabling the `dealloc` [macro option](dyntable#macro-options),
+//! which embeds a deallocation function in the VTable used by
+//! [`DynOwned`](owned::DynOwned).
+//!
+//! C headers describing dyntable VTables can be generated using the
+//! [`cheader`] module. Generated headers also contain C++ wrapper classes,
+//! allowing dyntrait objects to be used and implemented from C++.
+//! An example can be found in `tests/cppffi.rs` and `tests/cppffi.cpp`.
+//!
+//! ## COM Interfaces
+//! Traits can be laid out as COM interfaces using the `com` macro option,
+//! and used through the reference counted [`ComPtr`](com::ComPtr).
+//! See the [`com`] module for details, and `tests/com.rs` and `tests/com.c`
+//! for usage with a C client.
+//!
+//! ## C++ Classes
+//! Traits can be laid out as polymorphic C++ classes under the Itanium C++ ABI
+//! using the `itanium` macro option, and owned through a [`CppBox`](itanium::CppBox).
+//! See the [`itanium`] module for details, and `tests/itanium.rs` and
+//! `tests/itanium.cpp` for usage with C++ classes.
+//!
+//! ## Shared Ownership
+//! Values can be shared between multiple owners using the reference counted
+//! [`DynRc`](rc::DynRc) and [`DynArc`](rc::DynArc) containers of the [`rc`]
+//! module, which store their counts at a fixed offset usable from C.
+//!
+//! ## Thin Pointers
+//! APIs that only pass a single pointer, such as C callbacks taking a `void*`
+//! or atomics, can use the thin pointers of the [`thin`] module, which store
+//! the VTable pointer in the allocation instead of next to the value pointer.
+//!
+//! ## Contiguous Storage
+//! Values of different types can be stored in a single buffer using the
+//! [`DynVec`](dynvec::DynVec) of the [`dynvec`] module, avoiding an
+//! allocation per value.
+//!
+//! ## Batch Dispatch
+//! Values can be grouped by VTable using the [`DynBatch`](batch::DynBatch) of
+//! the [`batch`] module, which calls a method on all values of one type before
+//! moving on to the next type.
+//!
+//! ## Arena Allocation
+//! Many short-lived values can be allocated in an [`Arena`](arena::Arena),
+//! which frees their memory in bulk. See the [`arena`] module.
+//!
+//! ## Inline Storage
+//! Owned values can be stored without allocation in the fixed-capacity
+//! [`DynInline`](inline::DynInline) container of the [`inline`] module,
+//! which is available without the `alloc` feature.
+//!
 //! # Default Features
 //!
 //! ### `std`
@@ -106,7 +165,9 @@
 //! for [`AllocError`](alloc::AllocError).
 //!
 //! ### `alloc`
-//! Enables owning containers ([`DynBox`]) that require allocation.
+//! Enables owning containers ([`DynBox`], [`ThinBox`](thin::ThinBox)) that
+//! require allocation. Without it, [`DynInline`](inline::DynInline) can be
+//! used to own values.
 //!
 //! ## Optional Features
 //!
@@ -124,9 +185,10 @@ extern crate alloc as std_alloc;
 
 use core::{
 	ffi::c_void,
-	marker::PhantomData,
+	marker::{PhantomData, PhantomPinned},
 	mem,
 	ops::{Deref, DerefMut},
+	ptr::{self, NonNull},
 };
 
 /// Dyntable implementation details. You should not depend on these.
@@ -135,7 +197,17 @@ use core::{
 pub mod __private;
 
 pub mod alloc;
+pub mod arena;
+pub mod batch;
 pub mod boxed;
+pub mod cheader;
+pub mod com;
+pub mod dynvec;
+pub mod inline;
+pub mod itanium;
+pub mod owned;
+pub mod rc;
+pub mod thin;
 
 #[cfg(feature = "alloc")]
 #[cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
@@ -219,6 +291,99 @@ pub unsafe trait AssociatedLayout: VTable {
 	fn virtual_layout(&self) -> MemoryLayout;
 }
 
+/// Trait providing a function deallocating the memory of a given opaque
+/// pointer, with the allocator that created it.
+///
+/// An implementation of this trait (when combined with [`AssociatedDrop`])
+/// allows a type associated with this VTable to be owned by a
+/// [`DynOwned`](owned::DynOwned) without knowing its allocator.
+///
+/// # Safety
+/// `virtual_dealloc` must deallocate the given pointer with the allocator
+/// used to allocate values associated with this VTable.
+///
+/// # Notes
+/// This trait is implemented by the [`dyntable`] macro when the `dealloc`
+/// option is enabled.
+pub unsafe trait AssociatedDealloc: VTable {
+	/// Deallocate the given pointer with the allocator used to allocate
+	/// types associated with this VTable, without dropping the value.
+	///
+	/// # Safety
+	/// The pointer must point to memory allocated for a type associated
+	/// with this VTable, which is already dropped or moved out of. The
+	/// memory must not be used after calling this function.
+	unsafe fn virtual_dealloc(&self, instance: *mut c_void);
+}
+
+/// Header embedded at the start of VTables created with the
+/// `header` or `version` [macro options](dyntable#macro-options).
+///
+/// The header describes the VTable it is embedded in, allowing entries
+/// added in later versions of a trait to be checked for before they are
+/// used. See [VTable Headers](dyntable#vtable-headers).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VTableHeader {
+	/// The size of the VTable in bytes, up to the end of its last entry.
+	pub size: usize,
+	/// The version given to the trait the VTable was created from.
+	pub version: u32,
+}
+
+impl VTableHeader {
+	/// Returns `true` if the VTable contains an entry ending at `end`
+	/// bytes from the start of the VTable.
+	///
+	/// Use [`entry_end!`] to find the end of an entry.
+	#[inline(always)]
+	pub const fn contains(&self, end: usize) -> bool {
+		end <= self.size
+	}
+}
+
+/// Trait indicating the VTable starts with a [`VTableHeader`].
+///
+/// # Safety
+/// The VTable must have a stable layout with a [`VTableHeader`]
+/// as its first field. `HEADER` must describe the VTable.
+///
+/// # Notes
+/// This trait is implemented by the [`dyntable`] macro.
+pub unsafe trait AssociatedHeader: VTable {
+	/// The header of VTables created by this version of the trait.
+	const HEADER: VTableHeader;
+}
+
+/// Returns the end of a VTable entry, in bytes from the start of the VTable.
+///
+/// The result can be passed to `has_entry` on dyntable containers to
+/// check if a VTable created by another version of a trait contains
+/// the entry.
+///
+/// # Examples
+/// ```
+/// # use dyntable::*;
+/// #[dyntable(version = 1)]
+/// trait MyTrait {
+///     extern "C" fn foo(&self);
+/// }
+///
+/// impl MyTrait for u8 {
+///     extern "C" fn foo(&self) {}
+/// }
+///
+/// let x: DynBox<dyn MyTrait> = DynBox::new(0u8);
+/// assert!(DynBox::has_entry(&x, entry_end!(MyTraitVTable, foo)));
+/// ```
+#[macro_export]
+macro_rules! entry_end {
+	($vtable:ty, $entry:ident) => {
+		::core::mem::offset_of!($vtable, $entry)
+			+ $crate::__private::field_size(|vtable: &$vtable| &vtable.$entry)
+	};
+}
+
 /// This trait describes this VTable as containing another
 /// VTable.
 ///
@@ -261,12 +426,30 @@ pub trait VTableRepr {
 /// While constructing a [`DynPtr`] is always safe, using the pointer
 /// is only safe as long as the `ptr` and `vtable` fields both point to
 /// valid data.
+///
+/// # Nullability
+/// The `vtable` field is never null, so `Option<DynPtr<V>>` has the same
+/// layout as `DynPtr<V>`, with `None` represented by a null `vtable`. This
+/// extends to types wrapping a dynptr, such as [`DynRef`], [`DynRefMut`]
+/// and [`DynBox`] with a zero sized allocator. In C, an optional dynptr is
+/// the same struct as a dynptr, with `{ .ptr = <any>, .vtable = NULL }`
+/// meaning `None`.
+///
+/// The `ptr` field may be null, as zero sized or foreign objects may not
+/// have any data to point to.
+///
+/// Use [`DynPtr::from_nullable`] and [`DynPtr::into_nullable`] to convert
+/// between optional dynptrs and raw pointers.
+///
+/// The `improper_ctypes` lints do not take the niche of `#[repr(C)]` structs
+/// into account, and may have to be allowed where optional dynptrs are used
+/// in `extern` functions.
 #[repr(C)]
 pub struct DynPtr<V: VTableRepr + ?Sized> {
 	// Having the data pointer before the VTable pointer generates
 	// better ASM. (the compiler cannot change layout due to #[repr(C)])
 	pub ptr: *mut c_void,
-	pub vtable: *const V::VTable,
+	pub vtable: NonNull<V::VTable>,
 }
 
 impl<V: VTableRepr + ?Sized> Copy for DynPtr<V> {}
@@ -289,7 +472,40 @@ impl<V: VTableRepr + ?Sized> DynPtr<V> {
 	{
 		Self {
 			ptr: ptr as *mut c_void,
-			vtable: T::STATIC_VTABLE,
+			vtable: NonNull::from(T::STATIC_VTABLE),
+		}
+	}
+
+	/// Create a [`DynPtr`] from nullable raw pointers, such as ones passed
+	/// from C, returning `None` if `vtable` is null.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::*;
+	/// use core::ptr;
+	///
+	/// #[dyntable]
+	/// trait MyTrait {}
+	///
+	/// let ptr = DynPtr::<dyn MyTrait>::from_nullable(ptr::null_mut(), ptr::null());
+	/// assert!(ptr.is_none());
+	/// ```
+	#[inline(always)]
+	pub fn from_nullable(ptr: *mut c_void, vtable: *const V::VTable) -> Option<Self> {
+		Some(Self {
+			ptr,
+			vtable: NonNull::new(vtable as *mut V::VTable)?,
+		})
+	}
+
+	/// Convert an optional [`DynPtr`] into raw pointers, returning two null
+	/// pointers for `None`.
+	#[inline(always)]
+	pub fn into_nullable(ptr: Option<Self>) -> (*mut c_void, *const V::VTable) {
+		match ptr {
+			Some(ptr) => (ptr.ptr, ptr.vtable.as_ptr()),
+			None => (ptr::null_mut(), ptr::null()),
 		}
 	}
 
@@ -331,9 +547,38 @@ impl<V: VTableRepr + ?Sized> DynPtr<V> {
 			ptr: ptr.ptr,
 			/// SAFETY: the subtable is a slice into the existing vtable
 			/// pointer, and therefore has the same lifetime.
-			vtable: unsafe { (*ptr.vtable).subtable() },
+			vtable: NonNull::from(unsafe { ptr.vtable.as_ref() }.subtable()),
 		}
 	}
+
+	/// Reads the header of the pointed to VTable.
+	///
+	/// # Safety
+	/// The `vtable` field must point to a valid VTable, which may be from
+	/// an older or newer version of the trait.
+	#[inline(always)]
+	pub unsafe fn header(ptr: Self) -> VTableHeader
+	where
+		V::VTable: AssociatedHeader,
+	{
+		// SAFETY: the header is the first field of the VTable (see `AssociatedHeader`),
+		// and only the header is read, which is present in all versions.
+		unsafe { *(ptr.vtable.as_ptr() as *const VTableHeader) }
+	}
+
+	/// Returns `true` if the pointed to VTable contains an entry ending
+	/// at `end`. Use [`entry_end!`] to find the end of an entry.
+	///
+	/// # Safety
+	/// The `vtable` field must point to a valid VTable, which may be from
+	/// an older or newer version of the trait.
+	#[inline(always)]
+	pub unsafe fn has_entry(ptr: Self, end: usize) -> bool
+	where
+		V::VTable: AssociatedHeader,
+	{
+		unsafe { Self::header(ptr) }.contains(end)
+	}
 }
 
 /// Wrapper for the `self` parameter of vtable methods to bound
@@ -371,19 +616,52 @@ impl DynSelf<'_> {
 /// The VTable pointer provided by `dyn_vtable` must be
 /// valid for at least the lifetime of self.
 ///
+/// `PINS_TARGET` may only be `true` if a `Pin<&Self>` or `Pin<&mut Self>`
+/// guarantees that the value pointed to by `dyn_ptr` is pinned.
+///
+/// `OWNS_TARGET` may only be `true` if the value pointed to by `dyn_ptr`
+/// may be moved out of the container, after which `dyn_dealloc` is called.
+///
+/// `UNIQUE_TARGET` may only be `true` if a `&mut Self` guarantees exclusive
+/// access to the value pointed to by `dyn_ptr`.
+///
 /// # Notes
 /// This trait is used to implement dyntable container types.
 ///
 /// The `R` generic parameter is used for disambiguation of AsDyn impls,
 /// to allow implementations of dyntable traits on nonlocal types. When
 /// implemented by the [`dyntable`] macro, it will always be the `dyn` form
-/// of the trait (e.g. `dyn Foo<()>`).
+/// of the trait (e.g. `dyn Foo<()>`), with any associated types bound to a
+/// placeholder type.
 /// In most cases the disambiguator will be required to call methods on `AsDyn`,
 /// e.g. `AsDyn::<dyn MyTrait>::dyn_vtable(&boxed_mytrait)` to get a vtable.
-pub unsafe trait AsDyn<R: VTableRepr + ?Sized> {
+pub unsafe trait AsDyn<R: ?Sized> {
 	/// The dyn Trait that will be implemented for this type.
 	type Repr: VTableRepr + ?Sized;
 
+	/// Whether pinning this container pins the value it points to.
+	///
+	/// Methods with `Pin` receivers can only be called through containers
+	/// where this is `true`. Calling them through any other container is
+	/// a compile error.
+	const PINS_TARGET: bool = false;
+
+	/// Whether this container uniquely owns the value it points to, allowing
+	/// the value to be moved out of it.
+	///
+	/// Methods taking `self` by value can only be called through containers
+	/// where this is `true`. Calling them through any other container is
+	/// a compile error.
+	const OWNS_TARGET: bool = true;
+
+	/// Whether a mutable borrow of this container grants exclusive access
+	/// to the value it points to.
+	///
+	/// Methods taking `&mut self` or `Pin<&mut Self>` can only be called
+	/// through containers where this is `true`. Calling them through any
+	/// other container is a compile error.
+	const UNIQUE_TARGET: bool = true;
+
 	/// Returns a pointer to the underlying data of this dynptr.
 	///
 	/// The provided pointer will be valid for at least the lifetime
@@ -464,6 +742,40 @@ impl<'a, V: VTableRepr + ?Sized> DynRef<'a, V> {
 		r
 	}
 
+	/// Returns a reference to the VTable of the referenced value.
+	///
+	/// This can be used to read VTable data fields, such as
+	/// [associated constants](dyntable#associated-constants).
+	#[inline(always)]
+	pub fn vtable(r: &Self) -> &'a V::VTable {
+		// SAFETY: the vtable pointer is valid for `'a` (see `from_raw`)
+		unsafe { r.ptr.vtable.as_ref() }
+	}
+
+	/// Reads the header of the VTable, which may be from an older or newer
+	/// version of the trait.
+	#[inline(always)]
+	pub fn header(r: &Self) -> VTableHeader
+	where
+		V::VTable: AssociatedHeader,
+	{
+		// SAFETY: the vtable pointer is valid for `'a` (see `from_raw`)
+		unsafe { DynPtr::header(r.ptr) }
+	}
+
+	/// Returns `true` if the VTable contains an entry ending at `end`.
+	/// Use [`entry_end!`] to find the end of an entry.
+	///
+	/// Entries missing from the VTable must not be called.
+	/// See [VTable Headers](dyntable#vtable-headers).
+	#[inline(always)]
+	pub fn has_entry(r: &Self, end: usize) -> bool
+	where
+		V::VTable: AssociatedHeader,
+	{
+		Self::header(r).contains(end)
+	}
+
 	/// Upcast the given dynref to a bounded dyntrait ref.
 	///
 	/// # Examples
@@ -583,6 +895,40 @@ impl<'a, V: VTableRepr + ?Sized> DynRefMut<'a, V> {
 		unsafe { DynRefMut::from_raw(r.ptr) }
 	}
 
+	/// Returns a reference to the VTable of the referenced value.
+	///
+	/// This can be used to read VTable data fields, such as
+	/// [associated constants](dyntable#associated-constants).
+	#[inline(always)]
+	pub fn vtable(r: &Self) -> &'a V::VTable {
+		// SAFETY: the vtable pointer is valid for `'a` (see `from_raw`)
+		unsafe { r.ptr.vtable.as_ref() }
+	}
+
+	/// Reads the header of the VTable, which may be from an older or newer
+	/// version of the trait.
+	#[inline(always)]
+	pub fn header(r: &Self) -> VTableHeader
+	where
+		V::VTable: AssociatedHeader,
+	{
+		// SAFETY: the vtable pointer is valid for `'a` (see `from_raw`)
+		unsafe { DynPtr::header(r.ptr) }
+	}
+
+	/// Returns `true` if the VTable contains an entry ending at `end`.
+	/// Use [`entry_end!`] to find the end of an entry.
+	///
+	/// Entries missing from the VTable must not be called.
+	/// See [VTable Headers](dyntable#vtable-headers).
+	#[inline(always)]
+	pub fn has_entry(r: &Self, end: usize) -> bool
+	where
+		V::VTable: AssociatedHeader,
+	{
+		Self::header(r).contains(end)
+	}
+
 	/// Upcast the given mutable dynref to a bounded dyntrait ref.
 	///
 	/// # Examples
@@ -648,7 +994,9 @@ impl<V: VTableRepr + ?Sized> DerefMut for DynRefMut<'_, V> {
 #[repr(transparent)]
 pub struct DynRefCallProxy<'a, V: VTableRepr + ?Sized> {
 	ptr: DynPtr<V>,
-	_lt: PhantomData<&'a ()>,
+	// The proxy stands in for the referenced value, and a pinned proxy is
+	// only obtainable through a pinned `DynRef` or `DynRefMut`.
+	_lt: PhantomData<(&'a (), PhantomPinned)>,
 }
 
 impl<V: VTableRepr + ?Sized> DynRefCallProxy<'_, V> {
@@ -667,11 +1015,13 @@ impl<V: VTableRepr + ?Sized> DynRefCallProxy<'_, V> {
 
 unsafe impl<R, V> AsDyn<R> for DynRefCallProxy<'_, V>
 where
-	R: VTableRepr + ?Sized,
+	R: ?Sized,
 	V: VTableRepr + ?Sized,
 {
 	type Repr = V;
 
+	const PINS_TARGET: bool = true;
+
 	#[inline(always)]
 	fn dyn_ptr(&self) -> *mut c_void {
 		self.ptr.ptr
@@ -679,7 +1029,7 @@ where
 
 	#[inline(always)]
 	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
-		self.ptr.vtable
+		self.ptr.vtable.as_ptr()
 	}
 
 	fn dyn_dealloc(self) {
@@ -693,13 +1043,16 @@ use alloc::MemoryLayout;
 /// annotated trait as a FFI safe dynptr.
 ///
 /// # Trait Requirements
-/// - The trait must only contain methods (associated functions and const
-///   values are not yet supported)
+/// - The trait must only contain methods, [static functions](#static-functions),
+///   associated types and [associated constants](#associated-constants)
 /// - All trait methods must explicitly specify their ABI, usually `C`, unless
 ///   the `relax_abi = true` parameter is passed to the `#[dyntable]` invocation
 /// - No trait methods may have a receiver type other than `Self`, and must use
-///   the implicit self shorthand (`fn foo(&self)`)
+///   the implicit self shorthand (`fn foo(&self)`), or be
+///   [pinned](#pinned-receivers) (`fn foo(self: Pin<&mut Self>)`)
 /// - The trait must be [object safe][ref-obj-safety].
+/// - Methods bounded by `where Self: Sized` are exempt from the above
+///   method requirements, see [Sized Methods](#sized-methods).
 /// - All trait bounds (supertraits) must also be `#[dyntable]` annotated traits
 ///   (except `Send` and `Sync`)
 /// - All trait bounds, including indirect bounds
@@ -880,6 +1233,278 @@ use alloc::MemoryLayout;
 /// {}
 /// ```
 ///
+/// # Default Methods
+/// Methods may provide a default body, which is kept on the generated trait.
+/// Implementors that do not override a default method have the default
+/// implementation placed in their VTable.
+///
+/// ```
+/// # use dyntable::*;
+/// #[dyntable]
+/// trait Greeter {
+///     extern "C" fn name(&self) -> u32;
+///
+///     extern "C" fn greeting(&self) -> u32 {
+///         self.name() + 1
+///     }
+/// }
+///
+/// struct Person;
+///
+/// impl Greeter for Person {
+///     extern "C" fn name(&self) -> u32 {
+///         1
+///     }
+/// }
+///
+/// let greeter = DynBox::<dyn Greeter>::new(Person);
+/// assert_eq!(greeter.greeting(), 2);
+/// ```
+///
+/// # Sized Methods
+/// Methods bounded by `where Self: Sized` are excluded from the VTable, the
+/// same as with native `dyn` traits. They may take generics, return `Self`
+/// or omit the receiver, and are not required to specify an ABI.
+///
+/// Sized methods cannot be called through dyntrait containers such as a
+/// [`DynBox`]. Doing so is a compile error, however as it is raised during
+/// monomorphization it is only reported by `cargo build`, not `cargo check`.
+///
+/// ```
+/// # use dyntable::*;
+/// #[dyntable]
+/// trait Counter {
+///     extern "C" fn next(&mut self) -> u32;
+///
+///     fn new() -> Self
+///     where
+///         Self: Sized;
+///
+///     fn skip<const N: usize>(&mut self)
+///     where
+///         Self: Sized,
+///     {
+///         for _ in 0..N {
+///             self.next();
+///         }
+///     }
+/// }
+///
+/// struct Step(u32);
+///
+/// impl Counter for Step {
+///     extern "C" fn next(&mut self) -> u32 {
+///         self.0 += 1;
+///         self.0
+///     }
+///
+///     fn new() -> Self {
+///         Self(0)
+///     }
+/// }
+///
+/// let mut step = Step::new();
+/// step.skip::<2>();
+///
+/// let mut counter = DynBox::<dyn Counter>::new(step);
+/// assert_eq!(counter.next(), 3);
+/// ```
+///
+/// # Static Functions
+/// Functions without a receiver are stored in the VTable like methods, and are
+/// bounded by `where Self: Sized` on the generated trait to keep it object safe.
+/// They are called through the VTable, either from a dyntrait container or
+/// from a bare VTable reference, which makes them suitable for factories and
+/// type information.
+///
+/// ```
+/// # use dyntable::*;
+/// #[dyntable]
+/// trait Plugin {
+///     extern "C" fn create(id: u32) -> DynBox<dyn Plugin>;
+///
+///     extern "C" fn id(&self) -> u32;
+/// }
+///
+/// struct MyPlugin(u32);
+///
+/// impl Plugin for MyPlugin {
+///     extern "C" fn create(id: u32) -> DynBox<dyn Plugin> {
+///         DynBox::new(MyPlugin(id))
+///     }
+///
+///     extern "C" fn id(&self) -> u32 {
+///         self.0
+///     }
+/// }
+///
+/// let plugin = DynBox::<dyn Plugin>::new(MyPlugin(1));
+/// let created = DynBox::vtable(&plugin).create(2);
+/// assert_eq!(created.id(), 2);
+///
+/// // a VTable on its own is enough to call a static function
+/// let vtable = <MyPlugin as DynTrait<PluginVTable>>::STATIC_VTABLE;
+/// assert_eq!(vtable.create(3).id(), 3);
+/// ```
+///
+/// A static function is exposed as both a function pointer field and an
+/// inherent method of the same name on the VTable.
+///
+/// # Pinned Receivers
+/// Methods may take `self: Pin<&Self>` or `self: Pin<&mut Self>`, which are
+/// passed through the VTable the same way as references. This allows
+/// self-referential types, such as hand written futures, to be used
+/// as dyntraits.
+///
+/// Pinned methods are called through a pinned container, using
+/// [`DynBox::pin`](boxed::DynBox::pin) or
+/// [`DynBox::into_pin`](boxed::DynBox::into_pin) to create one and
+/// [`DynBox::as_pin_mut`](boxed::DynBox::as_pin_mut) or
+/// [`DynBox::as_pin_ref`](boxed::DynBox::as_pin_ref) to project it to a
+/// pinned [`DynRefMut`] or [`DynRef`].
+///
+/// ```
+/// # use dyntable::*;
+/// use core::pin::Pin;
+///
+/// #[dyntable]
+/// trait Generator {
+///     extern "C" fn resume(self: Pin<&mut Self>) -> u32;
+/// }
+///
+/// struct Countdown(u32);
+///
+/// impl Generator for Countdown {
+///     extern "C" fn resume(mut self: Pin<&mut Self>) -> u32 {
+///         self.0 -= 1;
+///         self.0
+///     }
+/// }
+///
+/// let mut generator: Pin<DynBox<dyn Generator>> = DynBox::pin(Countdown(3));
+/// let mut generator_ref: Pin<DynRefMut<dyn Generator>> = DynBox::as_pin_mut(&mut generator);
+/// assert_eq!(generator_ref.as_mut().resume(), 2);
+/// assert_eq!(generator_ref.as_mut().resume(), 1);
+/// ```
+///
+/// Calling a pinned method through a container that does not pin its
+/// target, such as a `Pin<&mut DynBox<_>>`, is a compile error raised
+/// during monomorphization (see [`AsDyn::PINS_TARGET`]).
+///
+/// # Optional Methods
+/// Methods annotated with `#[dyntable(optional)]` are stored as nullable
+/// function pointers (`Option<extern "ABI" fn(...)>`) in the VTable.
+/// This allows implementations written in other languages, or against an
+/// older version of the trait, to leave the entry out by setting it to `NULL`.
+///
+/// Optional methods must have a default body. Rust implementors may leave them
+/// out, in which case the default body is stored in the VTable. For every
+/// optional method a `try_` prefixed method is added to the trait, which
+/// returns `None` instead of calling the method when the entry is missing.
+/// Calling the method itself on a container falls back to the default body.
+///
+/// ```
+/// # use dyntable::*;
+/// #[dyntable]
+/// trait Plugin {
+///     extern "C" fn id(&self) -> u32;
+///
+///     #[dyntable(optional)]
+///     extern "C" fn priority(&self) -> u32 {
+///         0
+///     }
+/// }
+///
+/// struct MyPlugin;
+///
+/// impl Plugin for MyPlugin {
+///     extern "C" fn id(&self) -> u32 {
+///         1
+///     }
+/// }
+///
+/// let plugin = DynBox::<dyn Plugin>::new(MyPlugin);
+/// assert_eq!(plugin.priority(), 0);
+/// assert_eq!(plugin.try_priority(), Some(0));
+/// assert!(DynBox::vtable(&plugin).priority.is_some());
+/// ```
+///
+/// Optional static functions return `Option<R>` when called through the
+/// VTable's inherent method.
+///
+/// # Associated Types
+/// Associated types are represented as additional generic parameters
+/// of the VTable, and must be specified when naming the trait object,
+/// the same way as native `dyn` traits.
+///
+/// ```
+/// # use dyntable::*;
+/// #[dyntable]
+/// trait Reader {
+///     type Item;
+///
+///     extern "C" fn read(&mut self) -> Self::Item;
+/// }
+///
+/// struct Counter(u32);
+///
+/// impl Reader for Counter {
+///     type Item = u32;
+///
+///     extern "C" fn read(&mut self) -> u32 {
+///         self.0 += 1;
+///         self.0
+///     }
+/// }
+///
+/// let mut reader = DynBox::<dyn Reader<Item = u32>>::new(Counter(0));
+/// assert_eq!(reader.read(), 1);
+/// ```
+///
+/// Trait bounds on traits with associated types must bind every
+/// associated type, in both the bound and its `dyn` entry
+/// (e.g. `trait Writer<T>: Reader<Item = T> where dyn Reader<Item = T>:,`).
+///
+/// # Associated Constants
+/// Associated constants are stored as data fields in the VTable, in the
+/// order they are declared in along with methods. As a trait with associated
+/// constants cannot be used as a `dyn` trait, they are moved into a seperate
+/// trait named `(your trait)Consts` (see the `consts` option in
+/// [Macro Options](#macro-options)), which must be implemented alongside
+/// the dyntrait.
+///
+/// The constants of a dyntrait container can be read from its VTable without
+/// calling a function, using [`DynRef::vtable`], [`DynRefMut::vtable`] or
+/// [`DynBox::vtable`](boxed::DynBox::vtable).
+///
+/// ```
+/// # use dyntable::*;
+/// #[dyntable]
+/// trait Plugin {
+///     const VERSION: u32;
+///     const CAPABILITIES: u32 = 0;
+///
+///     extern "C" fn run(&self);
+/// }
+///
+/// struct MyPlugin;
+///
+/// impl Plugin for MyPlugin {
+///     extern "C" fn run(&self) {}
+/// }
+///
+/// impl PluginConsts for MyPlugin {
+///     const VERSION: u32 = 2;
+/// }
+///
+/// let plugin = DynBox::<dyn Plugin>::new(MyPlugin);
+/// assert_eq!(DynBox::vtable(&plugin).VERSION, 2);
+/// assert_eq!(DynBox::vtable(&plugin).CAPABILITIES, 0);
+/// ```
+///
+/// The types of associated constants cannot depend on trait generics or
+/// associated types.
+///
 /// # Macro Options
 /// - `repr` - The generated VTable's repr. `Rust` may be specified in addition
 ///   to any repr permitted by the `#[repr(...)]` attribute.
@@ -896,20 +1521,95 @@ use alloc::MemoryLayout;
 /// - `drop` - Specify the existence and ABI of the VTable's `drop` function. Valid
 ///   options are `none`, to remove the `drop` function, or any ABI permitted by the
 ///   `extern "..."` specifier. This option is required for using the annotated trait
-///   in owned dyn containers such as a [`DynBox`].
+///   in owned dyn containers such as a [`DynBox`]. Traits without a `drop` function
+///   can be owned by a [`NoDropBox`](boxed::NoDropBox), which never drops its value.
 ///
 ///   Defaults to `"C"`.
 ///
 /// - `embed_layout` - Embed the layout (size + align) of the implementing type in the
 ///   vtable. This option is required for using the annotated trait in owned dyn
-///   containers such as a [`DynBox`].
+///   containers such as a [`DynBox`], unless the container's deallocator does not
+///   need a layout, such as a [`Free`](alloc::Free) deallocator.
 ///
 ///   Defaults to `true`.
 ///
+/// - `dealloc` - Specify the existence and ABI of the VTable's `dealloc`
+///   function, which frees a value with the allocator that created it. Valid
+///   options are `none` or any ABI permitted by the `extern "..."` specifier.
+///   This option is required for using the annotated trait in a
+///   [`DynOwned`](owned::DynOwned). VTables of Rust types free values in the
+///   global allocator, which requires the `alloc` feature.
+///
+///   Defaults to `none`.
+///
+/// - `header` - Embed a [`VTableHeader`] at the start of the VTable, which
+///   allows checking which entries are present at runtime.
+///   See [VTable Headers](#vtable-headers). Cannot be used with `repr = Rust`.
+///
+///   Defaults to `false`, or `true` if `version` is specified.
+///
+/// - `version` - The version stored in the VTable header, as a `u32`.
+///   Enables `header`.
+///
+///   Defaults to `0`.
+///
+/// - `reserved` - Append the given number of reserved slots to the VTable,
+///   which are always null. See [Reserved Slots](#reserved-slots).
+///   Cannot be used with `repr = Rust`.
+///
+///   Defaults to `0`.
+///
+/// - `c_header` - Implement [`CVTable`](cheader::CVTable) for the VTable,
+///   allowing a C header with C++ wrapper classes to be generated for it
+///   using the [`cheader`] module. Requires the `alloc` feature, `repr = C` and a non generic trait without
+///   associated types. All ABIs used must be representable in C.
+///
+///   Defaults to `false`.
+///
+/// - `com` - Lay out the VTable as a COM interface with the given GUID,
+///   such as `com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c"`. See the [`com`]
+///   module. Requires the `alloc` feature, and cannot be used with options
+///   changing the layout of the VTable. The trait must have a single inline
+///   parent interface, such as [`IUnknown`](com::IUnknown), whose VTable
+///   has the default name. Methods must take `&self` and use the `system` ABI.
+///
+///   Not enabled by default.
+///
+/// - `itanium` - Lay out the VTable as the VTable of a polymorphic C++ class
+///   under the Itanium C++ ABI. See the [`itanium`] module. Requires the `alloc`
+///   feature, and cannot be used with `com` or options changing the layout of
+///   the VTable. The trait must have a single inline parent class, such as
+///   [`Polymorphic`](itanium::Polymorphic), whose VTable has the default name.
+///   Methods must take `&self` or `&mut self` and use the `C` ABI.
+///
+///   Defaults to `false`.
+///
 /// - `vtable` - Specify the name of the generated VTable.
 ///
 ///   Defaults to `(your trait)VTable`.
 ///
+/// - `consts` - Specify the name of the generated trait holding
+///   [associated constants](#associated-constants).
+///
+///   Defaults to `(your trait)Consts`.
+///
+/// - `drop_slot` - The position of the `drop` function in the VTable.
+///   See [Foreign VTable Layouts](#foreign-vtable-layouts).
+///
+/// - `layout_slot` - The position of the embedded layout in the VTable.
+///   See [Foreign VTable Layouts](#foreign-vtable-layouts).
+///
+/// - `dealloc_slot` - The position of the `dealloc` function in the VTable.
+///   See [Foreign VTable Layouts](#foreign-vtable-layouts).
+///
+/// Methods and associated constants accept the following options, using
+/// `#[dyntable(...)]` on the entry:
+/// - `optional` - Make the method's VTable entry nullable,
+///   see [Optional Methods](#optional-methods). Methods only.
+/// - `slot` - The position of the entry in the VTable.
+/// - `field` - The name of the entry's VTable field.
+///   Defaults to the name of the entry.
+///
 /// All above options are optional. Below is an example of the `#[dyntable]`
 /// attribute with all options explicitly specified with default values:
 /// ```
@@ -919,7 +1619,11 @@ use alloc::MemoryLayout;
 ///     relax_abi = false,
 ///     drop = "C",
 ///     embed_layout = true,
-///     vtable = MyTraitVTable
+///     header = false,
+///     reserved = 0,
+///     c_header = false,
+///     vtable = MyTraitVTable,
+///     consts = MyTraitConsts
 /// )]
 /// trait MyTrait {}
 /// ```
@@ -927,10 +1631,15 @@ use alloc::MemoryLayout;
 /// # VTable Layout
 /// VTables are represented as a struct that is by default `#[repr(C)]` (see
 /// the `repr` option described in [Macro Options](#macro-options)).
-/// The VTable entries are laid out in the order they have been listed in,
-/// preceeded by a pointer to the type's `drop` function, the memory layout
-/// of the trait's implementing type (if not disabled) and any `dyn` bounds
-/// (in the order they appear) as shown below:
+/// The VTable entries (methods and associated constants) are laid out in the
+/// order they have been listed in, preceeded by the [VTable header](#vtable-headers)
+/// (if enabled), a pointer to the type's `drop`
+/// function, the memory layout of the trait's implementing type (if not disabled),
+/// a pointer to the type's `dealloc` function (if enabled)
+/// and any `dyn` bounds (in the order they appear), and followed by any
+/// [reserved slots](#reserved-slots) as shown below. This order can be changed
+/// by giving fields an explicit position (see
+/// [Foreign VTable Layouts](#foreign-vtable-layouts)).
 ///
 /// ```
 /// # use dyntable::*;
@@ -959,6 +1668,7 @@ use alloc::MemoryLayout;
 ///     extern "C" fn my_function(&self);
 ///     extern "C" fn my_lifetime_function<'a>(&'a self) -> &'a ();
 ///     extern "C" fn my_owned_function(self);
+///     extern "C" fn my_static_function();
 /// }
 ///
 /// // MyTrait's VTable:
@@ -988,6 +1698,8 @@ use alloc::MemoryLayout;
 ///     my_lifetime_function: for<'a> extern "C" fn(dyntable::DynSelf<'a>) -> &'a (),
 ///     // an owned self parameter does not use `DynSelf`.
 ///     my_owned_function: extern "C" fn(*mut core::ffi::c_void),
+///     // a static function does not take self at all.
+///     my_static_function: extern "C" fn(),
 /// }
 /// # // this sanity check is at least better than nothing
 /// # use std::alloc::Layout;
@@ -1003,12 +1715,130 @@ use alloc::MemoryLayout;
 ///   `where dyn A: C, dyn B` is not the same as `where dyn A, dyn B: C`.
 /// - Only additions have been made to trait methods, and only at the end of the method
 ///   list. Removing a method is a backwards incompatible change.
+///   Giving an added method a default body keeps the change non breaking for
+///   Rust implementors of the trait as well. Marking an added method as
+///   [optional](#optional-methods) keeps it non breaking for foreign
+///   implementors.
 /// - All methods have the same ABI as previous versions. Method parameters and return
 ///   types must either match or share the same ABI.
 ///
+/// ## VTable Headers
+/// A VTable from an older version of a trait is shorter than the current one,
+/// and calling an entry past its end is undefined behavior. The `header` and
+/// `version` [macro options](#macro-options) embed a [`VTableHeader`] at the
+/// start of the VTable, holding the size of the VTable and the trait's version.
+/// As the header is always the first field, it can be read from VTables of
+/// any version.
+///
+/// [`DynPtr`], [`DynRef`], [`DynRefMut`] and [`DynBox`] provide `header`, to read
+/// the header, and `has_entry`, to check if an entry is present before using it.
+/// The end of an entry is found with [`entry_end!`].
+///
+/// ```
+/// # use dyntable::*;
+/// #[dyntable(version = 2)]
+/// trait Plugin {
+///     extern "C" fn id(&self) -> u32;
+///     // added in version 2
+///     extern "C" fn name(&self) -> u32;
+/// }
+///
+/// fn name(plugin: DynRef<dyn Plugin>) -> Option<u32> {
+///     match DynRef::has_entry(&plugin, entry_end!(PluginVTable, name)) {
+///         true => Some(plugin.name()),
+///         // the plugin was built against version 1
+///         false => None,
+///     }
+/// }
+/// # struct MyPlugin;
+/// # impl Plugin for MyPlugin {
+/// #     extern "C" fn id(&self) -> u32 { 0 }
+/// #     extern "C" fn name(&self) -> u32 { 1 }
+/// # }
+/// # assert_eq!(name(DynRef::from(&MyPlugin)), Some(1));
+/// ```
+///
+/// The header must be enabled in the first version of a trait, as adding it
+/// changes the position of every other entry. The size recorded in the header
+/// excludes trailing padding, so entries placed in the padding of an older
+/// VTable are correctly reported as missing.
+///
+/// ## Foreign VTable Layouts
+/// The position of VTable fields can be set explicitly, to implement a VTable
+/// already defined by a foreign library. Fields given a position with the
+/// `slot` entry option, or the `drop_slot`, `layout_slot` and `dealloc_slot`
+/// [macro options](#macro-options), are placed at that position (starting
+/// from `0`). The remaining fields fill the free positions in their usual order.
+/// The `field` entry option sets the name of an entry's VTable field, to match
+/// the foreign struct. The VTable header and reserved slots cannot be moved.
+///
+/// ```
+/// # use dyntable::*;
+/// // struct stream_vtable {
+/// //     size_t (*read)(void *self, char *buf, size_t len);
+/// //     void (*destroy)(void *self);
+/// //     size_t (*write)(void *self, const char *buf, size_t len);
+/// // };
+/// #[dyntable(drop_slot = 1, embed_layout = false, vtable = StreamVTable)]
+/// trait Stream {
+///     #[dyntable(slot = 2)]
+///     extern "C" fn write(&mut self, buf: *const u8, len: usize) -> usize;
+///
+///     #[dyntable(field = read)]
+///     extern "C" fn read_into(&mut self, buf: *mut u8, len: usize) -> usize;
+/// }
+///
+/// # use core::mem::offset_of;
+/// assert_eq!(offset_of!(StreamVTable, read), 0);
+/// assert_eq!(offset_of!(StreamVTable, write), 2 * size_of::<usize>());
+/// ```
+///
+/// A foreign VTable without an embedded layout can be owned by a [`DynBox`]
+/// using a [`Free`](alloc::Free) deallocator, such as C's `free`. A foreign
+/// VTable without a `drop` function can be owned by a
+/// [`NoDropBox`](boxed::NoDropBox).
+///
+/// ## Reserved Slots
+/// The `reserved = N` [macro option](#macro-options) appends `N` null function
+/// pointer slots to the VTable, as a field named `__reserved`. This allows
+/// publishing a fixed VTable size in a C ABI, while keeping room to add
+/// methods later.
+///
+/// A later version of the trait takes a slot by adding a method at the end of
+/// the method list and reducing `reserved` by one, which keeps the VTable's
+/// size unchanged. Since the slot is null in VTables created by older versions,
+/// a method taking a reserved slot should be [optional](#optional-methods).
+///
+/// ```
+/// # use dyntable::*;
+/// // version 1
+/// #[dyntable(reserved = 2)]
+/// trait PluginV1 {
+///     extern "C" fn id(&self) -> u32;
+/// }
+///
+/// // version 2
+/// #[dyntable(reserved = 1)]
+/// trait PluginV2 {
+///     extern "C" fn id(&self) -> u32;
+///
+///     #[dyntable(optional)]
+///     extern "C" fn name(&self) -> u32 {
+///         0
+///     }
+/// }
+///
+/// # use core::mem::size_of;
+/// assert_eq!(size_of::<PluginV1VTable>(), size_of::<PluginV2VTable>());
+/// ```
+///
+/// When combined with a [VTable header](#vtable-headers), reserved slots are
+/// counted in the header's size.
+///
 /// # What a `#[dyntable]` invocation generates
 /// The `#[dyntable]` macro generates the following code:
 /// - A VTable
+/// - A trait holding the trait's associated constants, if it has any.
 /// - An implementation of [`VTable`] for the generated VTable.
 /// - Implementations of [`VTableRepr`] for `dyn YourTrait`, `dyn YourTrait + Send`,
 ///   `dyn YourTrait + Sync` and `dyn YourTrait + Send + Sync`. These implementations
@@ -1021,6 +1851,18 @@ use alloc::MemoryLayout;
 ///   (see `src/private.rs` for details)
 /// - Implementations of [`AssociatedDrop`] and [`AssociatedLayout`] for the generated
 ///   vtable when the drop function and embedded layout are enabled.
+/// - An implementation of [`AssociatedDealloc`] for the generated vtable when
+///   the `dealloc` option is enabled.
+/// - An implementation of [`AssociatedHeader`] for the generated vtable when
+///   the header is enabled.
+/// - An implementation of [`CVTable`](cheader::CVTable) for the generated vtable
+///   when the `c_header` option is enabled.
+/// - An implementation of [`ComInterface`](com::ComInterface) for the generated
+///   vtable when the `com` option is enabled. The VTable is implemented for
+///   [`ComObject<T>`](com::ComObject) instead of `T`.
+/// - An implementation of [`CppVTable`](itanium::CppVTable) for the generated
+///   vtable when the `itanium` option is enabled. The VTable is implemented for
+///   [`CppObject<T>`](itanium::CppObject) instead of `T`.
 /// - An implementation of your trait for all types implementing
 ///   [`AsDyn<dyn YourTrait>`](AsDyn) (dyntrait containers such as [`DynBox`] or [`DynRef`])
 ///   where `AsDyn::Repr: Subtable<YourTraitVTable>`
@@ -0,0 +1,223 @@
+//! Owned dyntable trait objects freed through their VTable.
+//!
+//! A [`DynOwned`] owns a value whose VTable has a deallocation function,
+//! created with the `dealloc` [macro option](crate::dyntable#macro-options).
+//! Unlike a [`DynBox`](crate::DynBox), it does not need to know the allocator
+//! of its value, as the VTable frees the value with the allocator that created
+//! it. This allows a dynptr received from foreign code, such as a plugin, to
+//! be owned without a matching deallocation function being exported.
+//!
+//! VTables of Rust types free their values with the global allocator, which
+//! [`DynOwned::new`] allocates in. A `DynOwned` has the same representation
+//! as a [`DynPtr`], and can be passed across FFI boundaries in its place.
+//!
+//! # Examples
+//! ```
+//! # use dyntable::{*, owned::DynOwned};
+//! #[dyntable(dealloc = "C")]
+//! trait Plugin {
+//!     extern "C" fn id(&self) -> u32;
+//! }
+//!
+//! struct MyPlugin;
+//!
+//! impl Plugin for MyPlugin {
+//!     extern "C" fn id(&self) -> u32 {
+//!         1
+//!     }
+//! }
+//!
+//! // usually implemented by a plugin
+//! extern "C" fn create_plugin() -> DynPtr<dyn Plugin> {
+//!     DynOwned::into_raw(DynOwned::new(MyPlugin))
+//! }
+//!
+//! let plugin = unsafe { DynOwned::from_raw(create_plugin()) };
+//! assert_eq!(plugin.id(), 1);
+//! ```
+
+use core::{ffi::c_void, marker::PhantomData, mem};
+
+use crate::{
+	AsDyn,
+	AssociatedDealloc,
+	AssociatedDrop,
+	DynPtr,
+	DynRef,
+	DynRefMut,
+	SubTable,
+	VTable,
+	VTableRepr,
+};
+#[cfg(feature = "alloc")]
+use crate::{
+	alloc::{self, GlobalAllocator, MemoryLayout},
+	DynTrait,
+};
+
+/// An owned dyntable trait object, dropped and deallocated through its
+/// VTable. See the [module docs](self).
+#[repr(transparent)]
+pub struct DynOwned<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedDealloc,
+{
+	ptr: DynPtr<V>,
+}
+
+unsafe impl<V> Send for DynOwned<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedDealloc,
+	<V::VTable as VTable>::Bounds: Send,
+{
+}
+
+unsafe impl<V> Sync for DynOwned<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedDealloc,
+	<V::VTable as VTable>::Bounds: Sync,
+{
+}
+
+unsafe impl<R, V> AsDyn<R> for DynOwned<V>
+where
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedDealloc,
+{
+	type Repr = V;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.ptr.ptr
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		self.ptr.vtable.as_ptr()
+	}
+
+	fn dyn_dealloc(self) {
+		unsafe {
+			self.ptr.vtable.as_ref().virtual_dealloc(self.ptr.ptr);
+		}
+
+		mem::forget(self);
+	}
+}
+
+impl<V> DynOwned<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedDealloc,
+{
+	/// Allocates memory using the global allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	///
+	/// # Examples
+	/// ```
+	/// # use dyntable::{*, owned::DynOwned};
+	/// #[dyntable(dealloc = "C")]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let x: DynOwned<dyn MyTrait> = DynOwned::new(0u8);
+	/// ```
+	#[cfg(feature = "alloc")]
+	#[cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+	pub fn new<'v, T>(data: T) -> Self
+	where
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let layout = MemoryLayout::new::<T>();
+
+		// Rust VTables free their values in the global allocator.
+		let ptr = match alloc::allocate(&GlobalAllocator, layout) {
+			Ok(ptr) => ptr.cast::<T>().as_ptr(),
+			Err(_) => std_alloc::alloc::handle_alloc_error(layout.into()),
+		};
+
+		unsafe {
+			ptr.write(data);
+			Self::from_raw(DynPtr::new(ptr))
+		}
+	}
+
+	/// Constructs a `DynOwned` from a raw dynptr.
+	///
+	/// After calling this function, the raw dynptr is considered to be
+	/// owned by the `DynOwned` and will be cleaned up as such.
+	///
+	/// # Safety
+	/// The pointer `ptr` must be an owned dynptr to memory that the VTable's
+	/// deallocation function can free.
+	#[inline(always)]
+	pub unsafe fn from_raw(ptr: DynPtr<V>) -> Self {
+		Self { ptr }
+	}
+
+	/// Leak a `DynOwned` into a DynPtr, which can be converted back using
+	/// [`DynOwned::from_raw`].
+	#[inline(always)]
+	pub fn into_raw(o: Self) -> DynPtr<V> {
+		let ptr = o.ptr;
+		mem::forget(o);
+		ptr
+	}
+
+	/// Upcast the `DynOwned` to a bounded dyntrait.
+	#[inline(always)]
+	pub fn upcast<U>(o: Self) -> DynOwned<U>
+	where
+		U: VTableRepr + ?Sized,
+		U::VTable: AssociatedDrop + AssociatedDealloc,
+		V::VTable: SubTable<U::VTable>,
+	{
+		unsafe { DynOwned::from_raw(DynPtr::upcast(Self::into_raw(o))) }
+	}
+
+	/// Immutably borrows the wrapped value.
+	#[inline(always)]
+	pub fn borrow(o: &Self) -> DynRef<'_, V> {
+		DynRef {
+			ptr: o.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Mutably borrows the wrapped value.
+	#[inline(always)]
+	pub fn borrow_mut(o: &mut Self) -> DynRefMut<'_, V> {
+		DynRefMut {
+			ptr: o.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Returns a reference to the VTable of the wrapped value.
+	#[inline(always)]
+	pub fn vtable(o: &Self) -> &V::VTable {
+		unsafe { o.ptr.vtable.as_ref() }
+	}
+}
+
+impl<V> Drop for DynOwned<V>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedDealloc,
+{
+	fn drop(&mut self) {
+		unsafe {
+			let vtable = self.ptr.vtable.as_ref();
+			vtable.virtual_drop(self.ptr.ptr);
+			vtable.virtual_dealloc(self.ptr.ptr);
+		}
+	}
+}
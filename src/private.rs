@@ -1,11 +1,105 @@
-use core::{ffi::c_void, marker::PhantomData, mem};
+use core::{ffi::c_void, marker::PhantomData, mem, ops::Deref};
 
-use crate::{alloc::MemoryLayout, AssociatedDrop, AssociatedLayout, DynTrait, VTable};
+use crate::{
+	alloc::MemoryLayout,
+	AsDyn,
+	AssociatedDealloc,
+	AssociatedDrop,
+	AssociatedHeader,
+	AssociatedLayout,
+	DynTrait,
+	VTable,
+	VTableHeader,
+};
+
+#[cfg(feature = "alloc")]
+pub use std_alloc::{string::String, vec::Vec};
+
+/// Deallocates a `T` allocated in the global allocator. Used as the
+/// deallocation function of VTables created by the `dealloc` macro option.
+///
+/// # Safety
+/// `ptr` must point to memory allocated for a `T` by the global allocator.
+#[cfg(feature = "alloc")]
+#[inline(always)]
+pub unsafe fn dealloc_global<T>(ptr: *mut c_void) {
+	crate::alloc::deallocate(
+		&crate::alloc::GlobalAllocator,
+		core::ptr::NonNull::new_unchecked(ptr as *mut u8),
+		MemoryLayout::new::<T>(),
+	);
+}
 
 /// Trait that implies nothing, used for `VTable::Bounds`
 /// when no bounds are required
 pub trait NoBounds {}
 
+/// Placeholder bound to the associated types of a dyntable trait
+/// when it is used as an `AsDyn` disambiguator.
+pub struct AssociatedDisambiguator;
+
+/// Returns the size of a field selected by `select`, which is never called.
+/// Used by [`entry_end!`](crate::entry_end).
+#[inline(always)]
+pub const fn field_size<V, F>(_select: fn(&V) -> &F) -> usize {
+	mem::size_of::<F>()
+}
+
+/// Used as the body of `where Self: Sized` methods and static functions in
+/// the implementation of a dyntable trait for dyn containers. Referencing
+/// [`SizedOnly::ERROR`] fails compilation once the method is instantiated,
+/// so these methods cannot be called through a container, the same as with
+/// native `dyn` traits.
+pub struct SizedOnly<T: ?Sized>(PhantomData<T>);
+
+impl<T: ?Sized> SizedOnly<T> {
+	pub const ERROR: () = panic!(
+		"static functions and methods bounded by `where Self: Sized` cannot be called through a dyntable container"
+	);
+}
+
+/// Checked by methods with `Pin` receivers in the implementation of a
+/// dyntable trait for dyn containers. Referencing [`PinnedOnly::ERROR`]
+/// fails compilation once the method is instantiated if pinning the
+/// container does not pin its target (see [`AsDyn::PINS_TARGET`]).
+pub struct PinnedOnly<T: ?Sized, R: ?Sized>(PhantomData<T>, PhantomData<R>);
+
+impl<T: AsDyn<R> + ?Sized, R: ?Sized> PinnedOnly<T, R> {
+	pub const ERROR: () = if !T::PINS_TARGET {
+		panic!(
+			"methods with `Pin` receivers cannot be called through a container that does not pin its target; use a pinned projection such as `DynBox::as_pin_mut`"
+		)
+	};
+}
+
+/// Checked by methods taking `self` by value in the implementation of a
+/// dyntable trait for dyn containers. Referencing [`OwnedOnly::ERROR`]
+/// fails compilation once the method is instantiated if the container
+/// does not uniquely own its target (see [`AsDyn::OWNS_TARGET`]).
+pub struct OwnedOnly<T: ?Sized, R: ?Sized>(PhantomData<T>, PhantomData<R>);
+
+impl<T: AsDyn<R> + ?Sized, R: ?Sized> OwnedOnly<T, R> {
+	pub const ERROR: () = if !T::OWNS_TARGET {
+		panic!(
+			"methods taking `self` by value cannot be called through a container that does not uniquely own its target"
+		)
+	};
+}
+
+/// Checked by methods taking `&mut self` or `Pin<&mut Self>` in the
+/// implementation of a dyntable trait for dyn containers. Referencing
+/// [`UniqueOnly::ERROR`] fails compilation once the method is instantiated
+/// if the container may share its target (see [`AsDyn::UNIQUE_TARGET`]).
+pub struct UniqueOnly<T: ?Sized, R: ?Sized>(PhantomData<T>, PhantomData<R>);
+
+impl<T: AsDyn<R> + ?Sized, R: ?Sized> UniqueOnly<T, R> {
+	pub const ERROR: () = if !T::UNIQUE_TARGET {
+		panic!(
+			"methods taking `&mut self` cannot be called through a container that shares its target; use a unique borrow such as `DynRc::get_mut`"
+		)
+	};
+}
+
 /// Struct used to evade the orphan rule, which prevents directly
 /// implementing DynTrait for `T: DynTrait`
 pub struct DynImplTarget<T, V: VTable>(PhantomData<(T, V)>);
@@ -60,6 +154,36 @@ pub struct SendSyncWrapper<T: ?Sized>(T);
 unsafe impl<T: ?Sized> Send for SendSyncWrapper<T> {}
 unsafe impl<T: ?Sized> Sync for SendSyncWrapper<T> {}
 
+// Deref allows reading fields of the wrapped VTable, such as
+// associated constants.
+
+impl<T: VTable> Deref for SendVTable<T> {
+	type Target = T;
+
+	#[inline(always)]
+	fn deref(&self) -> &T {
+		&self.0
+	}
+}
+
+impl<T: VTable> Deref for SyncVTable<T> {
+	type Target = T;
+
+	#[inline(always)]
+	fn deref(&self) -> &T {
+		&self.0
+	}
+}
+
+impl<T: VTable> Deref for SendSyncVTable<T> {
+	type Target = T;
+
+	#[inline(always)]
+	fn deref(&self) -> &T {
+		&self.0
+	}
+}
+
 unsafe impl<T: VTable> VTable for SendVTable<T> {
 	type Bounds = SendWrapper<T::Bounds>;
 }
@@ -93,6 +217,27 @@ unsafe impl<T: AssociatedDrop> AssociatedDrop for SendSyncVTable<T> {
 	}
 }
 
+unsafe impl<T: AssociatedDealloc> AssociatedDealloc for SendVTable<T> {
+	#[inline(always)]
+	unsafe fn virtual_dealloc(&self, instance: *mut c_void) {
+		self.0.virtual_dealloc(instance);
+	}
+}
+
+unsafe impl<T: AssociatedDealloc> AssociatedDealloc for SyncVTable<T> {
+	#[inline(always)]
+	unsafe fn virtual_dealloc(&self, instance: *mut c_void) {
+		self.0.virtual_dealloc(instance);
+	}
+}
+
+unsafe impl<T: AssociatedDealloc> AssociatedDealloc for SendSyncVTable<T> {
+	#[inline(always)]
+	unsafe fn virtual_dealloc(&self, instance: *mut c_void) {
+		self.0.virtual_dealloc(instance);
+	}
+}
+
 unsafe impl<T: AssociatedLayout> AssociatedLayout for SendVTable<T> {
 	#[inline(always)]
 	fn virtual_layout(&self) -> MemoryLayout {
@@ -114,6 +259,20 @@ unsafe impl<T: AssociatedLayout> AssociatedLayout for SendSyncVTable<T> {
 	}
 }
 
+// The wrappers are #[repr(transparent)], so the header is still the first field.
+
+unsafe impl<T: AssociatedHeader> AssociatedHeader for SendVTable<T> {
+	const HEADER: VTableHeader = T::HEADER;
+}
+
+unsafe impl<T: AssociatedHeader> AssociatedHeader for SyncVTable<T> {
+	const HEADER: VTableHeader = T::HEADER;
+}
+
+unsafe impl<T: AssociatedHeader> AssociatedHeader for SendSyncVTable<T> {
+	const HEADER: VTableHeader = T::HEADER;
+}
+
 unsafe impl<'v, T: Send, V: 'v + VTable> DynTrait<'v, SendVTable<V>> for T
 where
 	DynImplTarget<T, V>: DynTraitProxy<'v, V>,
@@ -0,0 +1,1141 @@
+//! Reference counted containers for dyntable traits.
+//!
+//! [`DynRc`] and [`DynArc`] share ownership of a value between multiple
+//! handles, dropping it once the last handle is dropped, like the standard
+//! library's `Rc` and `Arc`. `DynRc` uses non-atomic reference counts and
+//! cannot be sent between threads, while `DynArc` uses atomic reference
+//! counts. [`DynRcWeak`] and [`DynArcWeak`] are weak references, which do
+//! not keep the value alive and may be upgraded to a strong reference while
+//! the value has not been dropped.
+//!
+//! As the value is shared, only methods taking `&self` can be called
+//! through these containers. Calling a method taking `&mut self` or `self`
+//! is a compile error. A value without other references can be mutably
+//! borrowed using [`DynRc::get_mut`] or [`DynArc::get_mut`].
+//!
+//! # Examples
+//! ```
+//! # use dyntable::{*, rc::*};
+//! #[dyntable]
+//! trait Plugin: Send + Sync {
+//!     extern "C" fn id(&self) -> u32;
+//! }
+//!
+//! struct MyPlugin;
+//!
+//! impl Plugin for MyPlugin {
+//!     extern "C" fn id(&self) -> u32 {
+//!         7
+//!     }
+//! }
+//!
+//! let plugin = DynArc::<dyn Plugin>::new(MyPlugin);
+//! let shared = plugin.clone();
+//! assert_eq!(DynArc::strong_count(&plugin), 2);
+//!
+//! std::thread::spawn(move || assert_eq!(shared.id(), 7))
+//!     .join()
+//!     .unwrap();
+//!
+//! assert_eq!(DynArc::strong_count(&plugin), 1);
+//! ```
+//!
+//! # Layout
+//! The value is allocated directly after its reference counts, and the
+//! dynptr of a container points to the value. The counts are therefore
+//! stored at fixed negative offsets from the value pointer:
+//!
+//! ```c
+//! size_t *strong = (size_t *)ptr.ptr - 2;
+//! size_t *weak = (size_t *)ptr.ptr - 1;
+//! ```
+//!
+//! The strong count is the number of strong references. The weak count is
+//! the number of weak references, plus one held collectively by all strong
+//! references. The allocation starts `max(2 * sizeof(size_t), align)` bytes
+//! before the value, where `align` is the alignment in the value's layout.
+//!
+//! Foreign code may take a strong reference by incrementing the strong
+//! count, and release it by decrementing the strong count. If no strong
+//! references remain, it must then drop the value using the VTable's drop
+//! function and release the strong references' weak reference, deallocating
+//! the allocation with the container's allocator if no weak references
+//! remain. The counts of a `DynArc` must be modified atomically, for
+//! example using `__atomic_fetch_add`, with release ordering when
+//! decrementing and an acquire fence before dropping or deallocating.
+//! While checking whether a reference is unique, the weak count is briefly
+//! locked by replacing a count of one with `SIZE_MAX`.
+
+#![cfg(feature = "alloc")]
+#![cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+
+use core::{
+	alloc::Layout,
+	cell::Cell,
+	ffi::c_void,
+	hint,
+	marker::PhantomData,
+	mem::{self, MaybeUninit},
+	ptr::NonNull,
+	sync::atomic::{self, AtomicUsize, Ordering},
+};
+
+use std_alloc::alloc::handle_alloc_error;
+
+use crate::{
+	alloc::{AllocError, Allocator, Deallocator, GlobalAllocator, MemoryLayout},
+	AsDyn,
+	AssociatedDrop,
+	AssociatedLayout,
+	DynPtr,
+	DynRef,
+	DynRefMut,
+	DynTrait,
+	SubTable,
+	VTable,
+	VTableRepr,
+};
+
+/// A reference count, either non-atomic or atomic.
+trait Count {
+	fn new(count: usize) -> Self;
+	fn get(&self) -> usize;
+	fn increment(&self);
+	/// Decrements the count, returning `true` if it reached zero.
+	fn decrement(&self) -> bool;
+	/// Increments the count unless it is zero, returning `true` if it
+	/// was incremented.
+	fn increment_nonzero(&self) -> bool;
+	/// Increments the count, waiting for it to be unlocked if it is locked.
+	fn increment_unlocked(&self);
+	/// Locks a count of one by replacing it with `usize::MAX`, returning
+	/// `false` if the count is not one.
+	fn lock(&self) -> bool;
+	/// Unlocks a count locked by [`Count::lock`], restoring it to one.
+	fn unlock(&self);
+}
+
+impl Count for Cell<usize> {
+	#[inline(always)]
+	fn new(count: usize) -> Self {
+		Cell::new(count)
+	}
+
+	#[inline(always)]
+	fn get(&self) -> usize {
+		Cell::get(self)
+	}
+
+	#[inline(always)]
+	fn increment(&self) {
+		self.set(self.get() + 1);
+	}
+
+	#[inline(always)]
+	fn decrement(&self) -> bool {
+		self.set(self.get() - 1);
+		self.get() == 0
+	}
+
+	#[inline(always)]
+	fn increment_nonzero(&self) -> bool {
+		match self.get() {
+			0 => false,
+			count => {
+				self.set(count + 1);
+				true
+			},
+		}
+	}
+
+	#[inline(always)]
+	fn increment_unlocked(&self) {
+		// a lock is never held across calls on a single thread
+		self.increment();
+	}
+
+	#[inline(always)]
+	fn lock(&self) -> bool {
+		match self.get() {
+			1 => {
+				self.set(usize::MAX);
+				true
+			},
+			_ => false,
+		}
+	}
+
+	#[inline(always)]
+	fn unlock(&self) {
+		self.set(1);
+	}
+}
+
+impl Count for AtomicUsize {
+	#[inline(always)]
+	fn new(count: usize) -> Self {
+		AtomicUsize::new(count)
+	}
+
+	#[inline(always)]
+	fn get(&self) -> usize {
+		self.load(Ordering::Acquire)
+	}
+
+	#[inline(always)]
+	fn increment(&self) {
+		// new references can only be made from existing ones, which
+		// already synchronize access to the value
+		self.fetch_add(1, Ordering::Relaxed);
+	}
+
+	#[inline(always)]
+	fn decrement(&self) -> bool {
+		if self.fetch_sub(1, Ordering::Release) != 1 {
+			return false;
+		}
+
+		// synchronize with the release of every other reference before
+		// dropping or deallocating
+		atomic::fence(Ordering::Acquire);
+		true
+	}
+
+	#[inline(always)]
+	fn increment_nonzero(&self) -> bool {
+		self.fetch_update(Ordering::Acquire, Ordering::Relaxed, |count| {
+			(count != 0).then_some(count + 1)
+		})
+		.is_ok()
+	}
+
+	#[inline]
+	fn increment_unlocked(&self) {
+		let mut count = self.load(Ordering::Relaxed);
+
+		loop {
+			if count == usize::MAX {
+				hint::spin_loop();
+				count = self.load(Ordering::Relaxed);
+				continue;
+			}
+
+			// acquire to synchronize with the release of the lock
+			match self.compare_exchange_weak(count, count + 1, Ordering::Acquire, Ordering::Relaxed) {
+				Ok(_) => return,
+				Err(current) => count = current,
+			}
+		}
+	}
+
+	#[inline(always)]
+	fn lock(&self) -> bool {
+		self.compare_exchange(1, usize::MAX, Ordering::Acquire, Ordering::Relaxed)
+			.is_ok()
+	}
+
+	#[inline(always)]
+	fn unlock(&self) {
+		self.store(1, Ordering::Release);
+	}
+}
+
+/// The reference counts stored in front of a shared value.
+#[repr(C)]
+struct Counts<C> {
+	strong: C,
+	weak: C,
+}
+
+/// Returns the layout of an allocation holding the reference counts
+/// followed by a value with the given layout, and the offset of the value.
+fn counts_layout<C>(value: MemoryLayout) -> (MemoryLayout, usize) {
+	value.with_header(MemoryLayout::new::<Counts<C>>())
+}
+
+/// Returns the reference counts of the shared value pointed to by `ptr`.
+#[inline(always)]
+unsafe fn counts<'a, C, V>(ptr: DynPtr<V>) -> &'a Counts<C>
+where
+	V: VTableRepr + ?Sized,
+{
+	&*(ptr.ptr as *const Counts<C>).sub(1)
+}
+
+fn try_new_in<'v, C, T, V, A>(data: T, alloc: &A) -> Result<DynPtr<V>, AllocError>
+where
+	C: Count,
+	T: DynTrait<'v, V::VTable>,
+	V: VTableRepr + ?Sized,
+	V::VTable: 'v,
+	A: Allocator,
+{
+	let (layout, offset) = counts_layout::<C>(MemoryLayout::new::<T>());
+
+	unsafe {
+		let ptr = alloc.allocate(layout)?.cast::<u8>().as_ptr().add(offset);
+		(ptr as *mut Counts<C>).sub(1).write(Counts {
+			strong: C::new(1),
+			weak: C::new(1),
+		});
+		(ptr as *mut T).write(data);
+
+		Ok(DynPtr::new(ptr as *mut T))
+	}
+}
+
+fn alloc_error<C, T>() -> ! {
+	let layout = counts_layout::<C>(Layout::new::<MaybeUninit<T>>().into()).0;
+	handle_alloc_error(layout.into())
+}
+
+/// Releases a strong reference, dropping the value if it was the last one.
+unsafe fn release_strong<C, V, A>(ptr: DynPtr<V>, alloc: &A)
+where
+	C: Count,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	A: Deallocator,
+{
+	if counts::<C, V>(ptr).strong.decrement() {
+		ptr.vtable.as_ref().virtual_drop(ptr.ptr);
+		release_weak::<C, V, A>(ptr, alloc);
+	}
+}
+
+/// Releases a weak reference, deallocating the value if it was the last one.
+unsafe fn release_weak<C, V, A>(ptr: DynPtr<V>, alloc: &A)
+where
+	C: Count,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	A: Deallocator,
+{
+	if counts::<C, V>(ptr).weak.decrement() {
+		let (layout, offset) = counts_layout::<C>(ptr.vtable.as_ref().virtual_layout());
+		alloc.deallocate(NonNull::new_unchecked((ptr.ptr as *mut u8).sub(offset)), layout);
+	}
+}
+
+/// Returns `true` if `ptr` is the only reference to its value.
+unsafe fn is_unique<C, V>(ptr: DynPtr<V>) -> bool
+where
+	C: Count,
+	V: VTableRepr + ?Sized,
+{
+	let counts = counts::<C, V>(ptr);
+
+	// Lock the weak count while reading the strong count, as otherwise a weak
+	// reference could be upgraded and then dropped between reading both
+	// counts. While locked no weak references exist, and none can be created,
+	// so the strong count cannot be increased by an upgrade either.
+	if !counts.weak.lock() {
+		return false;
+	}
+
+	let unique = counts.strong.get() == 1;
+	counts.weak.unlock();
+	unique
+}
+
+/// Returns the number of weak references to the value of `ptr`, not
+/// including the one held by strong references.
+unsafe fn weak_count<C, V>(ptr: DynPtr<V>) -> usize
+where
+	C: Count,
+	V: VTableRepr + ?Sized,
+{
+	let counts = counts::<C, V>(ptr);
+
+	match (counts.strong.get(), counts.weak.get()) {
+		(0, _) => 0,
+		// locked by `is_unique`, which requires that no weak references exist
+		(_, usize::MAX) => 0,
+		(_, weak) => weak - 1,
+	}
+}
+
+/// A single-threaded reference counted container for dyntable traits,
+/// equivalent to `Rc<dyn Trait>`. See the [module docs](self).
+#[repr(C)]
+pub struct DynRc<V, A = GlobalAllocator>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	alloc: A,
+	ptr: DynPtr<V>,
+}
+
+unsafe impl<R, V, A> AsDyn<R> for DynRc<V, A>
+where
+	A: Deallocator,
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	type Repr = V;
+
+	const OWNS_TARGET: bool = false;
+	const UNIQUE_TARGET: bool = false;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.ptr.ptr
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		self.ptr.vtable.as_ptr()
+	}
+
+	fn dyn_dealloc(self) {
+		unreachable!("shared values cannot be taken by value");
+	}
+}
+
+impl<V> DynRc<V, GlobalAllocator>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Allocates memory using the global allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, rc::*};
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let x: DynRc<dyn MyTrait> = DynRc::new(0u8);
+	/// ```
+	#[inline]
+	pub fn new<'v, T>(data: T) -> Self
+	where
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		DynRc::new_in(data, GlobalAllocator)
+	}
+
+	/// Constructs a `DynRc` from a raw dynptr in the global allocator,
+	/// taking ownership of one strong reference.
+	///
+	/// # Safety
+	/// The pointer `ptr` must point to the value of a `DynRc` allocated by
+	/// the rust global allocator, such as one returned by [`DynRc::into_raw`],
+	/// and the caller must own one of its strong references.
+	#[inline(always)]
+	pub unsafe fn from_raw(ptr: DynPtr<V>) -> Self {
+		Self::from_raw_in(ptr, GlobalAllocator)
+	}
+}
+
+impl<V, A> DynRc<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Allocates memory using the given allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	#[inline]
+	pub fn new_in<'v, T>(data: T, alloc: A) -> Self
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		match Self::try_new_in(data, alloc) {
+			Ok(rc) => rc,
+			Err(_) => alloc_error::<Cell<usize>, T>(),
+		}
+	}
+
+	/// Allocates memory using the given allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V`, and returning an error
+	/// if the allocation fails.
+	#[inline]
+	pub fn try_new_in<'v, T>(data: T, alloc: A) -> Result<Self, AllocError>
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let ptr = try_new_in::<Cell<usize>, T, V, A>(data, &alloc)?;
+		Ok(Self { alloc, ptr })
+	}
+
+	/// Constructs a `DynRc` from a raw dynptr in the given allocator,
+	/// taking ownership of one strong reference.
+	///
+	/// # Safety
+	/// The pointer `ptr` must point to the value of a `DynRc` allocated by
+	/// the allocator `alloc`, such as one returned by
+	/// [`DynRc::into_raw_with_allocator`], and the caller must own one of
+	/// its strong references.
+	#[inline(always)]
+	pub unsafe fn from_raw_in(ptr: DynPtr<V>, alloc: A) -> Self {
+		Self { alloc, ptr }
+	}
+
+	/// Leak a strong reference, returning its DynPtr and Allocator.
+	#[inline(always)]
+	pub fn into_raw_with_allocator(rc: Self) -> (DynPtr<V>, A) {
+		// SAFETY: the original value is forgotten
+		let alloc = unsafe { (&rc.alloc as *const A).read() };
+		let ptr = rc.ptr;
+		mem::forget(rc);
+
+		(ptr, alloc)
+	}
+
+	/// Leak a strong reference into a DynPtr.
+	///
+	/// # Examples
+	/// Recreate a `DynRc` which was previously converted to a raw pointer.
+	///
+	/// ```
+	/// # use dyntable::{*, rc::*};
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let x: DynRc<dyn MyTrait> = DynRc::new(0u8);
+	/// let ptr = DynRc::into_raw(x);
+	/// let x: DynRc<dyn MyTrait> = unsafe { DynRc::from_raw(ptr) };
+	/// ```
+	#[inline(always)]
+	pub fn into_raw(rc: Self) -> DynPtr<V> {
+		Self::into_raw_with_allocator(rc).0
+	}
+
+	/// Returns the dynptr of the shared value, without giving up the
+	/// strong reference.
+	#[inline(always)]
+	pub fn as_raw(rc: &Self) -> DynPtr<V> {
+		rc.ptr
+	}
+
+	/// Immutably borrows the shared value.
+	#[inline(always)]
+	pub fn borrow(rc: &Self) -> DynRef<'_, V> {
+		DynRef {
+			ptr: rc.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Mutably borrows the shared value if there are no other strong or
+	/// weak references to it.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, rc::*};
+	/// #[dyntable]
+	/// trait Counter {
+	///     extern "C" fn increment(&mut self);
+	/// }
+	///
+	/// impl Counter for u32 {
+	///     extern "C" fn increment(&mut self) {
+	///         *self += 1;
+	///     }
+	/// }
+	///
+	/// let mut counter: DynRc<dyn Counter> = DynRc::new(0u32);
+	/// DynRc::get_mut(&mut counter).unwrap().increment();
+	///
+	/// let shared = counter.clone();
+	/// assert!(DynRc::get_mut(&mut counter).is_none());
+	/// ```
+	#[inline]
+	pub fn get_mut(rc: &mut Self) -> Option<DynRefMut<'_, V>> {
+		match unsafe { is_unique::<Cell<usize>, V>(rc.ptr) } {
+			true => Some(DynRefMut {
+				ptr: rc.ptr,
+				_lt: PhantomData,
+			}),
+			false => None,
+		}
+	}
+
+	/// Returns a reference to the VTable of the shared value.
+	///
+	/// This can be used to read VTable data fields, such as
+	/// [associated constants](crate::dyntable#associated-constants).
+	#[inline(always)]
+	pub fn vtable(rc: &Self) -> &V::VTable {
+		unsafe { rc.ptr.vtable.as_ref() }
+	}
+
+	/// Returns the number of strong references to the shared value.
+	#[inline(always)]
+	pub fn strong_count(rc: &Self) -> usize {
+		unsafe { counts::<Cell<usize>, V>(rc.ptr) }.strong.get()
+	}
+
+	/// Returns the number of weak references to the shared value.
+	#[inline(always)]
+	pub fn weak_count(rc: &Self) -> usize {
+		unsafe { weak_count::<Cell<usize>, V>(rc.ptr) }
+	}
+
+	/// Returns `true` if both references point to the same value.
+	#[inline(always)]
+	pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+		a.ptr.ptr == b.ptr.ptr
+	}
+
+	/// Creates a weak reference to the shared value.
+	#[inline]
+	pub fn downgrade(rc: &Self) -> DynRcWeak<V, A>
+	where
+		A: Clone,
+	{
+		unsafe { counts::<Cell<usize>, V>(rc.ptr) }.weak.increment_unlocked();
+
+		DynRcWeak {
+			alloc: rc.alloc.clone(),
+			ptr: rc.ptr,
+		}
+	}
+
+	/// Upcast the reference to a bounded dyntrait reference.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, rc::*};
+	/// #[dyntable]
+	/// trait Animal {}
+	///
+	/// #[dyntable]
+	/// trait Feline: Animal
+	/// where
+	///     dyn Animal:,
+	/// {}
+	///
+	/// struct Cat;
+	///
+	/// impl Feline for Cat {}
+	/// impl Animal for Cat {}
+	///
+	/// let feline: DynRc<dyn Feline> = DynRc::new(Cat);
+	/// let animal: DynRc<dyn Animal> = DynRc::upcast(feline);
+	/// ```
+	#[inline(always)]
+	pub fn upcast<U>(rc: Self) -> DynRc<U, A>
+	where
+		U: VTableRepr + ?Sized,
+		U::VTable: AssociatedDrop + AssociatedLayout,
+		V::VTable: SubTable<U::VTable>,
+	{
+		let (ptr, alloc) = Self::into_raw_with_allocator(rc);
+		unsafe { DynRc::from_raw_in(DynPtr::upcast(ptr), alloc) }
+	}
+}
+
+impl<V, A> Clone for DynRc<V, A>
+where
+	A: Deallocator + Clone,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Creates another strong reference to the shared value.
+	#[inline]
+	fn clone(&self) -> Self {
+		unsafe { counts::<Cell<usize>, V>(self.ptr) }.strong.increment();
+
+		Self {
+			alloc: self.alloc.clone(),
+			ptr: self.ptr,
+		}
+	}
+}
+
+impl<V, A> Drop for DynRc<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	fn drop(&mut self) {
+		unsafe { release_strong::<Cell<usize>, V, A>(self.ptr, &self.alloc) }
+	}
+}
+
+/// A weak reference to the value of a [`DynRc`], which does not keep the
+/// value alive.
+#[repr(C)]
+pub struct DynRcWeak<V, A = GlobalAllocator>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	alloc: A,
+	ptr: DynPtr<V>,
+}
+
+impl<V, A> DynRcWeak<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Creates a strong reference to the value if it has not been dropped.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, rc::*};
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let x: DynRc<dyn MyTrait> = DynRc::new(0u8);
+	/// let weak = DynRc::downgrade(&x);
+	/// assert!(weak.upgrade().is_some());
+	///
+	/// drop(x);
+	/// assert!(weak.upgrade().is_none());
+	/// ```
+	#[inline]
+	pub fn upgrade(&self) -> Option<DynRc<V, A>>
+	where
+		A: Clone,
+	{
+		match unsafe { counts::<Cell<usize>, V>(self.ptr) }.strong.increment_nonzero() {
+			true => Some(DynRc {
+				alloc: self.alloc.clone(),
+				ptr: self.ptr,
+			}),
+			false => None,
+		}
+	}
+
+	/// Returns the number of strong references to the value.
+	#[inline(always)]
+	pub fn strong_count(&self) -> usize {
+		unsafe { counts::<Cell<usize>, V>(self.ptr) }.strong.get()
+	}
+
+	/// Returns the number of weak references to the value, or `0` if
+	/// the value has been dropped.
+	#[inline(always)]
+	pub fn weak_count(&self) -> usize {
+		unsafe { weak_count::<Cell<usize>, V>(self.ptr) }
+	}
+}
+
+impl<V, A> Clone for DynRcWeak<V, A>
+where
+	A: Deallocator + Clone,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Creates another weak reference to the value.
+	#[inline]
+	fn clone(&self) -> Self {
+		unsafe { counts::<Cell<usize>, V>(self.ptr) }.weak.increment();
+
+		Self {
+			alloc: self.alloc.clone(),
+			ptr: self.ptr,
+		}
+	}
+}
+
+impl<V, A> Drop for DynRcWeak<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	fn drop(&mut self) {
+		unsafe { release_weak::<Cell<usize>, V, A>(self.ptr, &self.alloc) }
+	}
+}
+
+/// A thread-safe reference counted container for dyntable traits,
+/// equivalent to `Arc<dyn Trait>`. See the [module docs](self).
+///
+/// `DynArc` is `Send` and `Sync` if the trait is bounded by both
+/// `Send` and `Sync`.
+#[repr(C)]
+pub struct DynArc<V, A = GlobalAllocator>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	alloc: A,
+	ptr: DynPtr<V>,
+}
+
+unsafe impl<V, A> Send for DynArc<V, A>
+where
+	A: Deallocator + Send,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	<V::VTable as VTable>::Bounds: Send + Sync,
+{
+}
+
+unsafe impl<V, A> Sync for DynArc<V, A>
+where
+	A: Deallocator + Sync,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	<V::VTable as VTable>::Bounds: Send + Sync,
+{
+}
+
+unsafe impl<R, V, A> AsDyn<R> for DynArc<V, A>
+where
+	A: Deallocator,
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	type Repr = V;
+
+	const OWNS_TARGET: bool = false;
+	const UNIQUE_TARGET: bool = false;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.ptr.ptr
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		self.ptr.vtable.as_ptr()
+	}
+
+	fn dyn_dealloc(self) {
+		unreachable!("shared values cannot be taken by value");
+	}
+}
+
+impl<V> DynArc<V, GlobalAllocator>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Allocates memory using the global allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	#[inline]
+	pub fn new<'v, T>(data: T) -> Self
+	where
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		DynArc::new_in(data, GlobalAllocator)
+	}
+
+	/// Constructs a `DynArc` from a raw dynptr in the global allocator,
+	/// taking ownership of one strong reference.
+	///
+	/// # Safety
+	/// The pointer `ptr` must point to the value of a `DynArc` allocated by
+	/// the rust global allocator, such as one returned by
+	/// [`DynArc::into_raw`], and the caller must own one of its strong
+	/// references.
+	#[inline(always)]
+	pub unsafe fn from_raw(ptr: DynPtr<V>) -> Self {
+		Self::from_raw_in(ptr, GlobalAllocator)
+	}
+}
+
+impl<V, A> DynArc<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Allocates memory using the given allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	#[inline]
+	pub fn new_in<'v, T>(data: T, alloc: A) -> Self
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		match Self::try_new_in(data, alloc) {
+			Ok(arc) => arc,
+			Err(_) => alloc_error::<AtomicUsize, T>(),
+		}
+	}
+
+	/// Allocates memory using the given allocator and moves `data` into
+	/// the allocated memory, upcasting it to `V`, and returning an error
+	/// if the allocation fails.
+	#[inline]
+	pub fn try_new_in<'v, T>(data: T, alloc: A) -> Result<Self, AllocError>
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let ptr = try_new_in::<AtomicUsize, T, V, A>(data, &alloc)?;
+		Ok(Self { alloc, ptr })
+	}
+
+	/// Constructs a `DynArc` from a raw dynptr in the given allocator,
+	/// taking ownership of one strong reference.
+	///
+	/// # Safety
+	/// The pointer `ptr` must point to the value of a `DynArc` allocated by
+	/// the allocator `alloc`, such as one returned by
+	/// [`DynArc::into_raw_with_allocator`], and the caller must own one of
+	/// its strong references.
+	#[inline(always)]
+	pub unsafe fn from_raw_in(ptr: DynPtr<V>, alloc: A) -> Self {
+		Self { alloc, ptr }
+	}
+
+	/// Leak a strong reference, returning its DynPtr and Allocator.
+	#[inline(always)]
+	pub fn into_raw_with_allocator(arc: Self) -> (DynPtr<V>, A) {
+		// SAFETY: the original value is forgotten
+		let alloc = unsafe { (&arc.alloc as *const A).read() };
+		let ptr = arc.ptr;
+		mem::forget(arc);
+
+		(ptr, alloc)
+	}
+
+	/// Leak a strong reference into a DynPtr.
+	#[inline(always)]
+	pub fn into_raw(arc: Self) -> DynPtr<V> {
+		Self::into_raw_with_allocator(arc).0
+	}
+
+	/// Returns the dynptr of the shared value, without giving up the
+	/// strong reference.
+	#[inline(always)]
+	pub fn as_raw(arc: &Self) -> DynPtr<V> {
+		arc.ptr
+	}
+
+	/// Immutably borrows the shared value.
+	#[inline(always)]
+	pub fn borrow(arc: &Self) -> DynRef<'_, V> {
+		DynRef {
+			ptr: arc.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Mutably borrows the shared value if there are no other strong or
+	/// weak references to it.
+	#[inline]
+	pub fn get_mut(arc: &mut Self) -> Option<DynRefMut<'_, V>> {
+		match unsafe { is_unique::<AtomicUsize, V>(arc.ptr) } {
+			true => Some(DynRefMut {
+				ptr: arc.ptr,
+				_lt: PhantomData,
+			}),
+			false => None,
+		}
+	}
+
+	/// Returns a reference to the VTable of the shared value.
+	///
+	/// This can be used to read VTable data fields, such as
+	/// [associated constants](crate::dyntable#associated-constants).
+	#[inline(always)]
+	pub fn vtable(arc: &Self) -> &V::VTable {
+		unsafe { arc.ptr.vtable.as_ref() }
+	}
+
+	/// Returns the number of strong references to the shared value.
+	#[inline(always)]
+	pub fn strong_count(arc: &Self) -> usize {
+		unsafe { counts::<AtomicUsize, V>(arc.ptr) }.strong.get()
+	}
+
+	/// Returns the number of weak references to the shared value.
+	#[inline(always)]
+	pub fn weak_count(arc: &Self) -> usize {
+		unsafe { weak_count::<AtomicUsize, V>(arc.ptr) }
+	}
+
+	/// Returns `true` if both references point to the same value.
+	#[inline(always)]
+	pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+		a.ptr.ptr == b.ptr.ptr
+	}
+
+	/// Creates a weak reference to the shared value.
+	#[inline]
+	pub fn downgrade(arc: &Self) -> DynArcWeak<V, A>
+	where
+		A: Clone,
+	{
+		// the weak count may be locked by `get_mut` on another reference
+		unsafe { counts::<AtomicUsize, V>(arc.ptr) }.weak.increment_unlocked();
+
+		DynArcWeak {
+			alloc: arc.alloc.clone(),
+			ptr: arc.ptr,
+		}
+	}
+
+	/// Upcast the reference to a bounded dyntrait reference.
+	#[inline(always)]
+	pub fn upcast<U>(arc: Self) -> DynArc<U, A>
+	where
+		U: VTableRepr + ?Sized,
+		U::VTable: AssociatedDrop + AssociatedLayout,
+		V::VTable: SubTable<U::VTable>,
+	{
+		let (ptr, alloc) = Self::into_raw_with_allocator(arc);
+		unsafe { DynArc::from_raw_in(DynPtr::upcast(ptr), alloc) }
+	}
+}
+
+impl<V, A> Clone for DynArc<V, A>
+where
+	A: Deallocator + Clone,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Creates another strong reference to the shared value.
+	#[inline]
+	fn clone(&self) -> Self {
+		unsafe { counts::<AtomicUsize, V>(self.ptr) }.strong.increment();
+
+		Self {
+			alloc: self.alloc.clone(),
+			ptr: self.ptr,
+		}
+	}
+}
+
+impl<V, A> Drop for DynArc<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	fn drop(&mut self) {
+		unsafe { release_strong::<AtomicUsize, V, A>(self.ptr, &self.alloc) }
+	}
+}
+
+/// A weak reference to the value of a [`DynArc`], which does not keep the
+/// value alive.
+#[repr(C)]
+pub struct DynArcWeak<V, A = GlobalAllocator>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	alloc: A,
+	ptr: DynPtr<V>,
+}
+
+unsafe impl<V, A> Send for DynArcWeak<V, A>
+where
+	A: Deallocator + Send,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	<V::VTable as VTable>::Bounds: Send + Sync,
+{
+}
+
+unsafe impl<V, A> Sync for DynArcWeak<V, A>
+where
+	A: Deallocator + Sync,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	<V::VTable as VTable>::Bounds: Send + Sync,
+{
+}
+
+impl<V, A> DynArcWeak<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Creates a strong reference to the value if it has not been dropped.
+	#[inline]
+	pub fn upgrade(&self) -> Option<DynArc<V, A>>
+	where
+		A: Clone,
+	{
+		match unsafe { counts::<AtomicUsize, V>(self.ptr) }.strong.increment_nonzero() {
+			true => Some(DynArc {
+				alloc: self.alloc.clone(),
+				ptr: self.ptr,
+			}),
+			false => None,
+		}
+	}
+
+	/// Returns the number of strong references to the value.
+	#[inline(always)]
+	pub fn strong_count(&self) -> usize {
+		unsafe { counts::<AtomicUsize, V>(self.ptr) }.strong.get()
+	}
+
+	/// Returns the number of weak references to the value, or `0` if
+	/// the value has been dropped.
+	#[inline(always)]
+	pub fn weak_count(&self) -> usize {
+		unsafe { weak_count::<AtomicUsize, V>(self.ptr) }
+	}
+}
+
+impl<V, A> Clone for DynArcWeak<V, A>
+where
+	A: Deallocator + Clone,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Creates another weak reference to the value.
+	#[inline]
+	fn clone(&self) -> Self {
+		// the weak count cannot be locked while a weak reference exists
+		unsafe { counts::<AtomicUsize, V>(self.ptr) }.weak.increment();
+
+		Self {
+			alloc: self.alloc.clone(),
+			ptr: self.ptr,
+		}
+	}
+}
+
+impl<V, A> Drop for DynArcWeak<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	fn drop(&mut self) {
+		unsafe { release_weak::<AtomicUsize, V, A>(self.ptr, &self.alloc) }
+	}
+}
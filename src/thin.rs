@@ -0,0 +1,798 @@
+//! Thin pointers to dyntable traits.
+//!
+//! A [`DynPtr`] is two pointers wide, holding a pointer to the value and a
+//! pointer to its VTable. Thin pointers instead store the VTable pointer as a
+//! header directly in front of the value, making them a single pointer wide.
+//! This allows them to be passed through C APIs that only accept a `void*`,
+//! or stored in atomics.
+//!
+//! A thin pointer points to the value, not to the header, so the value can
+//! still be accessed directly through the pointer.
+//!
+//! ```text
+//!               ThinPtr
+//!                  |
+//!                  v
+//! +----------------+---------+
+//! | *const VTable  |  value  |
+//! +----------------+---------+
+//! ```
+//!
+//! As the header is part of the allocation, thin references can only be
+//! obtained from a [`ThinBox`], or from memory laid out as above. A
+//! [`DynRef`] cannot be converted to a thin reference.
+//!
+//! # Examples
+//! ```
+//! # use dyntable::{*, thin::*};
+//! #[dyntable]
+//! trait Counter {
+//!     extern "C" fn get(&self) -> u32;
+//!     extern "C" fn increment(&mut self);
+//! }
+//!
+//! impl Counter for u32 {
+//!     extern "C" fn get(&self) -> u32 {
+//!         *self
+//!     }
+//!
+//!     extern "C" fn increment(&mut self) {
+//!         *self += 1;
+//!     }
+//! }
+//!
+//! let mut counter = ThinBox::<dyn Counter>::new(0u32);
+//! counter.increment();
+//!
+//! // the raw pointer may be passed through C as a `void*`
+//! let ptr: ThinPtr<dyn Counter> = ThinBox::into_raw(counter);
+//! let counter = unsafe { ThinRef::from_raw(ptr) };
+//! assert_eq!(counter.get(), 1);
+//!
+//! let _ = unsafe { ThinBox::from_raw(ptr) };
+//! ```
+
+#![cfg(feature = "alloc")]
+#![cfg_attr(nightlydoc, doc(cfg(feature = "alloc")))]
+
+use core::{
+	ffi::c_void,
+	marker::{PhantomData, PhantomPinned},
+	mem,
+	ops::{Deref, DerefMut},
+	ptr::{self, NonNull},
+};
+
+use std_alloc::alloc::handle_alloc_error;
+
+use crate::{
+	alloc::{allocate, AllocError, Allocator, Deallocator, GlobalAllocator, MemoryLayout},
+	boxed::DynBox,
+	AsDyn,
+	AssociatedDrop,
+	AssociatedLayout,
+	DynPtr,
+	DynRef,
+	DynRefMut,
+	DynTrait,
+	SubTable,
+	VTable,
+	VTableRepr,
+};
+
+/// Returns the layout of an allocation holding a VTable header followed by
+/// a value with the given layout, and the offset of the value.
+fn header_layout(value: MemoryLayout) -> (MemoryLayout, usize) {
+	value.with_header(MemoryLayout::new::<*const c_void>())
+}
+
+/// An FFI safe thin pointer to a dyntable trait, storing the VTable pointer
+/// in front of the pointed to value. See the [module docs](self).
+///
+/// A `ThinPtr` has the same layout as a `void*`.
+#[repr(transparent)]
+pub struct ThinPtr<V: VTableRepr + ?Sized> {
+	pub ptr: *mut c_void,
+	_marker: PhantomData<*const V::VTable>,
+}
+
+impl<V: VTableRepr + ?Sized> Copy for ThinPtr<V> {}
+impl<V: VTableRepr + ?Sized> Clone for ThinPtr<V> {
+	#[inline(always)]
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<V: VTableRepr + ?Sized> ThinPtr<V> {
+	/// Creates a `ThinPtr` from a pointer to a value preceded by its VTable
+	/// pointer.
+	///
+	/// While creating a `ThinPtr` is always safe, using it is only safe
+	/// if `ptr` is laid out as described in the [module docs](self).
+	#[inline(always)]
+	pub const fn from_raw(ptr: *mut c_void) -> Self {
+		Self {
+			ptr,
+			_marker: PhantomData,
+		}
+	}
+
+	/// Reads the VTable pointer stored in front of the value.
+	///
+	/// # Safety
+	/// The pointer must point to a value preceded by its VTable pointer.
+	#[inline(always)]
+	pub unsafe fn vtable(ptr: Self) -> *const V::VTable {
+		*(ptr.ptr as *const *const V::VTable).sub(1)
+	}
+
+	/// Converts the thin pointer into a [`DynPtr`] to the same value.
+	///
+	/// # Safety
+	/// The pointer must point to a value preceded by its VTable pointer.
+	#[inline(always)]
+	pub unsafe fn to_dyn(ptr: Self) -> DynPtr<V> {
+		DynPtr {
+			ptr: ptr.ptr,
+			vtable: NonNull::new_unchecked(Self::vtable(ptr) as *mut V::VTable),
+		}
+	}
+}
+
+/// Thin reference to a dyntable Trait, equivalent to `&dyn Trait`.
+///
+/// A `ThinRef` has the same layout as a `void*`.
+#[repr(transparent)]
+pub struct ThinRef<'a, V: VTableRepr + ?Sized> {
+	ptr: ThinPtr<V>,
+	_lt: PhantomData<&'a ()>,
+}
+
+impl<V: VTableRepr + ?Sized> Copy for ThinRef<'_, V> {}
+impl<V: VTableRepr + ?Sized> Clone for ThinRef<'_, V> {
+	#[inline(always)]
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+unsafe impl<V: VTableRepr + ?Sized> Send for ThinRef<'_, V> where <V::VTable as VTable>::Bounds: Sync {}
+
+impl<'a, V: VTableRepr + ?Sized> ThinRef<'a, V> {
+	/// Casts a [`ThinPtr`] to a [`ThinRef`].
+	///
+	/// # Safety
+	/// The pointer `ptr` must point to a value preceded by its VTable
+	/// pointer, with both the value's and the VTable's lifetime matching
+	/// or outliving `'a`.
+	#[inline(always)]
+	pub unsafe fn from_raw(ptr: ThinPtr<V>) -> Self {
+		Self {
+			ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	#[inline(always)]
+	pub fn borrow(r: Self) -> Self {
+		r
+	}
+
+	/// Returns the thin pointer held by the reference.
+	#[inline(always)]
+	pub fn as_raw(r: Self) -> ThinPtr<V> {
+		r.ptr
+	}
+
+	/// Returns a reference to the VTable of the referenced value.
+	#[inline(always)]
+	pub fn vtable(r: &Self) -> &'a V::VTable {
+		// SAFETY: the vtable pointer is valid for `'a` (see `from_raw`)
+		unsafe { &*ThinPtr::vtable(r.ptr) }
+	}
+
+	/// Converts the thin reference into a [`DynRef`] to the same value.
+	#[inline(always)]
+	pub fn to_dyn(r: Self) -> DynRef<'a, V> {
+		// SAFETY: the header is valid for `'a` (see `from_raw`)
+		unsafe { DynRef::from_raw(ThinPtr::to_dyn(r.ptr)) }
+	}
+
+	/// Upcast the given thin reference to a bounded dyntrait ref.
+	///
+	/// The header of the referenced value cannot be changed through a shared
+	/// reference, so the upcast reference is a [`DynRef`].
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, thin::*};
+	/// #[dyntable]
+	/// trait Animal {}
+	///
+	/// #[dyntable]
+	/// trait Feline: Animal
+	/// where
+	///     dyn Animal:,
+	/// {}
+	///
+	/// struct Cat;
+	///
+	/// impl Feline for Cat {}
+	/// impl Animal for Cat {}
+	///
+	/// let feline: ThinBox<dyn Feline> = ThinBox::new(Cat);
+	/// let feline_ref: ThinRef<dyn Feline> = ThinBox::borrow(&feline);
+	/// let animal_ref: DynRef<dyn Animal> = ThinRef::upcast(feline_ref);
+	/// ```
+	#[inline(always)]
+	pub fn upcast<U>(r: Self) -> DynRef<'a, U>
+	where
+		U: VTableRepr + ?Sized,
+		V::VTable: SubTable<U::VTable>,
+	{
+		DynRef::upcast(Self::to_dyn(r))
+	}
+}
+
+impl<'a, V: VTableRepr + ?Sized> From<ThinRef<'a, V>> for DynRef<'a, V> {
+	#[inline(always)]
+	fn from(value: ThinRef<'a, V>) -> Self {
+		ThinRef::to_dyn(value)
+	}
+}
+
+impl<'a, V: VTableRepr + ?Sized> Deref for ThinRef<'a, V> {
+	type Target = ThinRefCallProxy<'a, V>;
+
+	#[inline(always)]
+	fn deref(&self) -> &Self::Target {
+		ThinRefCallProxy::from_raw(&self.ptr)
+	}
+}
+
+/// Thin reference to a dyntable Trait, equivalent to `&mut dyn Trait`.
+///
+/// A `ThinRefMut` has the same layout as a `void*`.
+#[repr(transparent)]
+pub struct ThinRefMut<'a, V: VTableRepr + ?Sized> {
+	ptr: ThinPtr<V>,
+	_lt: PhantomData<&'a mut ()>,
+}
+
+unsafe impl<V: VTableRepr + ?Sized> Send for ThinRefMut<'_, V> where
+	<V::VTable as VTable>::Bounds: Sync
+{
+}
+
+impl<'a, V: VTableRepr + ?Sized> ThinRefMut<'a, V> {
+	/// Casts a [`ThinPtr`] to a [`ThinRefMut`].
+	///
+	/// # Safety
+	/// The pointer `ptr` must point to a value preceded by its VTable
+	/// pointer, with both the value's and the VTable's lifetime matching
+	/// or outliving `'a`. The value must not be aliased.
+	#[inline(always)]
+	pub unsafe fn from_raw(ptr: ThinPtr<V>) -> Self {
+		Self {
+			ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	#[inline(always)]
+	pub fn borrow(r: &Self) -> ThinRef<'_, V> {
+		// SAFETY: the lifetime of the returned ThinRef matches that of `r`'s borrow,
+		// and therefore cannot escape and allow an XOR mutability violation.
+		unsafe { ThinRef::from_raw(r.ptr) }
+	}
+
+	#[inline(always)]
+	pub fn borrow_mut(r: &mut Self) -> ThinRefMut<'_, V> {
+		// SAFETY: the lifetime of the returned ThinRefMut matches that of `r`'s borrow,
+		// and therefore cannot escape and allow two mutable references to the same data.
+		unsafe { ThinRefMut::from_raw(r.ptr) }
+	}
+
+	/// Returns the thin pointer held by the reference.
+	#[inline(always)]
+	pub fn as_raw(r: &Self) -> ThinPtr<V> {
+		r.ptr
+	}
+
+	/// Returns a reference to the VTable of the referenced value.
+	#[inline(always)]
+	pub fn vtable(r: &Self) -> &'a V::VTable {
+		// SAFETY: the vtable pointer is valid for `'a` (see `from_raw`)
+		unsafe { &*ThinPtr::vtable(r.ptr) }
+	}
+
+	/// Converts the thin reference into a [`DynRefMut`] to the same value.
+	#[inline(always)]
+	pub fn to_dyn(r: Self) -> DynRefMut<'a, V> {
+		// SAFETY: the header is valid for `'a` (see `from_raw`)
+		unsafe { DynRefMut::from_raw(ThinPtr::to_dyn(r.ptr)) }
+	}
+
+	/// Upcast the given mutable thin reference to a bounded dyntrait ref.
+	///
+	/// The header is not changed, as the value may be borrowed from an owner
+	/// expecting its original VTable, so the upcast reference is a
+	/// [`DynRefMut`].
+	#[inline(always)]
+	pub fn upcast<U>(r: Self) -> DynRefMut<'a, U>
+	where
+		U: VTableRepr + ?Sized,
+		V::VTable: SubTable<U::VTable>,
+	{
+		DynRefMut::upcast(Self::to_dyn(r))
+	}
+}
+
+impl<'a, V: VTableRepr + ?Sized> From<ThinRefMut<'a, V>> for DynRefMut<'a, V> {
+	#[inline(always)]
+	fn from(value: ThinRefMut<'a, V>) -> Self {
+		ThinRefMut::to_dyn(value)
+	}
+}
+
+impl<'a, V: VTableRepr + ?Sized> Deref for ThinRefMut<'a, V> {
+	type Target = ThinRefCallProxy<'a, V>;
+
+	#[inline(always)]
+	fn deref(&self) -> &Self::Target {
+		ThinRefCallProxy::from_raw(&self.ptr)
+	}
+}
+
+impl<V: VTableRepr + ?Sized> DerefMut for ThinRefMut<'_, V> {
+	#[inline(always)]
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		ThinRefCallProxy::from_raw_mut(&mut self.ptr)
+	}
+}
+
+#[doc(hidden)]
+#[repr(transparent)]
+pub struct ThinRefCallProxy<'a, V: VTableRepr + ?Sized> {
+	ptr: ThinPtr<V>,
+	// The proxy stands in for the referenced value, and a pinned proxy is
+	// only obtainable through a pinned `ThinRef` or `ThinRefMut`.
+	_lt: PhantomData<(&'a (), PhantomPinned)>,
+}
+
+impl<V: VTableRepr + ?Sized> ThinRefCallProxy<'_, V> {
+	#[inline(always)]
+	fn from_raw(ptr: &ThinPtr<V>) -> &Self {
+		// SAFETY: `ThinRefCallProxy`'s layout matches that of a `ThinPtr`
+		unsafe { mem::transmute(ptr) }
+	}
+
+	#[inline(always)]
+	fn from_raw_mut(ptr: &mut ThinPtr<V>) -> &mut Self {
+		// SAFETY: `ThinRefCallProxy`'s layout matches that of a `ThinPtr`
+		unsafe { mem::transmute(ptr) }
+	}
+}
+
+unsafe impl<R, V> AsDyn<R> for ThinRefCallProxy<'_, V>
+where
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+{
+	type Repr = V;
+
+	const PINS_TARGET: bool = true;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.ptr.ptr
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		// SAFETY: the proxy is only obtainable through a valid thin reference
+		unsafe { ThinPtr::vtable(self.ptr) }
+	}
+
+	fn dyn_dealloc(self) {
+		unreachable!("references cannot be deallocated");
+	}
+}
+
+/// An FFI safe Box that operates on dyntable traits, storing the VTable
+/// pointer in the allocation. See the [module docs](self).
+///
+/// When using a zero sized allocator such as [`GlobalAllocator`], a
+/// `ThinBox` has the same layout as a `void*`.
+#[repr(C)]
+pub struct ThinBox<V, A = GlobalAllocator>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	alloc: A,
+	ptr: ThinPtr<V>,
+}
+
+unsafe impl<V, A> Send for ThinBox<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	<V::VTable as VTable>::Bounds: Send,
+{
+}
+
+unsafe impl<V, A> Sync for ThinBox<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+	<V::VTable as VTable>::Bounds: Sync,
+{
+}
+
+unsafe impl<R, V, A> AsDyn<R> for ThinBox<V, A>
+where
+	A: Deallocator,
+	R: ?Sized,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	type Repr = V;
+
+	#[inline(always)]
+	fn dyn_ptr(&self) -> *mut c_void {
+		self.ptr.ptr
+	}
+
+	#[inline(always)]
+	fn dyn_vtable(&self) -> *const <Self::Repr as VTableRepr>::VTable {
+		unsafe { ThinPtr::vtable(self.ptr) }
+	}
+
+	fn dyn_dealloc(self) {
+		unsafe {
+			let (memory, layout) = Self::allocation(self.ptr);
+			self.alloc.deallocate(memory, layout);
+		}
+
+		mem::forget(self);
+	}
+}
+
+impl<V> ThinBox<V, GlobalAllocator>
+where
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Allocates memory using the global allocator and moves `data` into
+	/// the allocated memory after its VTable pointer, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, thin::*};
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let x: ThinBox<dyn MyTrait> = ThinBox::new(0u8);
+	/// ```
+	#[inline]
+	pub fn new<'v, T>(data: T) -> Self
+	where
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		ThinBox::new_in(data, GlobalAllocator)
+	}
+
+	/// Constructs a `ThinBox` from a raw thin pointer in the global allocator.
+	///
+	/// After calling this function, the raw thin pointer is considered to be
+	/// owned by the `ThinBox` and will be cleaned up as such.
+	///
+	/// # Safety
+	/// The pointer `ptr` must be an owned thin pointer to memory allocated
+	/// by the rust global allocator, laid out as a `ThinBox` allocation.
+	#[inline(always)]
+	pub unsafe fn from_raw(ptr: ThinPtr<V>) -> Self {
+		Self::from_raw_in(ptr, GlobalAllocator)
+	}
+}
+
+impl<V, A> ThinBox<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Allocates memory using the given allocator and moves `data` into
+	/// the allocated memory after its VTable pointer, upcasting it to `V`.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	#[inline]
+	pub fn new_in<'v, T>(data: T, alloc: A) -> Self
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		match Self::try_new_in(data, alloc) {
+			Ok(thinbox) => thinbox,
+			Err(_) => handle_alloc_error(header_layout(MemoryLayout::new::<T>()).0.into()),
+		}
+	}
+
+	/// Allocates memory using the given allocator and moves `data` into
+	/// the allocated memory after its VTable pointer, upcasting it to `V`,
+	/// and returning an error if the allocation fails.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, thin::*};
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let x: ThinBox<dyn MyTrait> = ThinBox::try_new_in(0u8, dyntable::alloc::GlobalAllocator)?;
+	/// # Ok::<_, dyntable::alloc::AllocError>(())
+	/// ```
+	#[inline]
+	pub fn try_new_in<'v, T>(data: T, alloc: A) -> Result<Self, AllocError>
+	where
+		A: Allocator,
+		T: DynTrait<'v, V::VTable>,
+		V::VTable: 'v,
+	{
+		let (layout, offset) = header_layout(MemoryLayout::new::<T>());
+
+		unsafe {
+			let ptr = alloc.allocate(layout)?.cast::<u8>().as_ptr().add(offset);
+			(ptr as *mut *const V::VTable)
+				.sub(1)
+				.write(<T as DynTrait<'v, V::VTable>>::STATIC_VTABLE);
+			(ptr as *mut T).write(data);
+
+			Ok(Self::from_raw_in(ThinPtr::from_raw(ptr as *mut c_void), alloc))
+		}
+	}
+
+	/// Constructs a `ThinBox` from a raw thin pointer in the given allocator.
+	///
+	/// After calling this function, the raw thin pointer is considered to be
+	/// owned by the `ThinBox` and will be cleaned up as such.
+	///
+	/// # Safety
+	/// The pointer `ptr` must be an owned thin pointer to memory allocated
+	/// by the allocator `alloc`, laid out as a `ThinBox` allocation.
+	#[inline(always)]
+	pub unsafe fn from_raw_in(ptr: ThinPtr<V>, alloc: A) -> Self {
+		Self { ptr, alloc }
+	}
+
+	/// Upcast the thin box to a bounded dyntrait box, replacing the
+	/// VTable pointer stored in the allocation.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use dyntable::{*, thin::*};
+	/// #[dyntable]
+	/// trait Animal {}
+	///
+	/// #[dyntable]
+	/// trait Feline: Animal
+	/// where
+	///     dyn Animal:,
+	/// {}
+	///
+	/// struct Cat;
+	///
+	/// impl Feline for Cat {}
+	/// impl Animal for Cat {}
+	///
+	/// let feline: ThinBox<dyn Feline> = ThinBox::new(Cat);
+	/// let animal: ThinBox<dyn Animal> = ThinBox::upcast(feline);
+	/// ```
+	#[inline(always)]
+	pub fn upcast<U>(b: Self) -> ThinBox<U, A>
+	where
+		U: VTableRepr + ?Sized,
+		U::VTable: AssociatedDrop + AssociatedLayout,
+		V::VTable: SubTable<U::VTable>,
+	{
+		let (ptr, alloc) = Self::into_raw_with_allocator(b);
+
+		unsafe {
+			// SAFETY: the box owns its header, and the subtable describes
+			// the same value, keeping the allocation's layout unchanged.
+			let vtable = (*ThinPtr::vtable(ptr)).subtable() as *const U::VTable;
+			(ptr.ptr as *mut *const U::VTable).sub(1).write(vtable);
+
+			ThinBox::from_raw_in(ThinPtr::from_raw(ptr.ptr), alloc)
+		}
+	}
+
+	/// Leak a ThinBox, returning its ThinPtr and Allocator
+	#[inline(always)]
+	pub fn into_raw_with_allocator(b: Self) -> (ThinPtr<V>, A) {
+		// SAFETY: the original value is forgotten
+		let alloc = unsafe { (&b.alloc as *const A).read() };
+		let ptr = b.ptr;
+		mem::forget(b);
+
+		(ptr, alloc)
+	}
+
+	/// Leak a ThinBox into a ThinPtr
+	///
+	/// # Examples
+	/// Recreate a `ThinBox` which was previously converted to a raw pointer.
+	///
+	/// ```
+	/// # use dyntable::{*, thin::*};
+	/// #[dyntable]
+	/// trait MyTrait {}
+	/// impl MyTrait for u8 {}
+	///
+	/// let x: ThinBox<dyn MyTrait> = ThinBox::new(0u8);
+	/// let ptr = ThinBox::into_raw(x);
+	/// let x: ThinBox<dyn MyTrait> = unsafe { ThinBox::from_raw(ptr) };
+	/// ```
+	#[inline(always)]
+	pub fn into_raw(b: Self) -> ThinPtr<V> {
+		Self::into_raw_with_allocator(b).0
+	}
+
+	/// Immutably borrows the wrapped value.
+	#[inline(always)]
+	pub fn borrow(b: &Self) -> ThinRef<'_, V> {
+		ThinRef {
+			ptr: b.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Mutably borrows the wrapped value.
+	#[inline(always)]
+	pub fn borrow_mut(b: &mut Self) -> ThinRefMut<'_, V> {
+		ThinRefMut {
+			ptr: b.ptr,
+			_lt: PhantomData,
+		}
+	}
+
+	/// Returns a reference to the VTable of the wrapped value.
+	///
+	/// This can be used to read VTable data fields, such as
+	/// [associated constants](crate::dyntable#associated-constants).
+	#[inline(always)]
+	pub fn vtable(b: &Self) -> &V::VTable {
+		unsafe { &*ThinPtr::vtable(b.ptr) }
+	}
+
+	/// Returns the start and layout of the allocation holding the value
+	/// pointed to by `ptr`.
+	#[inline(always)]
+	unsafe fn allocation(ptr: ThinPtr<V>) -> (NonNull<u8>, MemoryLayout) {
+		let (layout, offset) = header_layout((*ThinPtr::vtable(ptr)).virtual_layout());
+		let memory = NonNull::new_unchecked((ptr.ptr as *mut u8).sub(offset));
+
+		(memory, layout)
+	}
+}
+
+impl<V, A> From<DynBox<V, A>> for ThinBox<V, A>
+where
+	A: Allocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Moves the value of a `DynBox` into a new allocation holding its
+	/// VTable pointer.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	fn from(value: DynBox<V, A>) -> Self {
+		let (ptr, alloc) = DynBox::into_raw_with_allocator(value);
+
+		unsafe {
+			let value_layout = ptr.vtable.as_ref().virtual_layout();
+			let (layout, offset) = header_layout(value_layout);
+
+			let memory = match alloc.allocate(layout) {
+				Ok(memory) => memory.cast::<u8>(),
+				Err(_) => handle_alloc_error(layout.into()),
+			};
+
+			let thin = memory.as_ptr().add(offset);
+			(thin as *mut *const V::VTable).sub(1).write(ptr.vtable.as_ptr());
+			ptr::copy_nonoverlapping(ptr.ptr as *const u8, thin, value_layout.size);
+
+			if !value_layout.is_zero_sized() {
+				alloc.deallocate(NonNull::new_unchecked(ptr.ptr as *mut u8), value_layout);
+			}
+
+			Self::from_raw_in(ThinPtr::from_raw(thin as *mut c_void), alloc)
+		}
+	}
+}
+
+impl<V, A> From<ThinBox<V, A>> for DynBox<V, A>
+where
+	A: Allocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	/// Moves the value of a `ThinBox` into a new allocation without its
+	/// VTable pointer.
+	///
+	/// # Panics
+	/// This method panics on allocation failure.
+	fn from(value: ThinBox<V, A>) -> Self {
+		let (ptr, alloc) = ThinBox::into_raw_with_allocator(value);
+
+		unsafe {
+			let (thin_memory, thin_layout) = ThinBox::<V, A>::allocation(ptr);
+			let DynPtr { vtable, .. } = ThinPtr::to_dyn(ptr);
+			let layout = vtable.as_ref().virtual_layout();
+
+			let memory = match allocate(&alloc, layout) {
+				Ok(memory) => memory,
+				Err(_) => handle_alloc_error(layout.into()),
+			};
+
+			ptr::copy_nonoverlapping(ptr.ptr as *const u8, memory.as_ptr(), layout.size);
+			alloc.deallocate(thin_memory, thin_layout);
+
+			DynBox::from_raw_in(
+				DynPtr {
+					ptr: memory.as_ptr() as *mut c_void,
+					vtable,
+				},
+				alloc,
+			)
+		}
+	}
+}
+
+// `DerefMut` is intentionally not implemented, as swapping the targets of two
+// boxes would mismatch their allocators.
+impl<V, A> Deref for ThinBox<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	type Target = ThinRefCallProxy<'static, V>;
+
+	#[inline(always)]
+	fn deref(&self) -> &Self::Target {
+		ThinRefCallProxy::from_raw(&self.ptr)
+	}
+}
+
+impl<V, A> Drop for ThinBox<V, A>
+where
+	A: Deallocator,
+	V: VTableRepr + ?Sized,
+	V::VTable: AssociatedDrop + AssociatedLayout,
+{
+	fn drop(&mut self) {
+		unsafe {
+			let (memory, layout) = Self::allocation(self.ptr);
+			(*ThinPtr::vtable(self.ptr)).virtual_drop(self.ptr.ptr);
+			self.alloc.deallocate(memory, layout);
+		}
+	}
+}
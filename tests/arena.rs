@@ -0,0 +1,186 @@
+use std::{
+	cell::Cell,
+	mem,
+	ptr::NonNull,
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+use common::{tracked, Aligned, Empty, Tracked};
+use dyntable::{
+	alloc::{AllocError, Allocator, Deallocator, GlobalAllocator, MemoryLayout},
+	arena::{Arena, ArenaBox},
+	dyntable,
+	DynBox,
+};
+
+mod common;
+
+#[dyntable]
+trait Named {
+	extern "C" fn name(&self) -> u32;
+}
+
+#[dyntable]
+trait Value: Named
+where
+	dyn Named:,
+{
+	extern "C" fn get(&self) -> u32;
+	extern "C" fn set(&mut self, value: u32);
+	extern "C" fn take(self) -> u32;
+}
+
+impl Named for Tracked {
+	extern "C" fn name(&self) -> u32 {
+		1
+	}
+}
+
+impl Value for Tracked {
+	extern "C" fn get(&self) -> u32 {
+		self.value
+	}
+
+	extern "C" fn set(&mut self, value: u32) {
+		self.value = value;
+	}
+
+	extern "C" fn take(self) -> u32 {
+		self.value
+	}
+}
+
+impl Named for Aligned {
+	extern "C" fn name(&self) -> u32 {
+		self.0
+	}
+}
+
+impl Named for Empty {
+	extern "C" fn name(&self) -> u32 {
+		0
+	}
+}
+
+impl Named for u8 {
+	extern "C" fn name(&self) -> u32 {
+		*self as u32
+	}
+}
+
+impl Named for [u8; 1024] {
+	extern "C" fn name(&self) -> u32 {
+		self[1023] as u32
+	}
+}
+
+/// Allocator counting live allocations.
+struct Counting<'a>(&'a Cell<usize>);
+
+impl Deallocator for Counting<'_> {
+	unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: MemoryLayout) {
+		self.0.set(self.0.get() - 1);
+		GlobalAllocator.deallocate(ptr, layout);
+	}
+}
+
+impl Allocator for Counting<'_> {
+	fn allocate(&self, layout: MemoryLayout) -> Result<NonNull<[u8]>, AllocError> {
+		self.0.set(self.0.get() + 1);
+		GlobalAllocator.allocate(layout)
+	}
+}
+
+#[test]
+fn calls() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let arena = Arena::new();
+	let mut value = ArenaBox::<dyn Value>::new_in(tracked(42, &DROPS), &arena);
+	assert_eq!(value.name(), 1);
+	value.set(value.get() + 1);
+	assert_eq!(ArenaBox::borrow(&value).get(), 43);
+	assert_eq!(value.take(), 43);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+	let value = ArenaBox::<dyn Value>::new_in(tracked(0, &DROPS), &arena);
+	let named: ArenaBox<dyn Named> = ArenaBox::upcast(value);
+	assert_eq!(named.name(), 1);
+	drop(named);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+
+	// dropped values and moved out values are not dropped again
+	drop(arena);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn reset() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let chunks = Cell::new(0);
+	let mut arena = Arena::with_capacity_in(256, Counting(&chunks));
+
+	for frame in 1..=3 {
+		let mut leaked = Vec::new();
+
+		for value in 0..100 {
+			let boxed = ArenaBox::<dyn Value>::new_in(tracked(value, &DROPS), &arena);
+
+			match value % 2 {
+				0 => leaked.push(ArenaBox::leak(boxed)),
+				_ => mem::forget(boxed),
+			}
+		}
+
+		assert_eq!(leaked.iter().map(|value| value.get()).sum::<u32>(), 2450);
+		assert_eq!(DROPS.load(Ordering::Relaxed), (frame - 1) * 100);
+
+		arena.reset();
+		assert_eq!(DROPS.load(Ordering::Relaxed), frame * 100);
+		assert_eq!(chunks.get(), 1);
+	}
+
+	drop(arena);
+	assert_eq!(chunks.get(), 0);
+}
+
+#[test]
+fn allocator() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let chunks = Cell::new(0);
+	let arena = Arena::new_in(Counting(&chunks));
+
+	let values = (0..10)
+		.map(|value| DynBox::<dyn Value, _>::new_in(tracked(value, &DROPS), &arena))
+		.collect::<Vec<_>>();
+	assert_eq!(values.iter().map(|value| value.get()).sum::<u32>(), 45);
+	assert_eq!(chunks.get(), 1);
+
+	drop(values);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 10);
+	assert_eq!(chunks.get(), 1);
+
+	drop(arena);
+	assert_eq!(chunks.get(), 0);
+}
+
+#[test]
+fn alignment() {
+	let arena = Arena::with_capacity(16);
+
+	let empty = ArenaBox::<dyn Named>::new_in(Empty, &arena);
+	let small = ArenaBox::<dyn Named>::new_in(1u8, &arena);
+	let aligned = ArenaBox::<dyn Named>::new_in(Aligned(5), &arena);
+	let large = ArenaBox::<dyn Named>::new_in([7u8; 1024], &arena);
+
+	assert_eq!(empty.name(), 0);
+	assert_eq!(small.name(), 1);
+	assert_eq!(aligned.name(), 5);
+	assert_eq!(ArenaBox::borrow(&aligned).name(), 5);
+	assert_eq!(large.name(), 7);
+
+	let ptr = Arena::allocate(&arena, MemoryLayout::new::<Aligned>()).unwrap();
+	assert_eq!(ptr.cast::<u8>().as_ptr() as usize % 64, 0);
+}
@@ -0,0 +1,92 @@
+use dyntable::*;
+
+fn require_send<T: Send>(_: T) {}
+
+#[dyntable]
+trait Reader {
+	type Item;
+
+	extern "C" fn read(&mut self) -> Self::Item;
+	extern "C" fn peek(&self) -> &Self::Item;
+	extern "C" fn into_last(self) -> Self::Item;
+}
+
+#[dyntable]
+trait Writer<T>: Reader<Item = T>
+where
+	dyn Reader<Item = T>:,
+{
+	extern "C" fn write(&mut self, value: T);
+}
+
+#[dyntable]
+trait Transform {
+	type Input: Copy;
+	type Output;
+
+	extern "C" fn transform(&self, input: Self::Input) -> Self::Output;
+}
+
+#[repr(C)]
+struct Counter(u32);
+
+impl Reader for Counter {
+	type Item = u32;
+
+	extern "C" fn read(&mut self) -> u32 {
+		self.0 += 1;
+		self.0
+	}
+
+	extern "C" fn peek(&self) -> &u32 {
+		&self.0
+	}
+
+	extern "C" fn into_last(self) -> u32 {
+		self.0
+	}
+}
+
+impl Writer<u32> for Counter {
+	extern "C" fn write(&mut self, value: u32) {
+		self.0 = value;
+	}
+}
+
+struct Doubler;
+
+impl Transform for Doubler {
+	type Input = u32;
+	type Output = u64;
+
+	extern "C" fn transform(&self, input: u32) -> u64 {
+		input as u64 * 2
+	}
+}
+
+#[test]
+fn associated_types() {
+	let mut reader = DynBox::<dyn Reader<Item = u32>>::new(Counter(0));
+	assert_eq!(reader.read(), 1);
+	assert_eq!(*reader.peek(), 1);
+	assert_eq!(reader.into_last(), 1);
+
+	let transform = DynBox::<dyn Transform<Input = u32, Output = u64>>::new(Doubler);
+	assert_eq!(transform.transform(21), 42);
+}
+
+#[test]
+fn upcast() {
+	let mut writer = DynBox::<dyn Writer<u32>>::new(Counter(0));
+	writer.write(41);
+	assert_eq!(writer.read(), 42);
+
+	let mut reader: DynBox<dyn Reader<Item = u32>> = DynBox::upcast(writer);
+	assert_eq!(reader.read(), 43);
+}
+
+#[test]
+fn send_sync() {
+	let reader = DynBox::<dyn Reader<Item = u32> + Send>::new(Counter(0));
+	require_send(reader);
+}
@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use common::{tracked, Aligned, Empty, Tracked};
+use dyntable::{batch::DynBatch, dyntable};
+
+mod common;
+
+#[dyntable]
+trait Value {
+	extern "C" fn kind(&self) -> u32;
+	extern "C" fn get(&self) -> u32;
+	extern "C" fn set(&mut self, value: u32);
+}
+
+impl Value for Tracked {
+	extern "C" fn kind(&self) -> u32 {
+		0
+	}
+
+	extern "C" fn get(&self) -> u32 {
+		self.value
+	}
+
+	extern "C" fn set(&mut self, value: u32) {
+		self.value = value;
+	}
+}
+
+impl Value for Aligned {
+	extern "C" fn kind(&self) -> u32 {
+		assert_eq!(self as *const _ as usize % 64, 0);
+		1
+	}
+
+	extern "C" fn get(&self) -> u32 {
+		self.0
+	}
+
+	extern "C" fn set(&mut self, value: u32) {
+		self.0 = value;
+	}
+}
+
+impl Value for Empty {
+	extern "C" fn kind(&self) -> u32 {
+		2
+	}
+
+	extern "C" fn get(&self) -> u32 {
+		0
+	}
+
+	extern "C" fn set(&mut self, _value: u32) {}
+}
+
+fn kinds(batch: &DynBatch<dyn Value>) -> Vec<u32> {
+	let mut kinds = Vec::new();
+	batch.for_each(|value| kinds.push(value.kind()));
+	kinds
+}
+
+fn values(batch: &DynBatch<dyn Value>) -> Vec<u32> {
+	let mut values = Vec::new();
+	batch.for_each(|value| values.push(value.get()));
+	values
+}
+
+#[test]
+fn grouping() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut batch = DynBatch::<dyn Value>::new();
+	assert!(batch.is_empty());
+
+	for i in 0..30 {
+		match i % 3 {
+			0 => batch.push(Aligned(i)),
+			1 => batch.push(tracked(i, &DROPS)),
+			_ => batch.push(Empty),
+		}
+	}
+
+	assert_eq!(batch.len(), 30);
+	assert_eq!(batch.bucket_count(), 3);
+
+	let expected = [[1; 10], [0; 10], [2; 10]].concat();
+	assert_eq!(kinds(&batch), expected);
+
+	batch.for_each_mut(|mut value| {
+		let current = value.get();
+		value.set(current * 2);
+	});
+
+	let values = values(&batch);
+	assert_eq!(
+		values[..10],
+		(0..30).step_by(3).map(|i| i * 2).collect::<Vec<_>>()
+	);
+	assert_eq!(
+		values[10..20],
+		(1..30).step_by(3).map(|i| i * 2).collect::<Vec<_>>()
+	);
+
+	drop(batch);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 10);
+}
+
+#[test]
+fn retain() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut batch = DynBatch::<dyn Value>::new();
+	for i in 0..10 {
+		batch.push(tracked(i, &DROPS));
+		batch.push(Aligned(i));
+	}
+
+	batch.retain(|value| value.get() % 2 == 0);
+	assert_eq!(batch.len(), 10);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 5);
+
+	let mut values = values(&batch);
+	values[..5].sort();
+	values[5..].sort();
+	assert_eq!(values, [0, 2, 4, 6, 8, 0, 2, 4, 6, 8]);
+
+	batch.retain(|value| value.kind() != 0);
+	assert_eq!(kinds(&batch), [1; 5]);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 10);
+
+	// empty buckets are reused
+	batch.push(tracked(1, &DROPS));
+	assert_eq!(batch.bucket_count(), 2);
+	assert_eq!(kinds(&batch), [0, 1, 1, 1, 1, 1]);
+
+	batch.clear();
+	assert!(batch.is_empty());
+	assert_eq!(DROPS.load(Ordering::Relaxed), 11);
+}
@@ -0,0 +1,126 @@
+#![cfg(not(miri))]
+// `DynBox` with the global allocator is not marked FFI safe
+#![allow(improper_ctypes_definitions)]
+
+use std::{
+	env,
+	ffi::c_void,
+	fs,
+	process::{Command, Stdio},
+};
+
+use dyntable::{
+	cheader::{c_field, CHeader, CType},
+	*,
+};
+
+#[repr(C)]
+struct Point {
+	x: f32,
+	y: f32,
+}
+
+impl CType for Point {
+	fn c_type(header: &mut CHeader) -> String {
+		header.define_struct("Point", |_| {
+			vec![c_field("float", "x"), c_field("float", "y")]
+		})
+	}
+}
+
+#[dyntable(c_header = true, drop = "system")]
+trait Shape {
+	extern "system" fn area(&self) -> f64;
+}
+
+#[dyntable(c_header = true, version = 3, reserved = 2)]
+trait Canvas: Shape
+where
+	&dyn Shape:,
+{
+	const LAYERS: u32;
+
+	extern "C" fn draw<'a>(&'a mut self, at: &'a Point, data: *const c_void) -> bool;
+
+	#[dyntable(optional, field = clear_fn)]
+	extern "C" fn clear(&mut self) {}
+
+	extern "C" fn create(_: u8) -> DynBox<dyn Canvas>;
+}
+
+#[test]
+fn generate() {
+	let header = CHeader::new()
+		.add::<dyn Canvas + Send>()
+		.generate("CANVAS_H");
+
+	let expected_vtable = "\
+struct CanvasVTable {
+	DyntableVTableHeader __header;
+	void (*__drop)(void *);
+	DyntableMemoryLayout __layout;
+	ShapeVTable const *__vtable_Shape;
+	uint32_t LAYERS;
+	bool (*draw)(void *self, Point const *at, void const *data);
+	void (*clear_fn)(void *self);
+	CanvasDynPtr (*create)(uint8_t);
+	void (*__reserved[2])(void);
+};
+";
+
+	assert!(header.contains(expected_vtable), "{header}");
+	assert!(header.contains("double (DYNTABLE_SYSTEM *area)(void *self);"));
+	assert!(header.contains("void (DYNTABLE_SYSTEM *__drop)(void *);"));
+	assert!(header.contains("typedef struct Point Point;"));
+
+	// the generated header must be valid C
+	fs::create_dir_all("target/cheadertest").unwrap();
+	fs::write("target/cheadertest/canvas.h", &header).unwrap();
+	fs::write(
+		"target/cheadertest/canvas.c",
+		"#include \"canvas.h\"\n\
+		 _Static_assert(sizeof(CanvasVTable) > 0, \"\");\n",
+	)
+	.unwrap();
+
+	let status = Command::new(env::var("CC").expect("Missing CC environment var"))
+		.args(["-Werror", "-fsyntax-only", "target/cheadertest/canvas.c"])
+		.stdout(Stdio::inherit())
+		.stderr(Stdio::inherit())
+		.status()
+		.unwrap();
+
+	assert!(status.success());
+
+	// as well as valid C++, including the wrapper classes
+	fs::write(
+		"target/cheadertest/canvas.cpp",
+		"#include \"canvas.h\"\n\
+		 struct Impl {\n\
+		 \tstatic constexpr uint32_t LAYERS = 1;\n\
+		 \tdouble area() const { return 0; }\n\
+		 \tbool draw(Point const *, void const *) { return true; }\n\
+		 \tvoid clear() {}\n\
+		 \tstatic CanvasDynPtr create(uint8_t) { return CanvasImpl<Impl>::new_box(Impl()).ptr; }\n\
+		 };\n\
+		 double area(CanvasBox<> canvas) {\n\
+		 \tCanvasBox<>::borrow_mut(canvas).clear();\n\
+		 \treturn CanvasBox<>::borrow(canvas).as_Shape().area();\n\
+		 }\n",
+	)
+	.unwrap();
+
+	let status = Command::new(env::var("CXX").expect("Missing CXX environment var"))
+		.args([
+			"-Werror",
+			"-std=c++11",
+			"-fsyntax-only",
+			"target/cheadertest/canvas.cpp",
+		])
+		.stdout(Stdio::inherit())
+		.stderr(Stdio::inherit())
+		.status()
+		.unwrap();
+
+	assert!(status.success());
+}
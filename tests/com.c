@@ -0,0 +1,165 @@
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+// The C representation of the interfaces in com.rs, as an IDL compiler
+// would generate it.
+
+typedef int32_t HRESULT;
+
+#define S_OK ((HRESULT) 0)
+#define E_NOINTERFACE ((HRESULT) 0x80004002)
+
+typedef struct GUID {
+	uint32_t Data1;
+	uint16_t Data2;
+	uint16_t Data3;
+	uint8_t Data4[8];
+} GUID;
+
+static const GUID IID_IUnknown =
+	{0x00000000, 0x0000, 0x0000, {0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46}};
+static const GUID IID_IValue =
+	{0x8f3b1c2a, 0x5d4e, 0x4f60, {0xa1, 0xb2, 0xc3, 0xd4, 0xe5, 0xf6, 0x07, 0x18}};
+static const GUID IID_ICounter =
+	{0x2a4c6e80, 0x1b3d, 0x4f5a, {0x8c, 0x9e, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f}};
+static const GUID IID_IOther =
+	{0x00112233, 0x4455, 0x6677, {0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff}};
+
+typedef struct IUnknown IUnknown;
+typedef struct IValue IValue;
+typedef struct ICounter ICounter;
+
+typedef struct IUnknownVtbl {
+	HRESULT (*QueryInterface)(IUnknown* This, const GUID* riid, void** ppvObject);
+	uint32_t (*AddRef)(IUnknown* This);
+	uint32_t (*Release)(IUnknown* This);
+} IUnknownVtbl;
+
+typedef struct IValueVtbl {
+	HRESULT (*QueryInterface)(IValue* This, const GUID* riid, void** ppvObject);
+	uint32_t (*AddRef)(IValue* This);
+	uint32_t (*Release)(IValue* This);
+	int32_t (*get)(IValue* This);
+} IValueVtbl;
+
+typedef struct ICounterVtbl {
+	HRESULT (*QueryInterface)(ICounter* This, const GUID* riid, void** ppvObject);
+	uint32_t (*AddRef)(ICounter* This);
+	uint32_t (*Release)(ICounter* This);
+	int32_t (*get)(ICounter* This);
+	int32_t (*add)(ICounter* This, int32_t n);
+} ICounterVtbl;
+
+struct IUnknown {
+	const IUnknownVtbl* lpVtbl;
+};
+
+struct IValue {
+	const IValueVtbl* lpVtbl;
+};
+
+struct ICounter {
+	const ICounterVtbl* lpVtbl;
+};
+
+static int guid_eq(const GUID* a, const GUID* b) {
+	return memcmp(a, b, sizeof(GUID)) == 0;
+}
+
+// Client code, usable with both Rust and C objects.
+// Returns -1 if the object does not behave as expected.
+int32_t c_use_unknown(IUnknown* unknown) {
+	ICounter* counter = NULL;
+	IValue* value = NULL;
+	void* other = (void*) 1;
+	int32_t result;
+
+	if (unknown->lpVtbl->QueryInterface(unknown, &IID_ICounter, (void**) &counter) != S_OK) return -1;
+	if (unknown->lpVtbl->QueryInterface(unknown, &IID_IOther, &other) != E_NOINTERFACE) return -1;
+	if (other != NULL) return -1;
+	if (counter->lpVtbl->QueryInterface(counter, &IID_IValue, (void**) &value) != S_OK) return -1;
+
+	counter->lpVtbl->add(counter, 2);
+	counter->lpVtbl->add(counter, 3);
+	result = value->lpVtbl->get(value);
+
+	// the caller, counter and value each hold a reference
+	if (value->lpVtbl->AddRef(value) != 4) return -1;
+	if (value->lpVtbl->Release(value) != 3) return -1;
+
+	value->lpVtbl->Release(value);
+	counter->lpVtbl->Release(counter);
+	return result;
+}
+
+// A C implementation of ICounter
+
+struct CCounter {
+	ICounter iface;
+	uint32_t refs;
+	int32_t value;
+};
+
+static uint32_t alive = 0;
+
+uint32_t c_alive(void) {
+	return alive;
+}
+
+static HRESULT c_counter_query_interface(ICounter* This, const GUID* riid, void** ppvObject) {
+	if (guid_eq(riid, &IID_IUnknown) || guid_eq(riid, &IID_IValue) || guid_eq(riid, &IID_ICounter)) {
+		This->lpVtbl->AddRef(This);
+		*ppvObject = This;
+		return S_OK;
+	}
+
+	*ppvObject = NULL;
+	return E_NOINTERFACE;
+}
+
+static uint32_t c_counter_add_ref(ICounter* This) {
+	struct CCounter* self = (struct CCounter*) This;
+	return ++self->refs;
+}
+
+static uint32_t c_counter_release(ICounter* This) {
+	struct CCounter* self = (struct CCounter*) This;
+	uint32_t refs = --self->refs;
+
+	if (refs == 0) {
+		alive -= 1;
+		free(self);
+	}
+
+	return refs;
+}
+
+static int32_t c_counter_get(ICounter* This) {
+	struct CCounter* self = (struct CCounter*) This;
+	return self->value;
+}
+
+static int32_t c_counter_add(ICounter* This, int32_t n) {
+	struct CCounter* self = (struct CCounter*) This;
+	self->value += n;
+	return self->value;
+}
+
+static const ICounterVtbl c_counter_vtbl = {
+	.QueryInterface = c_counter_query_interface,
+	.AddRef = c_counter_add_ref,
+	.Release = c_counter_release,
+	.get = c_counter_get,
+	.add = c_counter_add,
+};
+
+ICounter* c_new_counter(int32_t value) {
+	struct CCounter* self = malloc(sizeof(struct CCounter));
+	self->iface.lpVtbl = &c_counter_vtbl;
+	self->refs = 1;
+	self->value = value;
+	alive += 1;
+	return &self->iface;
+}
@@ -0,0 +1,198 @@
+#![cfg(not(miri))]
+
+use std::{
+	env,
+	ffi::c_void,
+	fs,
+	mem::{offset_of, size_of},
+	process::{Command, Stdio},
+	sync::atomic::{AtomicI32, AtomicU32, Ordering},
+};
+
+use dyntable::{com::*, *};
+
+#[dyntable(com = "8f3b1c2a-5d4e-4f60-a1b2-c3d4e5f60718")]
+trait IValue: IUnknown
+where
+	dyn IUnknown:,
+{
+	extern "system" fn get(&self) -> i32;
+}
+
+#[dyntable(com = "{2a4c6e80-1b3d-4f5a-8c9e-0a1b2c3d4e5f}")]
+trait ICounter: IValue
+where
+	dyn IValue: IUnknown,
+{
+	extern "system" fn add(&self, n: i32) -> i32;
+}
+
+// not implemented by any object
+#[dyntable(com = "00112233-4455-6677-8899-aabbccddeeff")]
+trait IOther: IUnknown
+where
+	dyn IUnknown:,
+{
+	extern "system" fn other(&self);
+}
+
+struct RustCounter {
+	value: AtomicI32,
+	drops: &'static AtomicU32,
+}
+
+impl IValue for RustCounter {
+	extern "system" fn get(&self) -> i32 {
+		self.value.load(Ordering::Relaxed)
+	}
+}
+
+impl ICounter for RustCounter {
+	extern "system" fn add(&self, n: i32) -> i32 {
+		self.value.fetch_add(n, Ordering::Relaxed) + n
+	}
+}
+
+impl Drop for RustCounter {
+	fn drop(&mut self) {
+		self.drops.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+#[test]
+fn com_layout() {
+	let ptr = size_of::<*const c_void>();
+
+	// IUnknown entries come first, followed by each interface's methods
+	assert_eq!(size_of::<IUnknownVTable>(), ptr * 3);
+	assert_eq!(offset_of!(IUnknownVTable, query_interface), 0);
+	assert_eq!(offset_of!(IUnknownVTable, add_ref), ptr);
+	assert_eq!(offset_of!(IUnknownVTable, release), ptr * 2);
+	assert_eq!(size_of::<IValueVTable>(), ptr * 4);
+	assert_eq!(offset_of!(IValueVTable, get), ptr * 3);
+	assert_eq!(size_of::<ICounterVTable>(), ptr * 5);
+	assert_eq!(offset_of!(ICounterVTable, add), ptr * 4);
+
+	assert_eq!(size_of::<Guid>(), 16);
+	assert_eq!(size_of::<ComPtr<dyn ICounter>>(), ptr);
+	assert_eq!(size_of::<Option<ComPtr<dyn ICounter>>>(), ptr);
+
+	assert_eq!(<ICounterVTable as ComInterface>::IID, Guid {
+		data1: 0x2a4c6e80,
+		data2: 0x1b3d,
+		data3: 0x4f5a,
+		data4: [0x8c, 0x9e, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f],
+	});
+	assert_eq!(
+		<ICounterVTable as ComInterface>::IID.to_string(),
+		"2a4c6e80-1b3d-4f5a-8c9e-0a1b2c3d4e5f"
+	);
+	assert!(<ICounterVTable as ComInterface>::supports(&IID_IUNKNOWN));
+	assert!(<ICounterVTable as ComInterface>::supports(
+		&<IValueVTable as ComInterface>::IID
+	));
+	assert!(!<IValueVTable as ComInterface>::supports(
+		&<ICounterVTable as ComInterface>::IID
+	));
+}
+
+#[test]
+fn com_rust() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let counter = ComPtr::<dyn ICounter>::new(RustCounter {
+		value: AtomicI32::new(1),
+		drops: &DROPS,
+	});
+	assert_eq!(counter.add(2), 3);
+	assert_eq!(counter.get(), 3);
+
+	let value = ComPtr::upcast::<dyn IValue>(counter.clone());
+	assert_eq!(value.get(), 3);
+	assert!(ComPtr::query::<dyn IOther>(&value).is_none());
+
+	let unknown = ComPtr::query::<dyn IUnknown>(&value).unwrap();
+	let counter2 = ComPtr::query::<dyn ICounter>(&unknown).unwrap();
+	assert_eq!(counter2.add(1), 4);
+	assert_eq!(ComPtr::as_raw(&counter), ComPtr::as_raw(&counter2));
+
+	drop((counter, value, unknown));
+	assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+	drop(counter2);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn com_ffi() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	fs::create_dir_all("target/comtest").unwrap();
+
+	// the C side is written against the usual C representation of COM
+	// interfaces, as generated by an IDL compiler
+	let status = Command::new(env::var("CC").expect("Missing CC environment var"))
+		.args([
+			"-Werror",
+			"-shared",
+			"-fPIC",
+			"-o",
+			"target/comtest/libcom.so",
+			"tests/com.c",
+		])
+		.stdout(Stdio::inherit())
+		.stderr(Stdio::inherit())
+		.status()
+		.unwrap();
+
+	assert!(status.success());
+
+	unsafe {
+		let lib = libloading::Library::new("./target/comtest/libcom.so").unwrap();
+
+		let c_use_unknown = lib
+			.get::<unsafe extern "system" fn(*mut c_void) -> i32>(b"c_use_unknown")
+			.unwrap();
+		let c_new_counter = lib
+			.get::<unsafe extern "system" fn(i32) -> *mut c_void>(b"c_new_counter")
+			.unwrap();
+		let c_alive = lib
+			.get::<unsafe extern "system" fn() -> u32>(b"c_alive")
+			.unwrap();
+
+		// Rust object used by a C client
+		let counter = ComPtr::<dyn ICounter>::new(RustCounter {
+			value: AtomicI32::new(10),
+			drops: &DROPS,
+		});
+		let unknown = ComPtr::upcast::<dyn IUnknown>(counter);
+		assert_eq!(c_use_unknown(ComPtr::as_raw(&unknown)), 15);
+		let counter = ComPtr::query::<dyn ICounter>(&unknown).unwrap();
+		assert_eq!(counter.get(), 15);
+		drop(unknown);
+		assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+		drop(counter);
+		assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+		// C object used from Rust
+		let counter = ComPtr::<dyn ICounter>::from_raw(c_new_counter(3));
+		assert_eq!(c_alive(), 1);
+		assert_eq!(counter.get(), 3);
+		assert_eq!(counter.add(4), 7);
+		assert!(ComPtr::query::<dyn IOther>(&counter).is_none());
+
+		let value = ComPtr::query::<dyn IValue>(&counter).unwrap();
+		assert_eq!(value.get(), 7);
+		let unknown = ComPtr::query::<dyn IUnknown>(&value).unwrap();
+		drop((counter, value));
+
+		// the C client works with C objects as well
+		assert_eq!(c_use_unknown(ComPtr::as_raw(&unknown)), 12);
+		let value = ComPtr::query::<dyn IValue>(&unknown).unwrap();
+		assert_eq!(value.get(), 12);
+
+		drop(unknown);
+		assert_eq!(c_alive(), 1);
+		drop(value);
+		assert_eq!(c_alive(), 0);
+	}
+}
@@ -0,0 +1,29 @@
+//! Fixtures shared by the container tests. Each test implements its own
+//! traits for them.
+
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Value counting its drops.
+#[repr(C)]
+pub struct Tracked {
+	pub value: u32,
+	pub drops: &'static AtomicU32,
+}
+
+impl Drop for Tracked {
+	fn drop(&mut self) {
+		self.drops.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+pub fn tracked(value: u32, drops: &'static AtomicU32) -> Tracked {
+	Tracked { value, drops }
+}
+
+/// Value with a larger alignment than any allocation header.
+#[repr(C, align(64))]
+pub struct Aligned(pub u32);
+
+pub struct Empty;
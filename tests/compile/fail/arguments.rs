@@ -2,13 +2,6 @@ use dyntable::dyntable;
 
 fn main() {}
 
-// associated functions are not supported
-
-#[dyntable]
-trait Associated {
-	fn associated();
-}
-
 // variadics are not supported
 
 #[dyntable]
@@ -20,7 +13,7 @@ trait Variadic {
 
 #[dyntable]
 trait WrongFirstArg {
-	fn test(foo: Bar);
+	fn test(foo: Bar, &self);
 }
 
 // self must be implicit (not typed)
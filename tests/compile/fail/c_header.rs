@@ -0,0 +1,24 @@
+use dyntable::dyntable;
+
+#[dyntable(c_header = true)]
+trait Generic<T> {}
+
+#[dyntable(c_header = true)]
+trait Associated {
+	type Item;
+}
+
+#[dyntable(c_header = true, relax_abi = true)]
+trait RustAbi {
+	fn foo(&self);
+}
+
+#[dyntable(c_header = true)]
+trait UnsupportedAbi {
+	extern "efiapi" fn foo(&self);
+}
+
+#[dyntable(c_header = true, repr = Rust)]
+trait RustRepr {}
+
+fn main() {}
@@ -0,0 +1,75 @@
+use dyntable::{com::IUnknown, dyntable};
+
+#[dyntable(com = "not-a-guid")]
+trait BadGuid: IUnknown
+where
+	dyn IUnknown:,
+{
+}
+
+#[dyntable(com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c", embed_layout = true)]
+trait Layout: IUnknown
+where
+	dyn IUnknown:,
+{
+}
+
+#[dyntable(com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c")]
+trait NoParent {}
+
+#[dyntable(com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c")]
+trait RefParent: IUnknown
+where
+	&dyn IUnknown:,
+{
+}
+
+#[dyntable(com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c")]
+trait Generic<T>: IUnknown
+where
+	dyn IUnknown:,
+{
+}
+
+#[dyntable(com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c")]
+trait Mutable: IUnknown
+where
+	dyn IUnknown:,
+{
+	extern "system" fn set(&mut self);
+}
+
+#[dyntable(com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c")]
+trait PinnedMut: IUnknown
+where
+	dyn IUnknown:,
+{
+	extern "system" fn set(self: core::pin::Pin<&mut Self>);
+}
+
+#[dyntable(com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c")]
+trait CAbi: IUnknown
+where
+	dyn IUnknown:,
+{
+	extern "C" fn get(&self);
+}
+
+#[dyntable(com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c")]
+trait Consts: IUnknown
+where
+	dyn IUnknown:,
+{
+	const VALUE: u32;
+}
+
+#[dyntable(com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c")]
+trait Slot: IUnknown
+where
+	dyn IUnknown:,
+{
+	#[dyntable(slot = 0)]
+	extern "system" fn get(&self);
+}
+
+fn main() {}
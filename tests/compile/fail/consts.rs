@@ -0,0 +1,18 @@
+use dyntable::dyntable;
+
+fn main() {}
+
+// constants are stored in the VTable, and their types
+// cannot depend on generics
+
+#[dyntable]
+trait GenericConst<T> {
+	const VALUE: T;
+}
+
+#[dyntable]
+trait AssociatedConst {
+	type Value;
+
+	const VALUE: Option<Self::Value>;
+}
@@ -0,0 +1,9 @@
+use dyntable::dyntable;
+
+#[dyntable(header = false, version = 2)]
+trait DisabledHeader {}
+
+#[dyntable(repr = Rust, header = true)]
+trait RustRepr {}
+
+fn main() {}
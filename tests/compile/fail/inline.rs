@@ -0,0 +1,18 @@
+use dyntable::{dyntable, inline::DynInline};
+
+#[dyntable]
+trait Named {
+	extern "C" fn name(&self) -> u32;
+}
+
+struct Large([u32; 8]);
+
+impl Named for Large {
+	extern "C" fn name(&self) -> u32 {
+		self.0[0]
+	}
+}
+
+fn main() {
+	let _ = DynInline::<dyn Named, 16>::new(Large([0; 8]));
+}
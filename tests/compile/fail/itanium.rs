@@ -0,0 +1,44 @@
+use dyntable::{dyntable, itanium::Polymorphic};
+
+#[dyntable(itanium = true, drop = "C")]
+trait Drop: Polymorphic
+where
+	dyn Polymorphic:,
+{
+}
+
+#[dyntable(itanium = true, com = "6c2a8b5e-3f1d-4e0a-9b7c-2d4e6f8a0b1c")]
+trait Com: Polymorphic
+where
+	dyn Polymorphic:,
+{
+}
+
+#[dyntable(itanium = true)]
+trait NoParent {}
+
+#[dyntable(itanium = true)]
+trait Value: Polymorphic
+where
+	dyn Polymorphic:,
+{
+	extern "C" fn take(self);
+}
+
+#[dyntable(itanium = true)]
+trait Static: Polymorphic
+where
+	dyn Polymorphic:,
+{
+	extern "C" fn create() -> u32;
+}
+
+#[dyntable(itanium = true)]
+trait SystemAbi: Polymorphic
+where
+	dyn Polymorphic:,
+{
+	extern "system" fn get(&self);
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use dyntable::{boxed::NoDropBox, dyntable};
+
+#[dyntable(drop = none)]
+trait Named {
+	extern "C" fn name(&self) -> u32;
+}
+
+struct Owned(String);
+
+impl Named for Owned {
+	extern "C" fn name(&self) -> u32 {
+		self.0.len() as u32
+	}
+}
+
+fn main() {
+	let _ = NoDropBox::<dyn Named>::new(Owned(String::new()));
+}
@@ -0,0 +1,21 @@
+use core::pin::Pin;
+
+use dyntable::*;
+
+fn main() {
+	let mut counter = DynBox::<dyn Counter>::new(0u32);
+	// DynBox is Unpin, so its target is not pinned
+	Pin::new(&mut counter).next();
+}
+
+#[dyntable]
+trait Counter {
+	extern "C" fn next(self: Pin<&mut Self>) -> u32;
+}
+
+impl Counter for u32 {
+	extern "C" fn next(mut self: Pin<&mut Self>) -> u32 {
+		*self += 1;
+		*self
+	}
+}
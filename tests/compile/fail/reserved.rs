@@ -0,0 +1,6 @@
+use dyntable::dyntable;
+
+#[dyntable(repr = Rust, reserved = 2)]
+trait RustRepr {}
+
+fn main() {}
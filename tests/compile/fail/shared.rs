@@ -0,0 +1,18 @@
+use dyntable::{rc::DynRc, *};
+
+fn main() {
+	let value = DynRc::<dyn Value>::new(0u32);
+	// the value is shared, and cannot be moved out of the container
+	value.take();
+}
+
+#[dyntable]
+trait Value {
+	extern "C" fn take(self) -> u32;
+}
+
+impl Value for u32 {
+	extern "C" fn take(self) -> u32 {
+		self
+	}
+}
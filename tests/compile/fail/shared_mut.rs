@@ -0,0 +1,22 @@
+use dyntable::{rc::*, *};
+
+fn main() {
+	let mut value = DynRc::<dyn Counter>::new(0u32);
+	let _shared = value.clone();
+	// the value is shared, and cannot be mutated through the container
+	value.increment();
+
+	let mut value = DynArc::<dyn Counter>::new(0u32);
+	value.increment();
+}
+
+#[dyntable]
+trait Counter {
+	extern "C" fn increment(&mut self);
+}
+
+impl Counter for u32 {
+	extern "C" fn increment(&mut self) {
+		*self += 1;
+	}
+}
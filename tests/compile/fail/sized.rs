@@ -0,0 +1,29 @@
+use dyntable::*;
+
+fn main() {
+	let mut counter = DynBox::<dyn Counter>::new(Step(0));
+	// sized methods cannot be called through a container
+	counter.skip();
+}
+
+#[dyntable]
+trait Counter {
+	extern "C" fn next(&mut self) -> u32;
+
+	fn skip(&mut self)
+	where
+		Self: Sized,
+	{
+		self.next();
+	}
+}
+
+#[repr(C)]
+struct Step(u32);
+
+impl Counter for Step {
+	extern "C" fn next(&mut self) -> u32 {
+		self.0 += 1;
+		self.0
+	}
+}
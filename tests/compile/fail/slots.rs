@@ -0,0 +1,41 @@
+use dyntable::dyntable;
+
+#[dyntable]
+trait OutOfRange {
+	#[dyntable(slot = 3)]
+	extern "C" fn foo(&self);
+}
+
+#[dyntable]
+trait Duplicate {
+	#[dyntable(slot = 2)]
+	extern "C" fn foo(&self);
+	#[dyntable(slot = 2)]
+	extern "C" fn bar(&self);
+}
+
+#[dyntable(drop = none, drop_slot = 0)]
+trait NoDrop {}
+
+#[dyntable(dealloc_slot = 0)]
+trait NoDealloc {}
+
+#[dyntable]
+trait OptionalConst {
+	#[dyntable(optional)]
+	const FOO: u32;
+}
+
+#[dyntable]
+trait OptionalWithoutBody {
+	#[dyntable(optional)]
+	extern "C" fn foo(&self);
+}
+
+#[dyntable]
+trait UnknownOption {
+	#[dyntable(position = 0)]
+	extern "C" fn foo(&self);
+}
+
+fn main() {}
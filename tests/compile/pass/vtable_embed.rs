@@ -1,6 +1,6 @@
 //! Ensure VTables still work with and without embedded metadata.
 
-use dyntable::{dyntable, DynPtr, DynBox};
+use dyntable::{alloc::Free, boxed::NoDropBox, dyntable, DynBox, DynPtr};
 
 fn main() {}
 
@@ -26,3 +26,14 @@ struct NoMetaPtr(DynPtr<dyn NoMeta>);
 // ensure full meta dyntraits can be used in a box
 
 struct DefaultBox(DynBox<dyn Default>);
+
+// ensure dyntraits without a layout can be used in a box with a free deallocator
+
+type FreeFn = unsafe extern "C" fn(*mut core::ffi::c_void);
+
+struct NoLayoutBox(DynBox<dyn NoLayout, Free<FreeFn>>);
+
+// ensure dyntraits without a drop function can be used in a no-drop box
+
+struct NoDropNoDropBox(NoDropBox<dyn NoDrop>);
+struct NoMetaNoDropBox(NoDropBox<dyn NoMeta, Free<FreeFn>>);
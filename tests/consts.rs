@@ -0,0 +1,65 @@
+use dyntable::*;
+
+#[dyntable]
+trait Plugin {
+	/// Capability mask of the plugin.
+	const CAPABILITIES: u32;
+	const VERSION: (u16, u16) = (1, 0);
+
+	extern "C" fn id(&self) -> u32;
+}
+
+#[dyntable(consts = CodecInfo)]
+trait Codec: Plugin
+where
+	dyn Plugin:,
+{
+	type Sample;
+
+	const SAMPLE_RATE: u32;
+}
+
+struct Passthrough;
+
+impl Plugin for Passthrough {
+	extern "C" fn id(&self) -> u32 {
+		7
+	}
+}
+
+impl PluginConsts for Passthrough {
+	const CAPABILITIES: u32 = 0b101;
+}
+
+impl Codec for Passthrough {
+	type Sample = i16;
+}
+
+impl CodecInfo for Passthrough {
+	const SAMPLE_RATE: u32 = 44100;
+}
+
+#[test]
+fn consts() {
+	let mut plugin = DynBox::<dyn Plugin>::new(Passthrough);
+	assert_eq!(DynBox::vtable(&plugin).CAPABILITIES, 0b101);
+	assert_eq!(DynBox::vtable(&plugin).VERSION, (1, 0));
+	assert_eq!(DynRef::vtable(&DynBox::borrow(&plugin)).CAPABILITIES, 0b101);
+	assert_eq!(
+		DynRefMut::vtable(&DynBox::borrow_mut(&mut plugin)).VERSION,
+		(1, 0)
+	);
+	assert_eq!(plugin.id(), 7);
+
+	let plugin = DynBox::<dyn Plugin + Send + Sync>::new(Passthrough);
+	assert_eq!(DynBox::vtable(&plugin).CAPABILITIES, 0b101);
+}
+
+#[test]
+fn inherited_consts() {
+	let codec = DynBox::<dyn Codec<Sample = i16>>::new(Passthrough);
+	assert_eq!(DynBox::vtable(&codec).SAMPLE_RATE, 44100);
+
+	let plugin: DynRef<dyn Plugin> = DynRef::upcast(DynBox::borrow(&codec));
+	assert_eq!(DynRef::vtable(&plugin).CAPABILITIES, 0b101);
+}
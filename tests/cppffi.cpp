@@ -0,0 +1,106 @@
+#include <cstdlib>
+
+// generated by the cppffi test
+#include "cppffi.h"
+
+static uint32_t drops = 0;
+
+class CppCounter {
+public:
+	static constexpr uint32_t STEP = 2;
+
+	explicit CppCounter(uint32_t value) : value(value), changes_(0) {}
+	CppCounter(CppCounter &&other) : value(other.value), changes_(other.changes_) {}
+
+	~CppCounter() {
+		drops += 1;
+	}
+
+	uint32_t id() const {
+		return 7;
+	}
+
+	uint32_t changes() const {
+		return changes_;
+	}
+
+	uint32_t add(uint32_t amount) {
+		value += amount * STEP;
+		changes_ += 1;
+		return value;
+	}
+
+	uint32_t get() const {
+		return value;
+	}
+
+	uint32_t into_total() && {
+		return value + changes_ * 100;
+	}
+
+	void reset() {
+		value = 0;
+	}
+
+	static uint32_t zero() {
+		return 0;
+	}
+
+private:
+	uint32_t value;
+	uint32_t changes_;
+};
+
+struct RustDeallocator {
+	void (*dealloc)(void *, DyntableMemoryLayout);
+
+	void deallocate(void *ptr, DyntableMemoryLayout layout) const {
+		dealloc(ptr, layout);
+	}
+};
+
+extern "C" {
+uint32_t cpp_drops() {
+	return drops;
+}
+
+CounterDynPtr cpp_new_counter(uint32_t value) {
+	CounterBox<> counter = CounterImpl<CppCounter>::new_box(CppCounter(value));
+	return CounterBox<>::into_raw(counter);
+}
+
+void cpp_free(void *ptr) {
+	std::free(ptr);
+}
+
+uint32_t cpp_use_counter(CounterDynPtr ptr) {
+	CounterMut counter(ptr);
+
+	if (!counter.has_reset() || CounterMut::vtable(counter).STEP == 0) {
+		return 0;
+	}
+
+	counter.add(3);
+
+	// upcast through the pointer subtable, then the nested inline subtable
+	NamedRef named = counter.as_Named();
+	TrackedRef tracked = CounterRef(counter).as_Tracked();
+
+	if (tracked.as_Named().id() != named.id() || tracked.changes() == 0) {
+		return 0;
+	}
+
+	return named.id() * 1000 + counter.get();
+}
+
+uint32_t cpp_consume_counter(CounterDynPtr ptr, void (*dealloc)(void *, DyntableMemoryLayout)) {
+	CounterBox<RustDeallocator> counter(ptr, RustDeallocator{dealloc});
+	CounterBox<RustDeallocator> moved(std::move(counter));
+
+	if (counter || CounterBox<RustDeallocator>::borrow_mut(moved).add(1) != 4) {
+		return 0;
+	}
+
+	return std::move(moved).into_total();
+}
+}
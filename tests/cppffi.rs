@@ -0,0 +1,220 @@
+#![cfg(not(miri))]
+
+use std::{
+	env,
+	ffi::c_void,
+	fs,
+	process::{Command, Stdio},
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+use dyntable::{alloc::MemoryLayout, owned::DynOwned, *};
+
+#[test]
+fn cppffi() {
+	fs::create_dir_all("target/cppffitest").unwrap();
+
+	// the C++ implementation uses wrapper classes generated from the traits below
+	let header = cheader::CHeader::new()
+		.add::<dyn Counter>()
+		.generate("CPPFFI_H");
+	fs::write("target/cppffitest/cppffi.h", header).unwrap();
+
+	let status = Command::new(env::var("CXX").expect("Missing CXX environment var"))
+		.args([
+			"-Werror",
+			"-std=c++11",
+			"-Itarget/cppffitest",
+			"-shared",
+			"-fPIC",
+			"-o",
+			"target/cppffitest/libcppffi.so",
+			"tests/cppffi.cpp",
+		])
+		.stdout(Stdio::inherit())
+		.stderr(Stdio::inherit())
+		.status()
+		.unwrap();
+
+	assert!(status.success());
+
+	unsafe {
+		let lib = libloading::Library::new("./target/cppffitest/libcppffi.so").unwrap();
+
+		let cpp_drops = lib
+			.get::<unsafe extern "C" fn() -> u32>(b"cpp_drops")
+			.unwrap();
+		let cpp_new_counter = lib
+			.get::<unsafe extern "C" fn(u32) -> DynPtr<dyn Counter>>(b"cpp_new_counter")
+			.unwrap();
+		let cpp_free = lib
+			.get::<unsafe extern "C" fn(*mut c_void)>(b"cpp_free")
+			.unwrap();
+		let cpp_use_counter = lib
+			.get::<unsafe extern "C" fn(DynRefMut<dyn Counter>) -> u32>(b"cpp_use_counter")
+			.unwrap();
+		let cpp_consume_counter = lib
+			.get::<unsafe extern "C" fn(
+				DynPtr<dyn Counter>,
+				unsafe extern "C" fn(*mut c_void, MemoryLayout),
+			) -> u32>(b"cpp_consume_counter")
+			.unwrap();
+
+		// C++ implementation used from Rust
+		let mut cpp_counter =
+			DynBox::from_raw_in(cpp_new_counter(1), CppDeallocator { free: *cpp_free });
+
+		assert_eq!(DynBox::header(&cpp_counter).version, 1);
+		assert_eq!(cpp_counter.id(), 7);
+		assert_eq!(cpp_counter.changes(), 0);
+		assert_eq!(cpp_counter.add(3), 7);
+		assert_eq!(cpp_counter.changes(), 1);
+		assert_eq!(DynBox::vtable(&cpp_counter).STEP, 2);
+		assert_eq!((DynBox::vtable(&cpp_counter).zero)(), 0);
+		cpp_counter.reset();
+		assert_eq!(cpp_counter.get(), 0);
+
+		// the C++ implementation is used through the wrapper classes as well
+		assert_eq!(cpp_use_counter(DynBox::borrow_mut(&mut cpp_counter)), 7006);
+		assert_eq!(cpp_counter.get(), 6);
+
+		let drops = cpp_drops();
+		drop(cpp_counter);
+		assert_eq!(cpp_drops(), drops + 1);
+
+		// VTables built by C++ free boxed values with `free`
+		let named = DynOwned::<dyn Named>::from_raw(DynPtr::upcast(cpp_new_counter(1)));
+		assert_eq!(named.id(), 7);
+
+		let drops = cpp_drops();
+		drop(named);
+		assert_eq!(cpp_drops(), drops + 1);
+
+		// Rust implementation used from C++
+		let mut rust_counter = DynBox::<dyn Counter>::new(RustCounter {
+			value: 0,
+			changes: 0,
+		});
+		assert_eq!(cpp_use_counter(DynBox::borrow_mut(&mut rust_counter)), 1003);
+		assert_eq!(rust_counter.get(), 3);
+		assert_eq!(rust_counter.changes(), 1);
+
+		RUST_DROPS.store(0, Ordering::Relaxed);
+		DEALLOCS.store(0, Ordering::Relaxed);
+
+		// ownership is passed to C++, which consumes the counter and
+		// deallocates it with the Rust allocator
+		assert_eq!(
+			cpp_consume_counter(DynBox::into_raw(rust_counter), rust_dealloc),
+			204
+		);
+		assert_eq!(RUST_DROPS.load(Ordering::Relaxed), 1);
+		assert_eq!(DEALLOCS.load(Ordering::Relaxed), 1);
+	}
+}
+
+#[dyntable(c_header = true, dealloc = "C")]
+trait Named {
+	extern "C" fn id(&self) -> u32;
+}
+
+#[dyntable(c_header = true)]
+trait Tracked: Named
+where
+	dyn Named:,
+{
+	extern "C" fn changes(&self) -> u32;
+}
+
+#[dyntable(c_header = true, version = 1)]
+trait Counter: Tracked
+where
+	&dyn Tracked: Named,
+{
+	const STEP: u32;
+
+	extern "C" fn add(&mut self, amount: u32) -> u32;
+	extern "C" fn get(&self) -> u32;
+	extern "C" fn into_total(self) -> u32;
+
+	#[dyntable(optional)]
+	extern "C" fn reset(&mut self) {}
+
+	extern "C" fn zero() -> u32;
+}
+
+static RUST_DROPS: AtomicU32 = AtomicU32::new(0);
+static DEALLOCS: AtomicU32 = AtomicU32::new(0);
+
+#[repr(C)]
+struct RustCounter {
+	value: u32,
+	changes: u32,
+}
+
+impl Drop for RustCounter {
+	fn drop(&mut self) {
+		RUST_DROPS.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+impl Named for RustCounter {
+	extern "C" fn id(&self) -> u32 {
+		1
+	}
+}
+
+impl Tracked for RustCounter {
+	extern "C" fn changes(&self) -> u32 {
+		self.changes
+	}
+}
+
+impl CounterConsts for RustCounter {
+	const STEP: u32 = 1;
+}
+
+impl Counter for RustCounter {
+	extern "C" fn add(&mut self, amount: u32) -> u32 {
+		self.value += amount * Self::STEP;
+		self.changes += 1;
+		self.value
+	}
+
+	extern "C" fn get(&self) -> u32 {
+		self.value
+	}
+
+	extern "C" fn into_total(self) -> u32 {
+		self.value + self.changes * 100
+	}
+
+	extern "C" fn reset(&mut self) {
+		self.value = 0;
+	}
+
+	extern "C" fn zero() -> u32 {
+		0
+	}
+}
+
+unsafe extern "C" fn rust_dealloc(ptr: *mut c_void, layout: MemoryLayout) {
+	DEALLOCS.fetch_add(1, Ordering::Relaxed);
+
+	alloc::Deallocator::deallocate(
+		&alloc::GlobalAllocator,
+		std::ptr::NonNull::new(ptr as *mut u8).unwrap(),
+		layout,
+	);
+}
+
+#[derive(Copy, Clone)]
+struct CppDeallocator {
+	free: unsafe extern "C" fn(*mut c_void),
+}
+
+impl alloc::Deallocator for CppDeallocator {
+	unsafe fn deallocate(&self, ptr: std::ptr::NonNull<u8>, _: MemoryLayout) {
+		(self.free)(ptr.as_ptr() as *mut c_void)
+	}
+}
@@ -0,0 +1,52 @@
+use dyntable::*;
+
+#[dyntable]
+trait Shape {
+	extern "C" fn area(&self) -> u32;
+
+	/// Number of sides, defaulting to a square.
+	extern "C" fn sides(&self) -> u32 {
+		4
+	}
+
+	extern "C" fn scaled_area(&self, factor: u32) -> u32 {
+		self.area() * factor * factor
+	}
+}
+
+#[repr(C)]
+struct Square(u32);
+
+impl Shape for Square {
+	extern "C" fn area(&self) -> u32 {
+		self.0 * self.0
+	}
+}
+
+#[repr(C)]
+struct Triangle(u32, u32);
+
+impl Shape for Triangle {
+	extern "C" fn area(&self) -> u32 {
+		self.0 * self.1 / 2
+	}
+
+	extern "C" fn sides(&self) -> u32 {
+		3
+	}
+}
+
+#[test]
+fn default_methods() {
+	let square = DynBox::<dyn Shape>::new(Square(2));
+	assert_eq!(square.area(), 4);
+	assert_eq!(square.sides(), 4);
+	assert_eq!(square.scaled_area(2), 16);
+
+	let triangle = DynBox::<dyn Shape>::new(Triangle(2, 4));
+	assert_eq!(triangle.area(), 4);
+	assert_eq!(triangle.sides(), 3);
+
+	// a default body is also usable directly on the concrete type
+	assert_eq!(Square(3).scaled_area(3), 81);
+}
@@ -0,0 +1,258 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use common::{tracked, Aligned, Empty, Tracked};
+use dyntable::{
+	dyntable,
+	dynvec::{DynVec, Taken},
+};
+
+mod common;
+
+#[dyntable]
+trait Value {
+	extern "C" fn get(&self) -> u32;
+	extern "C" fn set(&mut self, value: u32);
+	extern "C" fn take(self) -> u32;
+}
+
+impl Value for Tracked {
+	extern "C" fn get(&self) -> u32 {
+		self.value
+	}
+
+	extern "C" fn set(&mut self, value: u32) {
+		self.value = value;
+	}
+
+	extern "C" fn take(self) -> u32 {
+		self.value
+	}
+}
+
+impl Value for Aligned {
+	extern "C" fn get(&self) -> u32 {
+		// values are moved when the buffer grows, and must stay aligned
+		assert_eq!(self as *const _ as usize % 64, 0);
+		self.0
+	}
+
+	extern "C" fn set(&mut self, value: u32) {
+		self.0 = value;
+	}
+
+	extern "C" fn take(self) -> u32 {
+		self.0
+	}
+}
+
+#[repr(C)]
+struct Large([u32; 64]);
+
+impl Value for Large {
+	extern "C" fn get(&self) -> u32 {
+		self.0[63]
+	}
+
+	extern "C" fn set(&mut self, value: u32) {
+		self.0[63] = value;
+	}
+
+	extern "C" fn take(self) -> u32 {
+		self.0[63]
+	}
+}
+
+// zero sized types are not FFI safe, but are passed by value to `take`
+#[allow(improper_ctypes_definitions)]
+impl Value for Empty {
+	extern "C" fn get(&self) -> u32 {
+		0
+	}
+
+	extern "C" fn set(&mut self, _value: u32) {}
+
+	extern "C" fn take(self) -> u32 {
+		0
+	}
+}
+
+impl Value for u8 {
+	extern "C" fn get(&self) -> u32 {
+		*self as u32
+	}
+
+	extern "C" fn set(&mut self, value: u32) {
+		*self = value as u8;
+	}
+
+	extern "C" fn take(self) -> u32 {
+		self as u32
+	}
+}
+
+fn values(vec: &DynVec<dyn Value>) -> Vec<u32> {
+	vec.iter().map(|value| value.get()).collect()
+}
+
+#[test]
+fn push_get() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut vec = DynVec::<dyn Value>::new();
+	assert!(vec.is_empty());
+	assert!(vec.get(0).is_none());
+
+	for i in 0..100 {
+		match i % 4 {
+			0 => vec.push(tracked(i, &DROPS)),
+			1 => vec.push(Aligned(i)),
+			2 => vec.push(Large([i; 64])),
+			_ => vec.push(Empty),
+		}
+	}
+
+	assert_eq!(vec.len(), 100);
+	for (i, value) in vec.iter().enumerate() {
+		let expected = match i % 4 {
+			3 => 0,
+			_ => i as u32,
+		};
+		assert_eq!(value.get(), expected);
+	}
+
+	assert_eq!(vec.get(4).unwrap().get(), 4);
+	vec.get_mut(4).unwrap().set(1000);
+	assert_eq!(vec.get(4).unwrap().get(), 1000);
+
+	for mut value in &mut vec {
+		let current = value.get();
+		value.set(current + 1);
+	}
+	assert_eq!(vec.iter().rev().nth(1).unwrap().get(), 99);
+	assert_eq!((&vec).into_iter().len(), 100);
+
+	drop(vec);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 25);
+}
+
+#[test]
+fn pop() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut vec = DynVec::<dyn Value>::new();
+	vec.push(tracked(1, &DROPS));
+	vec.push(Large([2; 64]));
+	vec.push(tracked(3, &DROPS));
+
+	assert_eq!(vec.pop().unwrap().take(), 3);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+	let popped = vec.pop().unwrap();
+	assert_eq!(Taken::borrow(&popped).get(), 2);
+	drop(popped);
+
+	vec.push(tracked(4, &DROPS));
+	assert_eq!(values(&vec), [1, 4]);
+
+	while let Some(value) = vec.pop() {
+		drop(value);
+	}
+	assert!(vec.is_empty());
+	assert_eq!(DROPS.load(Ordering::Relaxed), 3);
+}
+
+#[test]
+fn swap_remove() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut vec = DynVec::<dyn Value>::new();
+	for i in 0..5 {
+		vec.push(tracked(i, &DROPS));
+	}
+
+	assert_eq!(vec.swap_remove(1).get(), 1);
+	assert_eq!(values(&vec), [0, 4, 2, 3]);
+	assert_eq!(vec.swap_remove(3).take(), 3);
+	assert_eq!(values(&vec), [0, 4, 2]);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+
+	// the space of removed values is reclaimed when the buffer grows
+	for i in 5..200 {
+		vec.push(tracked(i, &DROPS));
+		drop(vec.swap_remove(0));
+	}
+	assert_eq!(vec.len(), 3);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 197);
+
+	drop(vec);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 200);
+}
+
+#[test]
+#[should_panic = "swap_remove index (is 1) should be < len (is 1)"]
+fn swap_remove_bounds() {
+	let mut vec = DynVec::<dyn Value>::new();
+	vec.push(Empty);
+	vec.swap_remove(1);
+}
+
+#[test]
+fn drain() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut vec = DynVec::<dyn Value>::new();
+	for i in 0..10 {
+		vec.push(tracked(i, &DROPS));
+	}
+
+	let drained = vec
+		.drain(2..5)
+		.map(|value| value.take())
+		.collect::<Vec<_>>();
+	assert_eq!(drained, [2, 3, 4]);
+	assert_eq!(values(&vec), [0, 1, 5, 6, 7, 8, 9]);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 3);
+
+	// values not returned by the iterator are dropped
+	let mut drain = vec.drain(1..=4);
+	assert_eq!(drain.len(), 4);
+	assert_eq!(drain.next_back().unwrap().get(), 7);
+	assert_eq!(drain.next().unwrap().get(), 1);
+	drop(drain);
+	assert_eq!(values(&vec), [0, 8, 9]);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 7);
+
+	vec.push(Aligned(10));
+	assert_eq!(vec.drain(..).count(), 4);
+	assert!(vec.is_empty());
+	assert_eq!(DROPS.load(Ordering::Relaxed), 10);
+
+	vec.push(tracked(11, &DROPS));
+	assert_eq!(values(&vec), [11]);
+	vec.clear();
+	assert!(vec.is_empty());
+	assert_eq!(DROPS.load(Ordering::Relaxed), 11);
+}
+
+#[test]
+fn compaction() {
+	let mut vec = DynVec::<dyn Value>::new();
+	vec.push(Empty);
+	for i in 0..8 {
+		vec.push(i as u8);
+		vec.push(Aligned(i));
+	}
+
+	drop(vec.swap_remove(1));
+	drop(vec.swap_remove(4));
+	let mut expected = vec![0, 7, 0, 1, 7, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6];
+	assert_eq!(values(&vec), expected);
+
+	// values are packed in index order when the buffer grows, and stay aligned
+	for i in 8..40 {
+		vec.push(i as u8);
+		vec.push(Aligned(i));
+		expected.extend([i, i]);
+	}
+	assert_eq!(values(&vec), expected);
+}
@@ -1,6 +1,9 @@
 #include <stdlib.h>
 #include <stddef.h>
 
+// generated by the ffi test
+#include "ffi.h"
+
 static struct DebugFlags {
 	unsigned int cdealloc_calls;
 	unsigned int cdrop_calls;
@@ -13,56 +16,33 @@ struct DebugFlags* get_debug_flags() {
 	return &debug_flags;
 }
 
-struct Layout {
-	size_t size;
-	size_t align;
-};
-
-struct ParentTraitVTable {
-	void (*drop)(void*);
-	struct Layout layout;
-	int (*get)(void*);
-};
-
-struct BoundedTraitVTable {
-	void (*drop)(void*);
-	struct Layout layout;
-	struct ParentTraitVTable parent;
-	void (*set)(void*, int);
-};
-
-struct DynPtr {
-	void* ptr;
-	void* vtable;
-};
-
 struct CValue {
-	int value;
+	int32_t value;
 };
 
-void drop_c_value() {
+void drop_c_value(void* value) {
 	debug_flags.cdrop_calls += 1;
 }
 
-int c_value_get(void* value) {
+int32_t c_value_get(void* value) {
 	struct CValue* v = value;
 	return v->value;
 }
 
-void c_value_set(void* value, int set) {
+void c_value_set(void* value, int32_t set) {
 	struct CValue* v = value;
 	v->value = set;
 }
 
-static struct BoundedTraitVTable c_value_vtable = {
-	.drop = &drop_c_value,
-	.layout = {
+static BoundedTraitVTable c_value_vtable = {
+	.__drop = &drop_c_value,
+	.__layout = {
 		.size = sizeof(struct CValue),
 		.align = _Alignof(struct CValue),
 	},
-	.parent = {
-		.drop = &drop_c_value,
-		.layout = {
+	.__vtable_ParentTrait = {
+		.__drop = &drop_c_value,
+		.__layout = {
 			.size = sizeof(struct CValue),
 			.align = _Alignof(struct CValue),
 		},
@@ -71,11 +51,11 @@ static struct BoundedTraitVTable c_value_vtable = {
 	.set = &c_value_set,
 };
 
-struct DynPtr new_c_value() {
+BoundedTraitDynPtr new_c_value() {
 	struct CValue* allocation = malloc(sizeof(struct CValue));
 	allocation->value = 0;
 
-	struct DynPtr ptr = {
+	BoundedTraitDynPtr ptr = {
 		.ptr = allocation,
 		.vtable = &c_value_vtable,
 	};
@@ -83,22 +63,114 @@ struct DynPtr new_c_value() {
 	return ptr;
 }
 
-void dealloc_c_value(void* value) {
+static void* c_allocate(void* ctx, DyntableMemoryLayout layout) {
+	return malloc(layout.size);
+}
+
+static void c_deallocate(void* ctx, void* ptr, DyntableMemoryLayout layout) {
+	free(ptr);
+	debug_flags.cdealloc_calls += 1;
+}
+
+static void* c_reallocate(
+	void* ctx,
+	void* ptr,
+	DyntableMemoryLayout old_layout,
+	DyntableMemoryLayout new_layout
+) {
+	// zero sized blocks are not allocated, and are represented by their alignment
+	if (old_layout.size == 0) {
+		ptr = NULL;
+	}
+
+	if (new_layout.size == 0) {
+		free(ptr);
+		return (void*) new_layout.align;
+	}
+
+	return realloc(ptr, new_layout.size);
+}
+
+DyntableAllocator c_allocator() {
+	DyntableAllocator alloc = {
+		.ctx = NULL,
+		.allocate = &c_allocate,
+		.deallocate = &c_deallocate,
+		.reallocate = &c_reallocate,
+	};
+
+	return alloc;
+}
+
+static void dealloc_owned_value(void* value) {
 	free(value);
 	debug_flags.cdealloc_calls += 1;
 }
 
+static OwnedTraitVTable owned_value_vtable = {
+	.__drop = &drop_c_value,
+	.__layout = {
+		.size = sizeof(struct CValue),
+		.align = _Alignof(struct CValue),
+	},
+	.__dealloc = &dealloc_owned_value,
+	.get = &c_value_get,
+};
+
+// owned values are freed through their vtable, without exporting a dealloc function
+OwnedTraitDynPtr new_owned_value(int32_t value) {
+	struct CValue* allocation = malloc(sizeof(struct CValue));
+	allocation->value = value;
+
+	OwnedTraitDynPtr ptr = {
+		.ptr = allocation,
+		.vtable = &owned_value_vtable,
+	};
+
+	return ptr;
+}
+
+void free_owned(OwnedTraitDynPtr ptr) {
+	ptr.vtable->__drop(ptr.ptr);
+	ptr.vtable->__dealloc(ptr.ptr);
+}
 
-void increment_bounded(struct DynPtr ptr) {
-	struct BoundedTraitVTable* vtable = ptr.vtable;
+// frees a box allocated by rust
+void free_bounded(BoundedTraitDynBox box) {
+	const BoundedTraitVTable* vtable = box.ptr.vtable;
 
-	int value = vtable->parent.get(ptr.ptr);
+	vtable->__drop(box.ptr.ptr);
+	box.alloc.deallocate(box.alloc.ctx, box.ptr.ptr, vtable->__layout);
+}
+
+
+void increment_bounded(BoundedTraitDynPtr ptr) {
+	const BoundedTraitVTable* vtable = ptr.vtable;
+
+	int32_t value = vtable->__vtable_ParentTrait.get(ptr.ptr);
 	value += 1;
 	vtable->set(ptr.ptr, value);
 }
 
-int get_parent(struct DynPtr ptr) {
-	struct ParentTraitVTable* vtable = ptr.vtable;
+int32_t get_parent(ParentTraitDynPtr ptr) {
+	const ParentTraitVTable* vtable = ptr.vtable;
 
 	return vtable->get(ptr.ptr);
 }
+
+BoundedTraitDynPtr new_null_value() {
+	BoundedTraitDynPtr ptr = {
+		.ptr = NULL,
+		.vtable = NULL,
+	};
+
+	return ptr;
+}
+
+int32_t get_parent_or(ParentTraitDynPtr ptr, int32_t fallback) {
+	if (ptr.vtable == NULL) {
+		return fallback;
+	}
+
+	return ptr.vtable->get(ptr.ptr);
+}
@@ -2,20 +2,39 @@
 
 use std::{
 	env,
-	ffi::c_void,
 	fs,
 	process::{Command, Stdio},
+	ptr::NonNull,
+	sync::atomic::{AtomicU32, Ordering},
 };
 
-use dyntable::{alloc::Deallocator, *};
+use dyntable::{
+	alloc::{DynAllocator, GlobalAllocator, MemoryLayout},
+	cheader::CType,
+	owned::DynOwned,
+	*,
+};
 
 #[test]
 fn ffi() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
 	fs::create_dir_all("target/ffitest").unwrap();
 
+	// the C implementation uses VTable definitions generated from the traits below
+	let mut header = cheader::CHeader::new();
+	header
+		.add::<dyn BoundedTrait>()
+		.add::<dyn ParentTrait>()
+		.add::<dyn OwnedTrait>();
+	<DynBox<dyn BoundedTrait, DynAllocator>>::c_type(&mut header);
+	let header = header.generate("FFI_H");
+	fs::write("target/ffitest/ffi.h", header).unwrap();
+
 	Command::new(env::var("CC").expect("Missing CC environment var"))
 		.args([
 			"-Werror",
+			"-Itarget/ffitest",
 			"-shared",
 			"-o",
 			"target/ffitest/libffi.so",
@@ -35,8 +54,17 @@ fn ffi() {
 		let new_c_value = lib
 			.get::<unsafe extern "C" fn() -> DynPtr<dyn BoundedTrait>>(b"new_c_value")
 			.unwrap();
-		let dealloc_c_value = lib
-			.get::<unsafe extern "C" fn(*mut c_void)>(b"dealloc_c_value")
+		let c_allocator = lib
+			.get::<unsafe extern "C" fn() -> DynAllocator>(b"c_allocator")
+			.unwrap();
+		let new_owned_value = lib
+			.get::<unsafe extern "C" fn(i32) -> DynOwned<dyn OwnedTrait>>(b"new_owned_value")
+			.unwrap();
+		let free_owned = lib
+			.get::<unsafe extern "C" fn(DynOwned<dyn OwnedTrait>)>(b"free_owned")
+			.unwrap();
+		let free_bounded = lib
+			.get::<unsafe extern "C" fn(DynBox<dyn BoundedTrait, DynAllocator>)>(b"free_bounded")
 			.unwrap();
 		let c_increment_bounded = lib
 			.get::<unsafe extern "C" fn(DynRefMut<dyn BoundedTrait>)>(b"increment_bounded")
@@ -44,6 +72,14 @@ fn ffi() {
 		let c_get_parent = lib
 			.get::<unsafe extern "C" fn(DynRef<dyn ParentTrait>) -> i32>(b"get_parent")
 			.unwrap();
+		let new_null_value = lib
+			.get::<unsafe extern "C" fn() -> Option<DynPtr<dyn BoundedTrait>>>(b"new_null_value")
+			.unwrap();
+		let c_get_parent_or = lib
+			.get::<unsafe extern "C" fn(Option<DynRef<dyn ParentTrait>>, i32) -> i32>(
+				b"get_parent_or",
+			)
+			.unwrap();
 
 		let mut rust_value = DynBox::<dyn BoundedTrait>::new(RustValue { value: 0 });
 
@@ -54,25 +90,19 @@ fn ffi() {
 			c_get_parent(DynRef::upcast(DynBox::borrow(&rust_value)))
 		);
 
-		#[derive(Copy, Clone)]
-		struct CDeallocator {
-			dealloc: unsafe extern "C" fn(*mut c_void),
-		}
-
-		let c_dealloc = CDeallocator {
-			dealloc: *dealloc_c_value,
-		};
-
-		impl Deallocator for CDeallocator {
-			unsafe fn deallocate(&self, ptr: std::ptr::NonNull<u8>, _: alloc::MemoryLayout) {
-				(self.dealloc)(ptr.as_ptr() as *mut c_void)
-			}
-		}
+		// `None` is passed to and from C as a null vtable
+		assert!(new_null_value().is_none());
+		assert_eq!(c_get_parent_or(None, -1), -1);
+		assert_eq!(
+			c_get_parent_or(Some(DynRef::upcast(DynBox::borrow(&rust_value))), -1),
+			1
+		);
 
 		assert_eq!((*debug_flags).cdrop_calls, 0);
 		assert_eq!((*debug_flags).cdealloc_calls, 0);
 
-		let mut c_value = DynBox::from_raw_in(new_c_value(), c_dealloc);
+		// values allocated by C are freed by C's allocator
+		let mut c_value = DynBox::from_raw_in(new_c_value(), c_allocator());
 		assert_eq!(c_value.get(), 0);
 		c_value.set(c_value.get() + 1);
 		assert_eq!(c_value.get(), 1);
@@ -80,21 +110,82 @@ fn ffi() {
 
 		assert_eq!((*debug_flags).cdrop_calls, 1);
 		assert_eq!((*debug_flags).cdealloc_calls, 1);
+
+		// and values allocated by rust are freed from C by rust's allocator
+		let rust_value = DynBox::new_in(
+			DroppedValue {
+				value: 0,
+				drops: &DROPS,
+			},
+			DynAllocator::global(),
+		);
+		free_bounded(rust_value);
+		assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+		// C allocators can also allocate rust values
+		let mut rust_value = DynBox::new_in(RustValue { value: 0 }, c_allocator());
+		c_increment_bounded(DynBox::borrow_mut(&mut rust_value));
+		assert_eq!(rust_value.get(), 1);
+		free_bounded(rust_value);
+		assert_eq!((*debug_flags).cdealloc_calls, 2);
+
+		// values owned through a VTable with a `dealloc` function are freed by it
+		let owned = new_owned_value(5);
+		assert_eq!(owned.get(), 5);
+		drop(owned);
+		assert_eq!((*debug_flags).cdrop_calls, 2);
+		assert_eq!((*debug_flags).cdealloc_calls, 3);
+
+		free_owned(DynOwned::new(DroppedValue {
+			value: 0,
+			drops: &DROPS,
+		}));
+		assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+
+		// values allocated by C can be moved into rust's allocator
+		let c_value = DynBox::from_raw_in(new_c_value(), c_allocator());
+		let c_value: DynBox<dyn BoundedTrait> = DynBox::reallocate_in(c_value, GlobalAllocator);
+		assert_eq!((*debug_flags).cdealloc_calls, 4);
+		assert_eq!(c_value.get(), 0);
+		drop(c_value);
+		assert_eq!((*debug_flags).cdrop_calls, 3);
+		assert_eq!((*debug_flags).cdealloc_calls, 4);
+
+		reallocate_zero_sized(c_allocator());
 	}
 }
 
+/// Moves a zero sized block into an allocated block and back.
+unsafe fn reallocate_zero_sized(alloc: DynAllocator) {
+	let empty = MemoryLayout::new::<[u64; 0]>();
+	let full = MemoryLayout::new::<u64>();
+
+	let ptr = alloc
+		.reallocate(NonNull::<u64>::dangling().cast(), empty, full)
+		.unwrap();
+	ptr.cast::<u64>().as_ptr().write(5);
+
+	let ptr = alloc.reallocate(ptr.cast(), full, empty).unwrap();
+	assert_eq!(ptr.cast::<u8>().as_ptr() as usize, empty.align);
+}
+
+#[test]
+fn zero_sized() {
+	unsafe { reallocate_zero_sized(DynAllocator::global()) };
+}
+
 #[repr(C)]
 struct DebugFlags {
 	cdealloc_calls: u32,
 	cdrop_calls: u32,
 }
 
-#[dyntable]
+#[dyntable(c_header = true)]
 trait ParentTrait {
 	extern "C" fn get(&self) -> i32;
 }
 
-#[dyntable]
+#[dyntable(c_header = true)]
 trait BoundedTrait: ParentTrait
 where
 	dyn ParentTrait:,
@@ -102,6 +193,17 @@ where
 	extern "C" fn set(&mut self, value: i32);
 }
 
+#[dyntable(c_header = true, dealloc = "C")]
+trait OwnedTrait {
+	extern "C" fn get(&self) -> i32;
+}
+
+impl OwnedTrait for DroppedValue {
+	extern "C" fn get(&self) -> i32 {
+		self.value
+	}
+}
+
 // no #[repr(C)]
 struct RustValue {
 	value: i32,
@@ -118,3 +220,26 @@ impl BoundedTrait for RustValue {
 		self.value = value;
 	}
 }
+
+struct DroppedValue {
+	value: i32,
+	drops: &'static AtomicU32,
+}
+
+impl Drop for DroppedValue {
+	fn drop(&mut self) {
+		self.drops.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+impl ParentTrait for DroppedValue {
+	extern "C" fn get(&self) -> i32 {
+		self.value
+	}
+}
+
+impl BoundedTrait for DroppedValue {
+	extern "C" fn set(&mut self, value: i32) {
+		self.value = value;
+	}
+}
@@ -0,0 +1,127 @@
+use std::{
+	ffi::c_void,
+	mem::{offset_of, size_of},
+	ptr::NonNull,
+};
+
+use dyntable::{alloc::MemoryLayout, *};
+
+// A VTable defined by a C library:
+//
+// struct stream_vtable {
+//     size_t (*read)(void *self, size_t len);
+//     void (*destroy)(void *self);
+//     uint32_t kind;
+//     struct { size_t size; size_t align; } layout;
+//     size_t (*write)(void *self, size_t len);
+// };
+#[repr(C)]
+struct CStreamVTable {
+	read: extern "C" fn(*mut c_void, usize) -> usize,
+	destroy: unsafe extern "C" fn(*mut c_void),
+	kind: u32,
+	layout: MemoryLayout,
+	write: extern "C" fn(*mut c_void, usize) -> usize,
+}
+
+#[dyntable(drop_slot = 1, layout_slot = 3)]
+trait Stream {
+	#[dyntable(slot = 4)]
+	extern "C" fn write(&mut self, len: usize) -> usize;
+
+	#[dyntable(field = read)]
+	extern "C" fn read_bytes(&mut self, len: usize) -> usize;
+
+	#[dyntable(field = kind)]
+	const KIND: u32;
+}
+
+#[test]
+fn layout() {
+	assert_eq!(size_of::<CStreamVTable>(), size_of::<StreamVTable>());
+	assert_eq!(
+		offset_of!(CStreamVTable, read),
+		offset_of!(StreamVTable, read)
+	);
+	assert_eq!(
+		offset_of!(CStreamVTable, destroy),
+		offset_of!(StreamVTable, __drop)
+	);
+	assert_eq!(
+		offset_of!(CStreamVTable, kind),
+		offset_of!(StreamVTable, kind)
+	);
+	assert_eq!(
+		offset_of!(CStreamVTable, layout),
+		offset_of!(StreamVTable, __layout)
+	);
+	assert_eq!(
+		offset_of!(CStreamVTable, write),
+		offset_of!(StreamVTable, write)
+	);
+}
+
+struct Buffer {
+	len: usize,
+}
+
+impl StreamConsts for Buffer {
+	const KIND: u32 = 7;
+}
+
+impl Stream for Buffer {
+	extern "C" fn write(&mut self, len: usize) -> usize {
+		self.len += len;
+		len
+	}
+
+	extern "C" fn read_bytes(&mut self, len: usize) -> usize {
+		let len = len.min(self.len);
+		self.len -= len;
+		len
+	}
+}
+
+#[test]
+fn rust_implementation() {
+	let mut stream = DynBox::<dyn Stream>::new(Buffer { len: 0 });
+	assert_eq!(stream.write(5), 5);
+	assert_eq!(stream.read_bytes(3), 3);
+	assert_eq!(stream.read_bytes(3), 2);
+	assert_eq!(DynBox::vtable(&stream).kind, 7);
+}
+
+#[test]
+fn foreign_implementation() {
+	extern "C" fn read(_: *mut c_void, len: usize) -> usize {
+		len / 2
+	}
+
+	unsafe extern "C" fn destroy(_: *mut c_void) {}
+
+	extern "C" fn write(this: *mut c_void, len: usize) -> usize {
+		unsafe { *(this as *mut usize) += len };
+		len
+	}
+
+	let vtable = CStreamVTable {
+		read,
+		destroy,
+		kind: 3,
+		layout: MemoryLayout::new::<usize>(),
+		write,
+	};
+
+	let mut written = 0usize;
+	let mut stream = unsafe {
+		DynRefMut::<dyn Stream>::from_raw(DynPtr {
+			ptr: &mut written as *mut usize as *mut c_void,
+			vtable: NonNull::from(&vtable).cast::<StreamVTable>(),
+		})
+	};
+
+	assert_eq!(stream.read_bytes(4), 2);
+	assert_eq!(stream.write(4), 4);
+	assert_eq!(DynRefMut::vtable(&stream).kind, 3);
+	assert_eq!(written, 4);
+}
@@ -0,0 +1,190 @@
+use std::{
+	ffi::c_void,
+	ptr::NonNull,
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+use dyntable::{
+	alloc::{AllocError, Allocator, Deallocator, Free, GlobalAllocator, MemoryLayout},
+	boxed::NoDropBox,
+	*,
+};
+
+extern "C" {
+	fn malloc(size: usize) -> *mut c_void;
+	fn free(ptr: *mut c_void);
+}
+
+#[dyntable(embed_layout = false)]
+trait Counter {
+	extern "C" fn get(&self) -> u32;
+	extern "C" fn increment(&mut self);
+}
+
+#[repr(C)]
+struct Tracked {
+	value: u32,
+	drops: &'static AtomicU32,
+}
+
+impl Drop for Tracked {
+	fn drop(&mut self) {
+		self.drops.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+impl Counter for Tracked {
+	extern "C" fn get(&self) -> u32 {
+		self.value
+	}
+
+	extern "C" fn increment(&mut self) {
+		self.value += 1;
+	}
+}
+
+static FREES: AtomicU32 = AtomicU32::new(0);
+
+unsafe extern "C" fn counted_free(ptr: *mut c_void) {
+	free(ptr);
+	FREES.fetch_add(1, Ordering::Relaxed);
+}
+
+#[test]
+fn free_box() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let ptr = unsafe {
+		let ptr = malloc(size_of::<Tracked>()) as *mut Tracked;
+		ptr.write(Tracked {
+			value: 1,
+			drops: &DROPS,
+		});
+		DynPtr::<dyn Counter>::new(ptr)
+	};
+
+	let dealloc: unsafe extern "C" fn(*mut c_void) = counted_free;
+	let mut counter: DynBox<dyn Counter, _> = unsafe { DynBox::from_raw_in(ptr, Free(dealloc)) };
+	counter.increment();
+	assert_eq!(counter.get(), 2);
+
+	drop(counter);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+	assert_eq!(FREES.load(Ordering::Relaxed), 1);
+}
+
+#[dyntable(drop = none)]
+trait Point {
+	extern "C" fn x(&self) -> i32;
+	extern "C" fn set_x(&mut self, x: i32);
+}
+
+#[dyntable(drop = none)]
+trait Point3: Point
+where
+	dyn Point:,
+{
+	extern "C" fn z(&self) -> i32;
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Vec3 {
+	x: i32,
+	y: i32,
+	z: i32,
+}
+
+impl Point for Vec3 {
+	extern "C" fn x(&self) -> i32 {
+		self.x
+	}
+
+	extern "C" fn set_x(&mut self, x: i32) {
+		self.x = x;
+	}
+}
+
+impl Point3 for Vec3 {
+	extern "C" fn z(&self) -> i32 {
+		self.z
+	}
+}
+
+#[test]
+fn no_drop_box() {
+	let mut point: NoDropBox<dyn Point3> = NoDropBox::new(Vec3 { x: 1, y: 2, z: 3 });
+	point.set_x(4);
+	assert_eq!(point.x(), 4);
+	assert_eq!(point.z(), 3);
+	NoDropBox::borrow_mut(&mut point).set_x(5);
+
+	let point: NoDropBox<dyn Point> = NoDropBox::upcast(point);
+	assert_eq!(NoDropBox::borrow(&point).x(), 5);
+
+	let ptr = NoDropBox::into_raw(point);
+	let point: NoDropBox<dyn Point, GlobalAllocator> = unsafe { NoDropBox::from_raw(ptr) };
+	assert_eq!(point.x(), 5);
+}
+
+#[dyntable(drop = none, embed_layout = false)]
+trait Compact {
+	extern "C" fn get(&self) -> u32;
+}
+
+impl Compact for u32 {
+	extern "C" fn get(&self) -> u32 {
+		*self
+	}
+}
+
+#[test]
+fn no_drop_free_box() {
+	let ptr = unsafe {
+		let ptr = malloc(size_of::<u32>()) as *mut u32;
+		ptr.write(7);
+		DynPtr::<dyn Compact>::new(ptr)
+	};
+
+	let dealloc: unsafe extern "C" fn(*mut c_void) = free;
+	let value: NoDropBox<dyn Compact, _> = unsafe { NoDropBox::from_raw_in(ptr, Free(dealloc)) };
+	assert_eq!(value.get(), 7);
+}
+
+/// An allocator counting every call, which allocates even zero sized blocks.
+#[derive(Copy, Clone)]
+struct Counting(&'static AtomicU32);
+
+impl Deallocator for Counting {
+	unsafe fn deallocate(&self, ptr: NonNull<u8>, _: MemoryLayout) {
+		self.0.fetch_add(1, Ordering::Relaxed);
+		free(ptr.as_ptr() as *mut c_void);
+	}
+}
+
+impl Allocator for Counting {
+	fn allocate(&self, layout: MemoryLayout) -> Result<NonNull<[u8]>, AllocError> {
+		self.0.fetch_add(1, Ordering::Relaxed);
+		let ptr = unsafe { malloc(layout.size.max(1)) } as *mut u8;
+		NonNull::new(std::ptr::slice_from_raw_parts_mut(ptr, layout.size)).ok_or(AllocError)
+	}
+}
+
+impl Point for () {
+	extern "C" fn x(&self) -> i32 {
+		0
+	}
+
+	extern "C" fn set_x(&mut self, _: i32) {}
+}
+
+#[test]
+fn zero_sized() {
+	static CALLS: AtomicU32 = AtomicU32::new(0);
+
+	// zero sized values are never allocated, as they are never deallocated
+	let point: NoDropBox<dyn Point, _> = NoDropBox::new_in((), Counting(&CALLS));
+	assert_eq!(point.x(), 0);
+	drop(point);
+	assert_eq!(CALLS.load(Ordering::Relaxed), 0);
+}
@@ -0,0 +1,101 @@
+use std::ptr::NonNull;
+
+use dyntable::*;
+
+// Two versions of the same trait, as seen by a plugin built against the
+// first version and a host built against the second.
+mod v1 {
+	use dyntable::*;
+
+	#[dyntable(version = 1, vtable = PluginVTable)]
+	pub trait Plugin {
+		const FLAGS: u8;
+
+		extern "C" fn id(&self) -> u32;
+	}
+}
+
+#[dyntable(version = 2)]
+trait Plugin {
+	const FLAGS: u8;
+
+	extern "C" fn id(&self) -> u32;
+
+	// fits in the trailing padding of the first version
+	const LEVEL: u8;
+
+	extern "C" fn name(&self) -> u32;
+}
+
+struct OldPlugin;
+
+impl v1::PluginConsts for OldPlugin {
+	const FLAGS: u8 = 3;
+}
+
+impl v1::Plugin for OldPlugin {
+	extern "C" fn id(&self) -> u32 {
+		1
+	}
+}
+
+struct NewPlugin;
+
+impl PluginConsts for NewPlugin {
+	const FLAGS: u8 = 5;
+	const LEVEL: u8 = 2;
+}
+
+impl Plugin for NewPlugin {
+	extern "C" fn id(&self) -> u32 {
+		2
+	}
+
+	extern "C" fn name(&self) -> u32 {
+		42
+	}
+}
+
+#[test]
+fn header() {
+	let plugin = DynBox::<dyn Plugin>::new(NewPlugin);
+	let header = DynBox::header(&plugin);
+
+	assert_eq!(header, <PluginVTable as AssociatedHeader>::HEADER);
+	assert_eq!(header.version, 2);
+	assert!(DynBox::has_entry(&plugin, entry_end!(PluginVTable, name)));
+	assert!(DynBox::has_entry(&plugin, entry_end!(PluginVTable, LEVEL)));
+	assert_eq!(plugin.name(), 42);
+}
+
+#[test]
+fn send_sync() {
+	let plugin = DynBox::<dyn Plugin + Send>::new(NewPlugin);
+	assert_eq!(DynBox::header(&plugin).version, 2);
+	assert!(DynBox::has_entry(&plugin, entry_end!(PluginVTable, name)));
+
+	let plugin = DynBox::<dyn Plugin + Sync>::new(NewPlugin);
+	assert_eq!(DynRef::header(&DynBox::borrow(&plugin)).version, 2);
+
+	let plugin = DynBox::<dyn Plugin + Send + Sync>::new(NewPlugin);
+	assert!(DynRef::has_entry(
+		&DynBox::borrow(&plugin),
+		entry_end!(PluginVTable, LEVEL)
+	));
+}
+
+#[test]
+fn older_vtable() {
+	let mut old = OldPlugin;
+	let ptr = DynPtr::<dyn Plugin> {
+		ptr: &mut old as *mut OldPlugin as *mut _,
+		vtable: NonNull::from(<OldPlugin as DynTrait<v1::PluginVTable>>::STATIC_VTABLE).cast(),
+	};
+
+	let plugin = unsafe { DynRef::from_raw(ptr) };
+	assert_eq!(DynRef::header(&plugin).version, 1);
+	assert!(DynRef::has_entry(&plugin, entry_end!(PluginVTable, id)));
+	assert!(!DynRef::has_entry(&plugin, entry_end!(PluginVTable, LEVEL)));
+	assert!(!DynRef::has_entry(&plugin, entry_end!(PluginVTable, name)));
+	assert_eq!(plugin.id(), 1);
+}
@@ -0,0 +1,130 @@
+use std::{
+	ffi::c_void,
+	mem,
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+use common::{tracked, Aligned, Tracked};
+use dyntable::{dyntable, inline::DynInline, DynRef};
+
+mod common;
+
+#[dyntable]
+trait Named {
+	extern "C" fn name(&self) -> u32;
+}
+
+#[dyntable]
+trait Value: Named
+where
+	dyn Named:,
+{
+	extern "C" fn get(&self) -> u32;
+	extern "C" fn set(&mut self, value: u32);
+	extern "C" fn take(self) -> u32;
+}
+
+impl Named for Tracked {
+	extern "C" fn name(&self) -> u32 {
+		1
+	}
+}
+
+impl Value for Tracked {
+	extern "C" fn get(&self) -> u32 {
+		self.value
+	}
+
+	extern "C" fn set(&mut self, value: u32) {
+		self.value = value;
+	}
+
+	extern "C" fn take(self) -> u32 {
+		self.value
+	}
+}
+
+impl Named for Aligned {
+	extern "C" fn name(&self) -> u32 {
+		self.0
+	}
+}
+
+struct Large([u32; 8]);
+
+impl Named for Large {
+	extern "C" fn name(&self) -> u32 {
+		self.0.iter().sum()
+	}
+}
+
+type Inline<V> = DynInline<V, 16>;
+
+#[test]
+fn layout() {
+	assert_eq!(
+		mem::size_of::<Inline<dyn Value>>(),
+		mem::size_of::<*const c_void>() + 16
+	);
+	assert_eq!(
+		mem::size_of::<Option<Inline<dyn Value>>>(),
+		mem::size_of::<Inline<dyn Value>>()
+	);
+}
+
+#[test]
+fn calls() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut inline = Inline::<dyn Value>::new(tracked(42, &DROPS));
+	assert_eq!(inline.name(), 1);
+	inline.set(inline.get() + 1);
+
+	{
+		let mut r = Inline::borrow_mut(&mut inline);
+		let value = r.get();
+		r.set(value + 1);
+	}
+
+	// moving the container moves the value along with it
+	let inline = [inline];
+	let [inline] = inline;
+	assert_eq!(Inline::borrow(&inline).get(), 44);
+	assert_eq!(inline.take(), 44);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+	drop(Inline::<dyn Value>::new(tracked(0, &DROPS)));
+	assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn upcast() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let inline = Inline::<dyn Value>::new(tracked(1, &DROPS));
+	assert_eq!(
+		DynRef::upcast::<dyn Named>(Inline::borrow(&inline)).name(),
+		1
+	);
+
+	let named: Inline<dyn Named> = Inline::upcast(inline);
+	assert_eq!(named.name(), 1);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+	drop(named);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn capacity() {
+	assert!(Inline::<dyn Named>::try_new(Large([1; 8])).is_err());
+	assert!(Inline::<dyn Named>::try_new(Aligned(1)).is_err());
+
+	let large = DynInline::<dyn Named, 32>::try_new(Large([1; 8]))
+		.ok()
+		.unwrap();
+	assert_eq!(large.name(), 8);
+
+	let aligned = DynInline::<dyn Named, 64, Aligned>::new(Aligned(2));
+	assert_eq!(aligned.name(), 2);
+	assert_eq!(&aligned as *const _ as usize % 64, 0);
+}
@@ -0,0 +1,88 @@
+#include <cstddef>
+#include <cstdint>
+#include <typeinfo>
+
+// The C++ classes implemented by the traits in itanium.rs
+
+class Shape {
+public:
+	virtual ~Shape() {}
+	virtual double area() const = 0;
+	virtual void scale(double factor) = 0;
+};
+
+class Tagged: public Shape {
+public:
+	virtual int32_t tag() const = 0;
+};
+
+// A C++ implementation of Tagged
+
+static int32_t alive = 0;
+static int32_t deletes = 0;
+
+class CppSquare: public Tagged {
+	double side;
+
+public:
+	explicit CppSquare(double side): side(side) {
+		alive += 1;
+	}
+
+	~CppSquare() override {
+		alive -= 1;
+	}
+
+	double area() const override {
+		return this->side * this->side;
+	}
+
+	void scale(double factor) override {
+		this->side *= factor;
+	}
+
+	int32_t tag() const override {
+		return 7;
+	}
+
+	// counts deallocations through the deleting destructor
+	static void operator delete(void* ptr) {
+		deletes += 1;
+		::operator delete(ptr);
+	}
+};
+
+extern "C" {
+
+int32_t cpp_alive() {
+	return alive;
+}
+
+int32_t cpp_deletes() {
+	return deletes;
+}
+
+Tagged* cpp_new_square(double side) {
+	return new CppSquare(side);
+}
+
+const void* cpp_square_type_info() {
+	return &typeid(CppSquare);
+}
+
+// Client code, usable with both Rust and C++ objects.
+
+double cpp_use_shape(Shape* shape) {
+	shape->scale(2.0);
+	return shape->area();
+}
+
+int32_t cpp_use_tagged(const Tagged* tagged) {
+	return tagged->tag() * 100 + static_cast<int32_t>(tagged->area());
+}
+
+void cpp_delete_shape(Shape* shape) {
+	delete shape;
+}
+
+}
@@ -0,0 +1,191 @@
+#![cfg(not(miri))]
+
+use std::{
+	env,
+	ffi::c_void,
+	fs,
+	mem::{offset_of, size_of},
+	process::{Command, Stdio},
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+use dyntable::{itanium::*, *};
+
+#[dyntable(itanium = true)]
+trait Shape: Polymorphic
+where
+	dyn Polymorphic:,
+{
+	extern "C" fn area(&self) -> f64;
+	extern "C" fn scale(&mut self, factor: f64);
+}
+
+#[dyntable(itanium = true)]
+trait Tagged: Shape
+where
+	dyn Shape: Polymorphic,
+{
+	extern "C" fn tag(&self) -> i32;
+}
+
+struct RustRect {
+	width: f64,
+	height: f64,
+	drops: &'static AtomicU32,
+}
+
+impl Shape for RustRect {
+	extern "C" fn area(&self) -> f64 {
+		self.width * self.height
+	}
+
+	extern "C" fn scale(&mut self, factor: f64) {
+		self.width *= factor;
+		self.height *= factor;
+	}
+}
+
+impl Tagged for RustRect {
+	extern "C" fn tag(&self) -> i32 {
+		3
+	}
+}
+
+impl Drop for RustRect {
+	fn drop(&mut self) {
+		self.drops.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+#[test]
+fn itanium_layout() {
+	let ptr = size_of::<*const c_void>();
+
+	// the destructor pair comes first, followed by each class's methods
+	assert_eq!(size_of::<PolymorphicVTable>(), ptr * 2);
+	assert_eq!(offset_of!(PolymorphicVTable, complete_dtor), 0);
+	assert_eq!(offset_of!(PolymorphicVTable, deleting_dtor), ptr);
+	assert_eq!(size_of::<ShapeVTable>(), ptr * 4);
+	assert_eq!(offset_of!(ShapeVTable, area), ptr * 2);
+	assert_eq!(offset_of!(ShapeVTable, scale), ptr * 3);
+	assert_eq!(size_of::<TaggedVTable>(), ptr * 5);
+	assert_eq!(offset_of!(TaggedVTable, tag), ptr * 4);
+
+	// offset-to-top and RTTI are stored before the VTable
+	assert_eq!(size_of::<VTablePrefix>(), ptr * 2);
+	assert_eq!(offset_of!(PrefixedVTable<TaggedVTable>, vtable), ptr * 2);
+
+	assert_eq!(size_of::<CppBox<dyn Tagged>>(), ptr);
+	assert_eq!(size_of::<Option<CppBox<dyn Tagged>>>(), ptr);
+}
+
+#[test]
+fn itanium_rust() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut rect = CppBox::<dyn Tagged>::new(RustRect {
+		width: 2.0,
+		height: 3.0,
+		drops: &DROPS,
+	});
+
+	assert_eq!(rect.tag(), 3);
+	rect.scale(2.0);
+	assert_eq!(rect.area(), 24.0);
+
+	let prefix = CppBox::prefix(&rect);
+	assert_eq!(prefix.offset_to_top, 0);
+	assert!(prefix.type_info.is_null());
+
+	let shape = CppBox::upcast::<dyn Shape>(rect);
+	assert_eq!(shape.area(), 24.0);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+	drop(shape);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn itanium_ffi() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	fs::create_dir_all("target/itaniumtest").unwrap();
+
+	let status = Command::new(env::var("CXX").expect("Missing CXX environment var"))
+		.args([
+			"-Werror",
+			"-std=c++11",
+			"-shared",
+			"-fPIC",
+			"-o",
+			"target/itaniumtest/libitanium.so",
+			"tests/itanium.cpp",
+		])
+		.stdout(Stdio::inherit())
+		.stderr(Stdio::inherit())
+		.status()
+		.unwrap();
+
+	assert!(status.success());
+
+	unsafe {
+		let lib = libloading::Library::new("./target/itaniumtest/libitanium.so").unwrap();
+
+		let cpp_alive = lib
+			.get::<unsafe extern "C" fn() -> i32>(b"cpp_alive")
+			.unwrap();
+		let cpp_deletes = lib
+			.get::<unsafe extern "C" fn() -> i32>(b"cpp_deletes")
+			.unwrap();
+		let cpp_new_square = lib
+			.get::<unsafe extern "C" fn(f64) -> *mut c_void>(b"cpp_new_square")
+			.unwrap();
+		let cpp_square_type_info = lib
+			.get::<unsafe extern "C" fn() -> *const c_void>(b"cpp_square_type_info")
+			.unwrap();
+		let cpp_use_shape = lib
+			.get::<unsafe extern "C" fn(*mut c_void) -> f64>(b"cpp_use_shape")
+			.unwrap();
+		let cpp_use_tagged = lib
+			.get::<unsafe extern "C" fn(*mut c_void) -> i32>(b"cpp_use_tagged")
+			.unwrap();
+		let cpp_delete_shape = lib
+			.get::<unsafe extern "C" fn(*mut c_void)>(b"cpp_delete_shape")
+			.unwrap();
+
+		// Rust object used from C++
+		let rect = CppBox::<dyn Tagged>::new(RustRect {
+			width: 1.0,
+			height: 2.0,
+			drops: &DROPS,
+		});
+		assert_eq!(cpp_use_shape(CppBox::as_raw(&rect)), 8.0);
+		assert_eq!(cpp_use_tagged(CppBox::as_raw(&rect)), 308);
+		assert_eq!(rect.area(), 8.0);
+
+		// `delete` calls the deleting destructor of the Rust object
+		cpp_delete_shape(CppBox::into_raw(rect));
+		assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+		// C++ object used from Rust
+		let mut square = CppBox::<dyn Tagged>::from_raw(cpp_new_square(3.0));
+		assert_eq!(cpp_alive(), 1);
+		assert_eq!(square.tag(), 7);
+		assert_eq!(square.area(), 9.0);
+		square.scale(2.0);
+		assert_eq!(square.area(), 36.0);
+
+		let prefix = CppBox::prefix(&square);
+		assert_eq!(prefix.offset_to_top, 0);
+		assert_eq!(prefix.type_info, cpp_square_type_info());
+
+		// the C++ client works with C++ objects as well
+		assert_eq!(cpp_use_shape(CppBox::as_raw(&square)), 144.0);
+		assert_eq!(cpp_use_tagged(CppBox::as_raw(&square)), 844);
+
+		// dropping the box deletes the object through its deleting destructor
+		let shape = CppBox::upcast::<dyn Shape>(square);
+		drop(shape);
+		assert_eq!(cpp_alive(), 0);
+		assert_eq!(cpp_deletes(), 1);
+	}
+}
@@ -0,0 +1,90 @@
+// rustc's FFI lints do not consider the niche of `#[repr(C)]` structs.
+#![allow(improper_ctypes_definitions)]
+
+use std::{
+	ffi::c_void,
+	mem::size_of,
+	ptr::{self, NonNull},
+};
+
+use dyntable::{cheader::CHeader, *};
+
+#[dyntable(c_header = true)]
+trait Value {
+	extern "C" fn get(&self) -> i32;
+}
+
+#[dyntable(c_header = true)]
+trait Factory {
+	extern "C" fn create(&self, value: i32) -> Option<DynBox<dyn Value>>;
+}
+
+impl Value for i32 {
+	extern "C" fn get(&self) -> i32 {
+		*self
+	}
+}
+
+#[test]
+fn layout() {
+	assert_eq!(
+		size_of::<Option<DynPtr<dyn Value>>>(),
+		size_of::<DynPtr<dyn Value>>()
+	);
+	assert_eq!(
+		size_of::<Option<DynRef<dyn Value>>>(),
+		size_of::<DynPtr<dyn Value>>()
+	);
+	assert_eq!(
+		size_of::<Option<DynRefMut<dyn Value>>>(),
+		size_of::<DynPtr<dyn Value>>()
+	);
+	assert_eq!(
+		size_of::<Option<DynBox<dyn Value>>>(),
+		size_of::<DynPtr<dyn Value>>()
+	);
+
+	// `None` is a null vtable
+	let none: [*const c_void; 2] = unsafe { std::mem::transmute(None::<DynPtr<dyn Value>>) };
+	assert!(none[1].is_null());
+
+	let null: Option<DynBox<dyn Value>> =
+		unsafe { std::mem::transmute([ptr::null::<c_void>(), ptr::null()]) };
+	assert!(null.is_none());
+}
+
+#[test]
+fn conversions() {
+	let mut value = 5i32;
+	let ptr = DynPtr::<dyn Value>::new(&mut value);
+
+	let (data, vtable) = DynPtr::into_nullable(Some(ptr));
+	assert_eq!(data, ptr.ptr);
+	assert_eq!(vtable, ptr.vtable.as_ptr() as *const _);
+
+	let ptr = DynPtr::<dyn Value>::from_nullable(data, vtable).unwrap();
+	assert_eq!(unsafe { DynRef::from_raw(ptr) }.get(), 5);
+
+	assert!(DynPtr::<dyn Value>::from_nullable(ptr::null_mut(), ptr::null()).is_none());
+	assert!(DynPtr::<dyn Value>::from_nullable(data, ptr::null()).is_none());
+	assert_eq!(
+		DynPtr::<dyn Value>::into_nullable(None),
+		(ptr::null_mut(), ptr::null())
+	);
+
+	// zero sized values may use a null data pointer
+	let ptr = DynPtr::<dyn Value> {
+		ptr: ptr::null_mut(),
+		vtable: NonNull::from(<i32 as DynTrait<ValueVTable>>::STATIC_VTABLE),
+	};
+	assert!(DynPtr::into_nullable(Some(ptr)).0.is_null());
+}
+
+#[test]
+fn cheader() {
+	let header = CHeader::new().add::<dyn Factory>().generate("FACTORY_H");
+	assert!(
+		header.contains("ValueDynPtr (*create)(void *self, int32_t value);"),
+		"{header}"
+	);
+}
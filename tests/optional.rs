@@ -0,0 +1,105 @@
+use std::{ffi::c_void, ptr::NonNull};
+
+use dyntable::*;
+
+#[dyntable]
+trait Plugin {
+	extern "C" fn id(&self) -> u32;
+
+	/// Added in a later version, with a fallback for older plugins.
+	#[dyntable(optional)]
+	extern "C" fn priority(&self) -> u32 {
+		self.id() * 10
+	}
+
+	/// Added in a later version, doing nothing for older plugins.
+	#[dyntable(optional)]
+	extern "C" fn reset(&mut self, value: u32) {
+		let _ = value;
+	}
+
+	#[dyntable(optional)]
+	extern "C" fn version() -> u32 {
+		1
+	}
+}
+
+#[repr(C)]
+struct Modern(u32);
+
+impl Plugin for Modern {
+	extern "C" fn id(&self) -> u32 {
+		self.0
+	}
+
+	extern "C" fn priority(&self) -> u32 {
+		1
+	}
+
+	extern "C" fn reset(&mut self, value: u32) {
+		self.0 = value;
+	}
+
+	extern "C" fn version() -> u32 {
+		2
+	}
+}
+
+#[repr(C)]
+struct Basic(u32);
+
+impl Plugin for Basic {
+	extern "C" fn id(&self) -> u32 {
+		self.0
+	}
+}
+
+#[test]
+fn rust_implementations() {
+	let mut modern = DynBox::<dyn Plugin>::new(Modern(3));
+	assert_eq!(modern.priority(), 1);
+	assert_eq!(modern.try_priority(), Some(1));
+	assert_eq!(modern.try_reset(4), Some(()));
+	assert_eq!(modern.id(), 4);
+	assert_eq!(DynBox::vtable(&modern).version(), Some(2));
+
+	// the default implementation is used when a method is left out
+	let mut basic = DynBox::<dyn Plugin>::new(Basic(3));
+	assert_eq!(basic.priority(), 30);
+	assert_eq!(basic.try_priority(), Some(30));
+	assert!(DynBox::vtable(&basic).priority.is_some());
+	assert_eq!(basic.try_reset(4), Some(()));
+	assert_eq!(basic.id(), 3);
+	assert_eq!(DynBox::vtable(&basic).version(), Some(1));
+
+	// `try_` methods are also callable directly
+	assert_eq!(Basic(5).try_priority(), Some(50));
+}
+
+#[test]
+fn missing_entries() {
+	let mut value = Basic(3);
+
+	// simulate a foreign implementation written against an older version
+	let mut vtable = <Basic as DynTrait<PluginVTable>>::VTABLE;
+	vtable.priority = None;
+	vtable.reset = None;
+	vtable.version = None;
+
+	let ptr = DynPtr::<dyn Plugin> {
+		ptr: &mut value as *mut Basic as *mut c_void,
+		vtable: NonNull::from(&vtable),
+	};
+
+	let plugin = unsafe { DynRef::from_raw(ptr) };
+	assert_eq!(plugin.try_priority(), None);
+	// falls back to the trait's default implementation
+	assert_eq!(plugin.priority(), 30);
+	assert_eq!(DynRef::vtable(&plugin).version(), None);
+
+	let mut plugin = unsafe { DynRefMut::from_raw(ptr) };
+	assert_eq!(plugin.try_reset(4), None);
+	// the default implementation is called on the container
+	plugin.reset(4);
+	assert_eq!(plugin.id(), 3);
+}
@@ -0,0 +1,156 @@
+use std::{
+	ffi::c_void,
+	mem::{offset_of, size_of},
+	ptr::NonNull,
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+use dyntable::{alloc::MemoryLayout, owned::DynOwned, *};
+
+#[dyntable(dealloc = "C")]
+trait Base {
+	extern "C" fn get(&self) -> u32;
+}
+
+#[dyntable(dealloc = "C")]
+trait Value: Base
+where
+	dyn Base:,
+{
+	extern "C" fn set(&mut self, value: u32);
+	extern "C" fn take(self) -> u32;
+}
+
+#[repr(C)]
+struct Tracked {
+	value: u32,
+	drops: &'static AtomicU32,
+}
+
+impl Drop for Tracked {
+	fn drop(&mut self) {
+		self.drops.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+impl Base for Tracked {
+	extern "C" fn get(&self) -> u32 {
+		self.value
+	}
+}
+
+impl Value for Tracked {
+	extern "C" fn set(&mut self, value: u32) {
+		self.value = value;
+	}
+
+	extern "C" fn take(self) -> u32 {
+		self.value
+	}
+}
+
+struct Empty;
+
+impl Base for Empty {
+	extern "C" fn get(&self) -> u32 {
+		0
+	}
+}
+
+#[test]
+fn owned() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut value = DynOwned::<dyn Value>::new(Tracked {
+		value: 1,
+		drops: &DROPS,
+	});
+	value.set(value.get() + 1);
+	assert_eq!(DynOwned::borrow(&value).get(), 2);
+	DynOwned::borrow_mut(&mut value).set(3);
+
+	// the value is moved out, and only deallocated
+	assert_eq!(value.take(), 3);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+	let value = DynOwned::<dyn Value>::new(Tracked {
+		value: 4,
+		drops: &DROPS,
+	});
+	let base: DynOwned<dyn Base> = DynOwned::upcast(value);
+	assert_eq!(base.get(), 4);
+	drop(base);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+
+	let value = DynOwned::<dyn Base + Send>::new(Tracked {
+		value: 5,
+		drops: &DROPS,
+	});
+	std::thread::spawn(move || drop(value)).join().unwrap();
+	assert_eq!(DROPS.load(Ordering::Relaxed), 3);
+
+	let empty = DynOwned::<dyn Base>::new(Empty);
+	assert_eq!(empty.get(), 0);
+}
+
+// A VTable defined by a C library, which frees its own objects:
+//
+// struct foreign_vtable {
+//     void (*dealloc)(void *self);
+//     void (*drop)(void *self);
+//     struct { size_t size; size_t align; } layout;
+//     uint32_t (*get)(void const *self);
+// };
+#[repr(C)]
+struct CForeignVTable {
+	dealloc: extern "C" fn(*mut c_void),
+	drop: extern "C" fn(*mut c_void),
+	layout: MemoryLayout,
+	get: extern "C" fn(*const c_void) -> u32,
+}
+
+#[dyntable(dealloc = "C", dealloc_slot = 0)]
+trait Foreign {
+	extern "C" fn get(&self) -> u32;
+}
+
+static FOREIGN_DROPS: AtomicU32 = AtomicU32::new(0);
+static FOREIGN_DEALLOCS: AtomicU32 = AtomicU32::new(0);
+
+extern "C" fn foreign_dealloc(ptr: *mut c_void) {
+	drop(unsafe { Box::from_raw(ptr as *mut u32) });
+	FOREIGN_DEALLOCS.fetch_add(1, Ordering::Relaxed);
+}
+
+extern "C" fn foreign_drop(_: *mut c_void) {
+	FOREIGN_DROPS.fetch_add(1, Ordering::Relaxed);
+}
+
+extern "C" fn foreign_get(ptr: *const c_void) -> u32 {
+	unsafe { *(ptr as *const u32) }
+}
+
+static FOREIGN_VTABLE: CForeignVTable = CForeignVTable {
+	dealloc: foreign_dealloc,
+	drop: foreign_drop,
+	layout: MemoryLayout::new::<u32>(),
+	get: foreign_get,
+};
+
+#[test]
+fn foreign() {
+	assert_eq!(size_of::<CForeignVTable>(), size_of::<ForeignVTable>());
+	assert_eq!(offset_of!(ForeignVTable, __dealloc), 0);
+
+	let ptr = DynPtr::<dyn Foreign> {
+		ptr: Box::into_raw(Box::new(5u32)) as *mut c_void,
+		vtable: NonNull::from(&FOREIGN_VTABLE).cast(),
+	};
+
+	let value = unsafe { DynOwned::from_raw(ptr) };
+	assert_eq!(value.get(), 5);
+	drop(value);
+
+	assert_eq!(FOREIGN_DROPS.load(Ordering::Relaxed), 1);
+	assert_eq!(FOREIGN_DEALLOCS.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,71 @@
+use core::{marker::PhantomPinned, pin::Pin, ptr};
+
+use dyntable::*;
+
+#[dyntable]
+trait Generator {
+	extern "C" fn resume(self: Pin<&mut Self>) -> u32;
+
+	extern "C" fn peek(self: Pin<&Self>) -> u32;
+}
+
+/// Counts up, keeping a pointer to its own state after the first resume.
+#[repr(C)]
+struct SelfRef {
+	value: u32,
+	ptr: *const u32,
+	_pinned: PhantomPinned,
+}
+
+impl SelfRef {
+	fn new() -> Self {
+		Self {
+			value: 0,
+			ptr: ptr::null(),
+			_pinned: PhantomPinned,
+		}
+	}
+}
+
+impl Generator for SelfRef {
+	extern "C" fn resume(self: Pin<&mut Self>) -> u32 {
+		let this = unsafe { self.get_unchecked_mut() };
+
+		if this.ptr.is_null() {
+			this.ptr = &this.value;
+		}
+
+		// the value has not moved since the first resume
+		assert!(ptr::eq(this.ptr, &this.value));
+		this.value += 1;
+		this.value
+	}
+
+	extern "C" fn peek(self: Pin<&Self>) -> u32 {
+		self.value
+	}
+}
+
+#[test]
+fn pinned_box() {
+	let mut generator: Pin<DynBox<dyn Generator>> = DynBox::pin(SelfRef::new());
+
+	assert_eq!(DynBox::as_pin_mut(&mut generator).as_mut().resume(), 1);
+	assert_eq!(DynBox::as_pin_mut(&mut generator).as_mut().resume(), 2);
+	assert_eq!(generator.as_ref().peek(), 2);
+	assert_eq!(DynBox::as_pin_ref(&generator).as_ref().peek(), 2);
+
+	// moving the box does not move the pinned value
+	let mut moved = generator;
+	assert_eq!(DynBox::as_pin_mut(&mut moved).as_mut().resume(), 3);
+}
+
+#[test]
+fn pinned_projection() {
+	let mut generator = DynBox::into_pin(DynBox::<dyn Generator>::new(SelfRef::new()));
+	let mut projection: Pin<DynRefMut<dyn Generator>> = DynBox::as_pin_mut(&mut generator);
+
+	assert_eq!(projection.as_mut().resume(), 1);
+	assert_eq!(projection.as_mut().resume(), 2);
+	assert_eq!(projection.as_ref().peek(), 2);
+}
@@ -0,0 +1,54 @@
+#include <stdlib.h>
+#include <stddef.h>
+
+// generated by the rc test
+#include "rc.h"
+
+static unsigned int frees = 0;
+static ValueDynPtr held;
+
+void *c_alloc(size_t size, size_t align) {
+	return aligned_alloc(align, (size + align - 1) / align * align);
+}
+
+void c_free(void *ptr) {
+	free(ptr);
+	frees += 1;
+}
+
+unsigned int c_frees() {
+	return frees;
+}
+
+// take a strong reference to a DynArc
+void c_retain(ValueDynPtr ptr) {
+	size_t *counts = (size_t *)ptr.ptr - 2;
+	__atomic_fetch_add(&counts[0], 1, __ATOMIC_RELAXED);
+	held = ptr;
+}
+
+int32_t c_get() {
+	return held.vtable->get(held.ptr);
+}
+
+// release the reference taken by c_retain, dropping and freeing the
+// value if it was the last one
+void c_release() {
+	size_t *counts = (size_t *)held.ptr - 2;
+
+	if (__atomic_fetch_sub(&counts[0], 1, __ATOMIC_RELEASE) != 1) {
+		return;
+	}
+
+	__atomic_thread_fence(__ATOMIC_ACQUIRE);
+	held.vtable->__drop(held.ptr);
+
+	if (__atomic_fetch_sub(&counts[1], 1, __ATOMIC_RELEASE) != 1) {
+		return;
+	}
+
+	__atomic_thread_fence(__ATOMIC_ACQUIRE);
+	size_t align = held.vtable->__layout.align;
+	size_t offset = 2 * sizeof(size_t) > align ? 2 * sizeof(size_t) : align;
+	c_free((char *)held.ptr - offset);
+}
@@ -0,0 +1,237 @@
+use std::{
+	env,
+	ffi::c_void,
+	fs,
+	mem::size_of,
+	process::{Command, Stdio},
+	ptr::NonNull,
+	sync::atomic::{AtomicU32, Ordering},
+	thread,
+};
+
+use common::{tracked, Aligned, Tracked};
+use dyntable::{
+	alloc::{AllocError, Allocator, Deallocator, MemoryLayout},
+	rc::*,
+	*,
+};
+
+mod common;
+
+#[dyntable(c_header = true)]
+trait Value {
+	extern "C" fn get(&self) -> i32;
+	extern "C" fn set(&mut self, value: i32);
+}
+
+#[dyntable]
+trait Counter: Value + Send + Sync
+where
+	dyn Value:,
+{
+	extern "C" fn count(&self) -> u32;
+}
+
+impl Value for Tracked {
+	extern "C" fn get(&self) -> i32 {
+		self.value as i32
+	}
+
+	extern "C" fn set(&mut self, value: i32) {
+		self.value = value as u32;
+	}
+}
+
+impl Counter for Tracked {
+	extern "C" fn count(&self) -> u32 {
+		self.drops.load(Ordering::Relaxed)
+	}
+}
+
+impl Value for Aligned {
+	extern "C" fn get(&self) -> i32 {
+		self.0 as i32
+	}
+
+	extern "C" fn set(&mut self, value: i32) {
+		self.0 = value as u32;
+	}
+}
+
+#[test]
+fn layout() {
+	let ptr = size_of::<DynPtr<dyn Value>>();
+	assert_eq!(size_of::<DynRc<dyn Value>>(), ptr);
+	assert_eq!(size_of::<DynArc<dyn Value>>(), ptr);
+	assert_eq!(size_of::<Option<DynArc<dyn Value>>>(), ptr);
+	assert_eq!(size_of::<DynArcWeak<dyn Value>>(), ptr);
+
+	let rc = DynRc::<dyn Value>::new(Aligned(1));
+	let raw = DynRc::as_raw(&rc);
+	assert_eq!(raw.ptr as usize % 64, 0);
+
+	let counts = unsafe { (raw.ptr as *const usize).sub(2) };
+	let weak = DynRc::downgrade(&rc);
+	let rc2 = rc.clone();
+	assert_eq!(unsafe { *counts }, 2);
+	assert_eq!(unsafe { *counts.add(1) }, 2);
+	drop((weak, rc2));
+}
+
+#[test]
+fn rc() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut rc = DynRc::<dyn Value>::new(tracked(1, &DROPS));
+	DynRc::get_mut(&mut rc).unwrap().set(2);
+
+	let rc2 = rc.clone();
+	assert!(DynRc::ptr_eq(&rc, &rc2));
+	assert_eq!(rc2.get(), 2);
+	assert_eq!(DynRc::strong_count(&rc), 2);
+	assert!(DynRc::get_mut(&mut rc).is_none());
+	drop(rc2);
+
+	let weak = DynRc::downgrade(&rc);
+	assert_eq!(DynRc::weak_count(&rc), 1);
+	assert!(DynRc::get_mut(&mut rc).is_none());
+	assert_eq!(weak.upgrade().unwrap().get(), 2);
+
+	drop(rc);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+	assert!(weak.upgrade().is_none());
+	assert_eq!(weak.strong_count(), 0);
+	assert_eq!(weak.weak_count(), 0);
+}
+
+#[test]
+fn arc() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut counter = DynArc::<dyn Counter>::new(tracked(3, &DROPS));
+	assert!(DynArc::get_mut(&mut counter).is_some());
+	// the weak count is unlocked after checking for other references
+	assert_eq!(DynArc::weak_count(&counter), 0);
+
+	let weak = DynArc::downgrade(&counter);
+	assert!(DynArc::get_mut(&mut counter).is_none());
+
+	let threads = (0..4)
+		.map(|_| {
+			let counter = counter.clone();
+			let weak = weak.clone();
+			thread::spawn(move || {
+				assert_eq!(counter.get(), 3);
+				assert_eq!(weak.upgrade().unwrap().count(), 0);
+			})
+		})
+		.collect::<Vec<_>>();
+
+	for thread in threads {
+		thread.join().unwrap();
+	}
+
+	assert_eq!(DynArc::strong_count(&counter), 1);
+	assert_eq!(DynArc::weak_count(&counter), 1);
+
+	let value = DynArc::upcast::<dyn Value>(counter);
+	assert_eq!(value.get(), 3);
+	drop(value);
+
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+	assert!(weak.upgrade().is_none());
+}
+
+#[derive(Copy, Clone)]
+struct CAllocator {
+	alloc: unsafe extern "C" fn(usize, usize) -> *mut c_void,
+	free: unsafe extern "C" fn(*mut c_void),
+}
+
+impl Deallocator for CAllocator {
+	unsafe fn deallocate(&self, ptr: NonNull<u8>, _: MemoryLayout) {
+		(self.free)(ptr.as_ptr() as *mut c_void)
+	}
+}
+
+impl Allocator for CAllocator {
+	fn allocate(&self, layout: MemoryLayout) -> Result<NonNull<[u8]>, AllocError> {
+		let ptr = unsafe { (self.alloc)(layout.size, layout.align) } as *mut u8;
+		let slice = std::ptr::slice_from_raw_parts_mut(ptr, layout.size);
+		NonNull::new(slice).ok_or(AllocError)
+	}
+}
+
+#[cfg(not(miri))]
+#[test]
+fn ffi() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	fs::create_dir_all("target/rctest").unwrap();
+
+	let header = cheader::CHeader::new().add::<dyn Value>().generate("RC_H");
+	fs::write("target/rctest/rc.h", header).unwrap();
+
+	let status = Command::new(env::var("CC").expect("Missing CC environment var"))
+		.args([
+			"-Werror",
+			"-Itarget/rctest",
+			"-shared",
+			"-fPIC",
+			"-o",
+			"target/rctest/librc.so",
+			"tests/rc.c",
+		])
+		.stdout(Stdio::inherit())
+		.stderr(Stdio::inherit())
+		.status()
+		.unwrap();
+
+	assert!(status.success());
+
+	unsafe {
+		let lib = libloading::Library::new("./target/rctest/librc.so").unwrap();
+
+		let alloc = CAllocator {
+			alloc: *lib
+				.get::<unsafe extern "C" fn(usize, usize) -> *mut c_void>(b"c_alloc")
+				.unwrap(),
+			free: *lib
+				.get::<unsafe extern "C" fn(*mut c_void)>(b"c_free")
+				.unwrap(),
+		};
+		let c_frees = lib
+			.get::<unsafe extern "C" fn() -> u32>(b"c_frees")
+			.unwrap();
+		let c_retain = lib
+			.get::<unsafe extern "C" fn(DynPtr<dyn Value>)>(b"c_retain")
+			.unwrap();
+		let c_get = lib.get::<unsafe extern "C" fn() -> i32>(b"c_get").unwrap();
+		let c_release = lib.get::<unsafe extern "C" fn()>(b"c_release").unwrap();
+
+		// C releases the last reference
+		let value = DynArc::<dyn Value, _>::new_in(tracked(5, &DROPS), alloc);
+		c_retain(DynArc::as_raw(&value));
+		assert_eq!(DynArc::strong_count(&value), 2);
+		assert_eq!(c_get(), 5);
+
+		drop(value);
+		assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+		c_release();
+		assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+		assert_eq!(c_frees(), 1);
+
+		// C releases the last strong reference, and Rust the last weak reference
+		let value = DynArc::<dyn Value, _>::new_in(tracked(6, &DROPS), alloc);
+		let weak = DynArc::downgrade(&value);
+		c_retain(DynArc::as_raw(&value));
+		drop(value);
+		c_release();
+		assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+		assert_eq!(c_frees(), 1);
+
+		assert!(weak.upgrade().is_none());
+		drop(weak);
+		assert_eq!(c_frees(), 2);
+	}
+}
@@ -1,3 +1,5 @@
+use std::ptr::NonNull;
+
 use dyntable::{dyntable, DynBox, DynPtr, DynTrait, VTableRepr};
 
 /// OriginalBase is the original base trait of MainTrait.
@@ -52,7 +54,7 @@ fn reftable_updates() {
 
 	let dynb = DynBox::<dyn MainTrait>::new(TargetStruct);
 	let mut dynp = DynBox::into_raw(dynb);
-	dynp.vtable = &new_vt;
+	dynp.vtable = NonNull::from(&new_vt);
 	let dynb = unsafe { DynBox::from_raw(dynp) };
 	assert_eq!(dynb.basefn(), 0);
 	assert_eq!(dynb.mainfn(), 1);
@@ -61,7 +63,7 @@ fn reftable_updates() {
 	new_vt.__vtable_OriginalBase =
 		&updated_vt as *const _ as *const <dyn OriginalBase as VTableRepr>::VTable;
 	let mut dynp = DynBox::into_raw(dynb);
-	dynp.vtable = &new_vt;
+	dynp.vtable = NonNull::from(&new_vt);
 	let dynb = unsafe { DynBox::from_raw(dynp) };
 	assert_eq!(dynb.basefn(), 2);
 	assert_eq!(dynb.mainfn(), 1);
@@ -85,7 +87,9 @@ where
 }
 
 unsafe fn _ensure_inheritance(p: DynPtr<dyn EnsureInheritance2>) {
-	let _ptr: *const <dyn OriginalBase as VTableRepr>::VTable = (*p.vtable)
+	let _ptr: *const <dyn OriginalBase as VTableRepr>::VTable = p
+		.vtable
+		.as_ref()
 		.__vtable_EnsureInheritance
 		.__vtable_MainTrait
 		.__vtable_OriginalBase;
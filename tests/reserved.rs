@@ -0,0 +1,77 @@
+use std::{
+	mem::{offset_of, size_of},
+	ptr::NonNull,
+};
+
+use dyntable::*;
+
+// The first published version, leaving room for two more methods.
+mod v1 {
+	use dyntable::*;
+
+	#[dyntable(reserved = 2, vtable = PluginVTable)]
+	pub trait Plugin {
+		extern "C" fn id(&self) -> u32;
+	}
+}
+
+// A later version, which has taken one of the reserved slots.
+#[dyntable(reserved = 1)]
+trait Plugin {
+	extern "C" fn id(&self) -> u32;
+
+	#[dyntable(optional)]
+	extern "C" fn name(&self) -> u32 {
+		0
+	}
+}
+
+struct OldPlugin;
+
+impl v1::Plugin for OldPlugin {
+	extern "C" fn id(&self) -> u32 {
+		1
+	}
+}
+
+struct NewPlugin;
+
+impl Plugin for NewPlugin {
+	extern "C" fn id(&self) -> u32 {
+		2
+	}
+
+	extern "C" fn name(&self) -> u32 {
+		42
+	}
+}
+
+#[test]
+fn layout() {
+	assert_eq!(size_of::<v1::PluginVTable>(), size_of::<PluginVTable>());
+	assert_eq!(
+		offset_of!(v1::PluginVTable, __reserved),
+		offset_of!(PluginVTable, name)
+	);
+
+	let vtable = <NewPlugin as DynTrait<PluginVTable>>::VTABLE;
+	assert!(vtable.__reserved.iter().all(Option::is_none));
+}
+
+#[test]
+fn reserved_slots() {
+	let mut old = OldPlugin;
+	let ptr = DynPtr::<dyn Plugin> {
+		ptr: &mut old as *mut OldPlugin as *mut _,
+		vtable: NonNull::from(<OldPlugin as DynTrait<v1::PluginVTable>>::STATIC_VTABLE).cast(),
+	};
+
+	// reserved slots are null in older VTables
+	let plugin = unsafe { DynRef::from_raw(ptr) };
+	assert_eq!(plugin.id(), 1);
+	assert_eq!(plugin.try_name(), None);
+	assert_eq!(plugin.name(), 0);
+
+	let plugin = DynBox::<dyn Plugin>::new(NewPlugin);
+	assert_eq!(plugin.try_name(), Some(42));
+}
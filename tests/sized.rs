@@ -0,0 +1,48 @@
+use dyntable::*;
+
+#[dyntable]
+trait Counter {
+	extern "C" fn next(&mut self) -> u32;
+
+	fn new(start: u32) -> Self
+	where
+		Self: Sized;
+
+	fn take<T: Extend<u32>>(&mut self, n: usize, into: &mut T)
+	where
+		Self: Sized,
+	{
+		into.extend((0..n).map(|_| self.next()));
+	}
+}
+
+#[repr(C)]
+struct Step(u32);
+
+impl Counter for Step {
+	extern "C" fn next(&mut self) -> u32 {
+		self.0 += 1;
+		self.0
+	}
+
+	fn new(start: u32) -> Self {
+		Self(start)
+	}
+}
+
+#[test]
+fn sized_methods() {
+	let mut step = Step::new(2);
+	let mut values = Vec::new();
+	step.take(3, &mut values);
+	assert_eq!(values, [3, 4, 5]);
+
+	// sized methods take no space in the VTable
+	assert_eq!(
+		core::mem::size_of::<CounterVTable>(),
+		core::mem::size_of::<usize>() * 4,
+	);
+
+	let mut counter = DynBox::<dyn Counter>::new(step);
+	assert_eq!(counter.next(), 6);
+}
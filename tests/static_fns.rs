@@ -0,0 +1,62 @@
+// `DynBox` with the global allocator is not marked FFI safe
+#![allow(improper_ctypes_definitions)]
+
+use dyntable::*;
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+struct PluginInfo {
+	id: u32,
+	version: u32,
+}
+
+#[dyntable]
+trait Plugin {
+	extern "C" fn describe() -> PluginInfo;
+
+	extern "C" fn create(id: u32) -> DynBox<dyn Plugin>;
+
+	extern "C" fn revision() -> u32 {
+		0
+	}
+
+	extern "C" fn id(&self) -> u32;
+}
+
+#[repr(C)]
+struct Echo(u32);
+
+impl Plugin for Echo {
+	extern "C" fn describe() -> PluginInfo {
+		PluginInfo { id: 0, version: 3 }
+	}
+
+	extern "C" fn create(id: u32) -> DynBox<dyn Plugin> {
+		DynBox::new(Echo(id))
+	}
+
+	extern "C" fn id(&self) -> u32 {
+		self.0
+	}
+}
+
+#[test]
+fn static_functions() {
+	assert_eq!(Echo::describe(), PluginInfo { id: 0, version: 3 });
+
+	let plugin = DynBox::<dyn Plugin>::new(Echo(1));
+	let vtable = DynBox::vtable(&plugin);
+	assert_eq!(vtable.describe(), PluginInfo { id: 0, version: 3 });
+	assert_eq!(vtable.revision(), 0);
+
+	let created = vtable.create(5);
+	assert_eq!(created.id(), 5);
+	assert_eq!(DynRef::vtable(&DynBox::borrow(&created)).create(6).id(), 6);
+}
+
+#[test]
+fn bare_vtable() {
+	let vtable: &PluginVTable = <Echo as DynTrait<PluginVTable>>::STATIC_VTABLE;
+	assert_eq!((vtable.create)(2).id(), 2);
+	assert_eq!(vtable.describe().version, 3);
+}
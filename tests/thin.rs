@@ -0,0 +1,187 @@
+use std::{
+	ffi::c_void,
+	mem,
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+use common::{tracked, Aligned, Empty, Tracked};
+use dyntable::{
+	dyntable,
+	thin::{ThinBox, ThinPtr, ThinRef, ThinRefMut},
+	DynBox,
+	DynRef,
+};
+
+mod common;
+
+#[dyntable]
+trait Named {
+	extern "C" fn name(&self) -> u32;
+}
+
+#[dyntable]
+trait Value: Named
+where
+	dyn Named:,
+{
+	extern "C" fn get(&self) -> u32;
+	extern "C" fn set(&mut self, value: u32);
+	extern "C" fn take(self) -> u32;
+}
+
+#[dyntable]
+trait Referenced: Named
+where
+	&dyn Named:,
+{
+	extern "C" fn get(&self) -> u32;
+}
+
+impl Named for Tracked {
+	extern "C" fn name(&self) -> u32 {
+		1
+	}
+}
+
+impl Value for Tracked {
+	extern "C" fn get(&self) -> u32 {
+		self.value
+	}
+
+	extern "C" fn set(&mut self, value: u32) {
+		self.value = value;
+	}
+
+	extern "C" fn take(self) -> u32 {
+		self.value
+	}
+}
+
+impl Referenced for Tracked {
+	extern "C" fn get(&self) -> u32 {
+		self.value
+	}
+}
+
+impl Named for Aligned {
+	extern "C" fn name(&self) -> u32 {
+		self.0
+	}
+}
+
+impl Named for Empty {
+	extern "C" fn name(&self) -> u32 {
+		0
+	}
+}
+
+#[test]
+fn layout() {
+	assert_eq!(
+		mem::size_of::<ThinPtr<dyn Value>>(),
+		mem::size_of::<*const c_void>()
+	);
+	assert_eq!(
+		mem::size_of::<ThinRef<dyn Value>>(),
+		mem::size_of::<*const c_void>()
+	);
+	assert_eq!(
+		mem::size_of::<ThinRefMut<dyn Value>>(),
+		mem::size_of::<*const c_void>()
+	);
+	assert_eq!(
+		mem::size_of::<ThinBox<dyn Value>>(),
+		mem::size_of::<*const c_void>()
+	);
+}
+
+#[test]
+fn calls() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let mut thin = ThinBox::<dyn Value>::new(tracked(42, &DROPS));
+	assert_eq!(thin.name(), 1);
+	thin.set(thin.get() + 1);
+
+	{
+		let mut r = ThinBox::borrow_mut(&mut thin);
+		let value = r.get();
+		r.set(value + 1);
+		assert_eq!(ThinRefMut::borrow(&r).get(), 44);
+	}
+
+	assert_eq!(ThinBox::borrow(&thin).get(), 44);
+	assert_eq!(DynRef::from(ThinBox::borrow(&thin)).get(), 44);
+	assert_eq!(thin.take(), 44);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+	drop(ThinBox::<dyn Value>::new(tracked(0, &DROPS)));
+	assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn upcast() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let thin = ThinBox::<dyn Value>::new(tracked(1, &DROPS));
+	assert_eq!(
+		ThinRef::upcast::<dyn Named>(ThinBox::borrow(&thin)).name(),
+		1
+	);
+
+	let named: ThinBox<dyn Named> = ThinBox::upcast(thin);
+	assert_eq!(named.name(), 1);
+	drop(named);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+	// referenced subtables are not stored inline with the VTable
+	let thin = ThinBox::<dyn Referenced>::new(tracked(2, &DROPS));
+	let named: ThinBox<dyn Named> = ThinBox::upcast(thin);
+	assert_eq!(named.name(), 1);
+	drop(named);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn dynbox_conversion() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let dynbox = DynBox::<dyn Value>::new(tracked(3, &DROPS));
+	let thin = ThinBox::from(dynbox);
+	assert_eq!(thin.get(), 3);
+
+	let dynbox = DynBox::from(thin);
+	assert_eq!(dynbox.get(), 3);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+	drop(dynbox);
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+	let thin = ThinBox::from(DynBox::<dyn Named>::new(Aligned(6)));
+	assert_eq!(thin.name(), 6);
+	assert_eq!(DynBox::from(thin).name(), 6);
+
+	let thin = ThinBox::from(DynBox::<dyn Named>::new(Empty));
+	assert_eq!(DynBox::from(thin).name(), 0);
+}
+
+extern "C" fn callback(data: *mut c_void) -> u32 {
+	let value = unsafe { ThinRef::<dyn Value>::from_raw(ThinPtr::from_raw(data)) };
+	value.get()
+}
+
+#[test]
+fn void_ptr() {
+	static DROPS: AtomicU32 = AtomicU32::new(0);
+
+	let thin = ThinBox::<dyn Value>::new(tracked(7, &DROPS));
+	let ptr = ThinBox::into_raw(thin);
+	assert_eq!(callback(ptr.ptr), 7);
+
+	drop(unsafe { ThinBox::<dyn Value>::from_raw(ptr) });
+	assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+	// the VTable is stored before the value without moving it out of alignment
+	let ptr = ThinBox::into_raw(ThinBox::<dyn Named>::new(Aligned(5)));
+	assert_eq!(ptr.ptr as usize % 64, 0);
+	drop(unsafe { ThinBox::<dyn Named>::from_raw(ptr) });
+}